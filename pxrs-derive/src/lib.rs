@@ -0,0 +1,140 @@
+// `#[derive(PxRecord)]` for structs whose fields map onto the columns of a
+// Paradox table. Field attributes:
+//
+//   #[px(name = "Customer No")]  column name, defaults to the field name
+//   #[px(field_type = 0x01)]     field type used when creating a table
+//   #[px(size = 40)]             field size used when creating a table
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields, LitStr};
+
+#[proc_macro_derive(PxRecord, attributes(px))]
+pub fn derive_px_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct Column {
+    ident: syn::Ident,
+    ty: syn::Type,
+    name: String,
+    field_type: Option<Expr>,
+    size: Option<Expr>,
+}
+
+fn parse_column(field: &syn::Field) -> syn::Result<Column> {
+    let ident = field.ident.clone().expect("named field");
+    let mut column = Column {
+        name: ident.to_string(),
+        ident,
+        ty: field.ty.clone(),
+        field_type: None,
+        size: None,
+    };
+
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("px")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                column.name = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("field_type") {
+                column.field_type = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("size") {
+                column.size = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error("expected `name`, `field_type` or `size`"));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(column)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "PxRecord needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "PxRecord can only be derived for structs",
+            ))
+        }
+    };
+
+    let columns = fields
+        .iter()
+        .map(parse_column)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let descriptions = columns.iter().map(|c| {
+        let Column { ty, name, .. } = c;
+        let field_type = match &c.field_type {
+            Some(expr) => quote!(#expr),
+            None => quote!(<#ty as ::pxrs::typed::PxValue>::FIELD_TYPE),
+        };
+        let size = match &c.size {
+            Some(expr) => quote!(#expr),
+            None => quote!(<#ty as ::pxrs::typed::PxValue>::SIZE),
+        };
+
+        quote! {
+            ::pxrs::typed::Column {
+                name: #name,
+                field_type: #field_type,
+                size: #size,
+                accepts: <#ty as ::pxrs::typed::PxValue>::accepts,
+                writes: <#ty as ::pxrs::typed::PxValue>::writes,
+            }
+        }
+    });
+
+    let decoders = columns.iter().map(|c| {
+        let Column {
+            ident, ty, name, ..
+        } = c;
+        quote! {
+            #ident: ::pxrs::typed::decode_column::<#ty>(&mut values, #name)?
+        }
+    });
+
+    let encoders = columns.iter().map(|c| {
+        let Column {
+            ident, ty, name, ..
+        } = c;
+        quote!(::pxrs::typed::encode_column::<#ty>(&self.#ident, #name)?)
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::pxrs::typed::PxRecord for #ident #ty_generics #where_clause {
+            const COLUMNS: &'static [::pxrs::typed::Column] = &[#(#descriptions),*];
+
+            fn decode(
+                values: ::std::vec::Vec<::pxrs::typed::Value>,
+            ) -> ::pxrs::typed::Result<Self> {
+                let mut values = values.into_iter();
+                ::std::result::Result::Ok(#ident {
+                    #(#decoders),*
+                })
+            }
+
+            fn encode(&self) -> ::pxrs::typed::Result<::std::vec::Vec<::pxrs::typed::Value>> {
+                ::std::result::Result::Ok(::std::vec![#(#encoders),*])
+            }
+        }
+    })
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+use crate::diff::Row;
+use crate::error::{Error, Result};
+use crate::fingerprint::checksum;
+use crate::table::{Record, Table};
+use crate::types::PX_FILETYPE_DB_INDEXED;
+
+// What change data capture remembers of a table between runs: a hash of
+// the value of every field of every record, by primary key. Values are
+// hashed rather than bytes as the same value can be stored in more than
+// one way. Blobs count as changed when their pointer does.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CdcState {
+    // Names of the fields the hashes are given for
+    pub fields: Vec<String>,
+    // The key of each record, as the JSON object of its key fields
+    pub records: BTreeMap<String, Vec<u64>>,
+}
+
+// A change to a record since the previous run. Inserts carry the whole
+// record, updates the fields that changed, deletes only the key.
+#[derive(Debug, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Event {
+    Insert { key: Row, record: Row },
+    Update { key: Row, fields: Row },
+    Delete { key: Box<RawValue> },
+}
+
+fn json_error(e: serde_json::Error) -> Error {
+    Error::value(e.to_string())
+}
+
+fn row(record: &Record, names: &[String], indexes: impl Iterator<Item = usize>) -> Result<Row> {
+    let values = indexes
+        .map(|i| Ok((names[i].clone(), record.fetch(i)?)))
+        .collect::<Result<_>>()?;
+    Ok(Row(values))
+}
+
+// Compares the records of a keyed table with the state of the previous
+// run, passing every change to `emit` as it is found and the deletes at
+// the end. Returns the state to pass to the next run, an empty state
+// reports every record as inserted. Fields that are new since the
+// previous run count as changed.
+pub fn capture(
+    table: &Table,
+    previous: &CdcState,
+    mut emit: impl FnMut(Event) -> Result<()>,
+) -> Result<CdcState> {
+    let header = table.header();
+    if header.file_type != PX_FILETYPE_DB_INDEXED || header.primary_key_fields <= 0 {
+        return Err(Error::invalid("Change data capture needs a keyed table"));
+    }
+    let keys = header.primary_key_fields as usize;
+
+    let names: Vec<String> = table.fields().iter().map(|f| f.name.clone()).collect();
+    let previous_index: Vec<Option<usize>> = names
+        .iter()
+        .map(|name| previous.fields.iter().position(|p| p == name))
+        .collect();
+
+    let mut state = CdcState {
+        fields: names.clone(),
+        records: BTreeMap::new(),
+    };
+
+    for record in table.records() {
+        let record = record?;
+        let hashes = (0..names.len())
+            .map(|i| {
+                let value = serde_json::to_string(&record.get(i)?).map_err(json_error)?;
+                Ok(checksum(value.as_bytes()))
+            })
+            .collect::<Result<Vec<u64>>>()?;
+
+        let key = row(&record, &names, 0..keys)?;
+        let id = key.id()?;
+
+        // Checked before anything is emitted for the record
+        if state.records.contains_key(&id) {
+            let error = Error::corrupt("Duplicate key")
+                .at_block(record.block())
+                .at_record(record.slot());
+            return Err(match table.path() {
+                Some(path) => error.in_file(path),
+                None => error,
+            });
+        }
+
+        match previous.records.get(&id) {
+            None => emit(Event::Insert {
+                key,
+                record: row(&record, &names, 0..names.len())?,
+            })?,
+            Some(old) => {
+                let changed: Vec<usize> = (0..names.len())
+                    .filter(|&i| previous_index[i].and_then(|p| old.get(p)) != Some(&hashes[i]))
+                    .collect();
+                if !changed.is_empty() {
+                    emit(Event::Update {
+                        key,
+                        fields: row(&record, &names, changed.into_iter())?,
+                    })?;
+                }
+            }
+        }
+
+        state.records.insert(id, hashes);
+    }
+
+    for id in previous.records.keys() {
+        if !state.records.contains_key(id) {
+            let key = RawValue::from_string(id.clone()).map_err(json_error)?;
+            emit(Event::Delete { key })?;
+        }
+    }
+
+    Ok(state)
+}
@@ -0,0 +1,172 @@
+use crate::error::{Error, Result};
+use crate::index::PrimaryIndex;
+use crate::table::{companion_path, Table};
+use crate::types::PX_FILETYPE_DB_INDEXED;
+
+// Verifies the structure of a table: the block chain, the record count,
+// that every field decodes (blobs included), the key order of keyed
+// tables and that the primary index matches the data blocks. Problems are
+// returned as errors locating them, an empty list means the table is
+// sound. Errors reading the files at all are returned as such.
+pub fn check_table(table: &Table) -> Result<Vec<Error>> {
+    let header = table.header();
+    let mut problems = vec![];
+    let locate = |e: Error| match table.path() {
+        Some(path) => e.in_file(path),
+        None => e,
+    };
+
+    // Block chain, its back links and the records in it
+    let mut blocks = vec![];
+    let mut prev = 0;
+    for block in table.blocks() {
+        let block = match block {
+            Ok(block) => block,
+            Err(e) => {
+                problems.push(e);
+                break;
+            }
+        };
+        if block.prev_block != prev {
+            problems.push(locate(
+                Error::corrupt(format!(
+                    "Block links back to block {} instead of {}",
+                    block.prev_block, prev
+                ))
+                .at_block(block.number),
+            ));
+        }
+        prev = block.number;
+        blocks.push(block);
+    }
+
+    if blocks.len() != header.used_blocks as usize {
+        problems.push(locate(Error::corrupt(format!(
+            "The header counts {} used blocks, the chain has {}",
+            header.used_blocks,
+            blocks.len()
+        ))));
+    }
+    if prev != header.last_block {
+        problems.push(locate(Error::corrupt(format!(
+            "The header names block {} as last, the chain ends with block {}",
+            header.last_block, prev
+        ))));
+    }
+
+    let records: usize = blocks.iter().map(|b| b.num_recs_in_block).sum();
+    if records != header.num_records as usize {
+        problems.push(locate(Error::corrupt(format!(
+            "The header counts {} records, the blocks hold {}",
+            header.num_records, records
+        ))));
+    }
+
+    let keys = match header.file_type {
+        PX_FILETYPE_DB_INDEXED => header.primary_key_fields.max(0) as usize,
+        _ => 0,
+    };
+    let mut previous_key: Option<Vec<_>> = None;
+
+    for block in &blocks {
+        for slot in 0..block.num_recs_in_block {
+            let Some(record) = table.record(block, slot) else {
+                continue;
+            };
+
+            let mut key = vec![];
+            for i in 0..table.fields().len() {
+                match record.fetch(i) {
+                    Ok(value) if i < keys => key.push(value),
+                    Ok(_) => {}
+                    Err(e) => problems.push(e),
+                }
+            }
+            if keys == 0 || key.len() < keys {
+                continue;
+            }
+
+            if let Some(previous) = &previous_key {
+                let mut ordering = std::cmp::Ordering::Equal;
+                for (a, b) in previous.iter().zip(&key) {
+                    ordering = table.collation().key_order(a, b)?;
+                    if ordering.is_ne() {
+                        break;
+                    }
+                }
+                if ordering.is_ge() {
+                    let problem = match ordering.is_eq() {
+                        true => "Duplicate key",
+                        false => "Key out of order",
+                    };
+                    problems.push(locate(
+                        Error::corrupt(problem)
+                            .at_block(block.number)
+                            .at_record(slot),
+                    ));
+                }
+            }
+            previous_key = Some(key);
+        }
+    }
+
+    if keys > 0 {
+        problems.extend(check_index(table, &blocks)?);
+    }
+
+    Ok(problems)
+}
+
+// Compares the leaf entries of the .PX file with the data blocks
+fn check_index(table: &Table, blocks: &[crate::types::PxBlocks]) -> Result<Vec<Error>> {
+    let Some(path) = table.path().and_then(|p| companion_path(p, "PX")) else {
+        return Ok(vec![]);
+    };
+    let px = Table::open(&path)?;
+    let locate = |e: Error| e.in_file(&path);
+
+    let entries = match PrimaryIndex::from_table(px).and_then(|index| index.entries()) {
+        Ok(entries) => entries,
+        Err(e) => return Ok(vec![locate(e)]),
+    };
+
+    let mut problems = vec![];
+    if entries.len() != blocks.len() {
+        problems.push(locate(Error::corrupt(format!(
+            "The index lists {} data blocks, the table has {}",
+            entries.len(),
+            blocks.len()
+        ))));
+    }
+
+    for (entry, block) in entries.iter().zip(blocks) {
+        if entry.block != block.number {
+            problems.push(locate(Error::corrupt(format!(
+                "The index lists block {} where the chain has block {}",
+                entry.block, block.number
+            ))));
+            break;
+        }
+        if entry.count as usize != block.num_recs_in_block {
+            problems.push(locate(
+                Error::corrupt(format!(
+                    "The index counts {} records, the block holds {}",
+                    entry.count, block.num_recs_in_block
+                ))
+                .at_block(block.number),
+            ));
+        }
+
+        let first = table.record(block, 0).map(|r| r.values()).transpose()?;
+        let matches =
+            first.is_some_and(|values| values.get(..entry.key.len()) == Some(&entry.key[..]));
+        if !matches {
+            problems.push(locate(
+                Error::corrupt("The index key differs from the first key of the block")
+                    .at_block(block.number),
+            ));
+        }
+    }
+
+    Ok(problems)
+}
@@ -0,0 +1,268 @@
+use std::cmp::Ordering;
+
+use crate::error::Result;
+use crate::expr::compare_values;
+use crate::types::Value;
+
+// The order Paradox sorts Alpha values in, chosen by the sort order byte
+// of the header. Like Paradox, every sort order is a table giving each
+// byte of its code page a weight: letters sort by their base letter first,
+// then by accent, then lower case before upper case. Other characters keep
+// their byte order and sort before the letters. ASCII sorts by byte value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Collation {
+    #[default]
+    Ascii,
+    International,
+    NorwegianDanish,
+    NorwegianDanish40,
+    SwedishFinnish,
+    Spanish,
+    AnsiInternational,
+}
+
+// Weights of the bytes of a code page: the letter, or the byte itself for
+// other characters, shifted left by 5, the accent shifted left by 1 and 1
+// for upper case letters. Letters weigh 0x100 and up in alphabet order,
+// so they follow every other character.
+type Weights = [u16; 256];
+
+fn primary(weight: u16) -> u16 {
+    weight >> 5
+}
+
+fn accent(weight: u16) -> u16 {
+    weight >> 1 & 0xf
+}
+
+fn case(weight: u16) -> u16 {
+    weight & 1
+}
+
+// One level of the weights of an Alpha value, which holds the bytes of the
+// file as chars
+fn weights_of<'a>(
+    s: &'a str,
+    weights: &'a Weights,
+    level: fn(u16) -> u16,
+) -> impl Iterator<Item = u16> + 'a {
+    s.chars()
+        .map(move |c| level(weights[u8::try_from(c).unwrap_or(0xff) as usize]))
+}
+
+// Paradox 'intl', code page 437
+const INTERNATIONAL: Weights = [
+    0x0000, 0x0020, 0x0040, 0x0060, 0x0080, 0x00a0, 0x00c0, 0x00e0, 0x0100, 0x0120, 0x0140, 0x0160,
+    0x0180, 0x01a0, 0x01c0, 0x01e0, 0x0200, 0x0220, 0x0240, 0x0260, 0x0280, 0x02a0, 0x02c0, 0x02e0,
+    0x0300, 0x0320, 0x0340, 0x0360, 0x0380, 0x03a0, 0x03c0, 0x03e0, 0x0400, 0x0420, 0x0440, 0x0460,
+    0x0480, 0x04a0, 0x04c0, 0x04e0, 0x0500, 0x0520, 0x0540, 0x0560, 0x0580, 0x05a0, 0x05c0, 0x05e0,
+    0x0600, 0x0620, 0x0640, 0x0660, 0x0680, 0x06a0, 0x06c0, 0x06e0, 0x0700, 0x0720, 0x0740, 0x0760,
+    0x0780, 0x07a0, 0x07c0, 0x07e0, 0x0800, 0x2001, 0x2021, 0x2041, 0x2061, 0x2081, 0x20a1, 0x20c1,
+    0x20e1, 0x2101, 0x2121, 0x2141, 0x2161, 0x2181, 0x21a1, 0x21c1, 0x21e1, 0x2201, 0x2221, 0x2241,
+    0x2261, 0x2281, 0x22a1, 0x22c1, 0x22e1, 0x2301, 0x2321, 0x0b60, 0x0b80, 0x0ba0, 0x0bc0, 0x0be0,
+    0x0c00, 0x2000, 0x2020, 0x2040, 0x2060, 0x2080, 0x20a0, 0x20c0, 0x20e0, 0x2100, 0x2120, 0x2140,
+    0x2160, 0x2180, 0x21a0, 0x21c0, 0x21e0, 0x2200, 0x2220, 0x2240, 0x2260, 0x2280, 0x22a0, 0x22c0,
+    0x22e0, 0x2300, 0x2320, 0x0f60, 0x0f80, 0x0fa0, 0x0fc0, 0x0fe0, 0x2043, 0x2288, 0x2082, 0x2006,
+    0x2008, 0x2004, 0x200c, 0x2042, 0x2086, 0x2088, 0x2084, 0x2108, 0x2106, 0x2104, 0x2009, 0x200d,
+    0x2083, 0x200e, 0x200f, 0x21c6, 0x21c8, 0x21c4, 0x2286, 0x2284, 0x2304, 0x21c9, 0x2289, 0x1360,
+    0x1380, 0x13a0, 0x13c0, 0x13e0, 0x2002, 0x2102, 0x21c2, 0x2282, 0x21a2, 0x21a3, 0x14c0, 0x14e0,
+    0x1500, 0x1520, 0x1540, 0x1560, 0x1580, 0x15a0, 0x15c0, 0x15e0, 0x1600, 0x1620, 0x1640, 0x1660,
+    0x1680, 0x16a0, 0x16c0, 0x16e0, 0x1700, 0x1720, 0x1740, 0x1760, 0x1780, 0x17a0, 0x17c0, 0x17e0,
+    0x1800, 0x1820, 0x1840, 0x1860, 0x1880, 0x18a0, 0x18c0, 0x18e0, 0x1900, 0x1920, 0x1940, 0x1960,
+    0x1980, 0x19a0, 0x19c0, 0x19e0, 0x1a00, 0x1a20, 0x1a40, 0x1a60, 0x1a80, 0x1aa0, 0x1ac0, 0x1ae0,
+    0x1b00, 0x1b20, 0x1b40, 0x1b60, 0x1b80, 0x1ba0, 0x1bc0, 0x1be0, 0x1c00, 0x225e, 0x1c40, 0x1c60,
+    0x1c80, 0x1ca0, 0x1cc0, 0x1ce0, 0x1d00, 0x1d20, 0x1d40, 0x1d60, 0x1d80, 0x1da0, 0x1dc0, 0x1de0,
+    0x1e00, 0x1e20, 0x1e40, 0x1e60, 0x1e80, 0x1ea0, 0x1ec0, 0x1ee0, 0x1f00, 0x1f20, 0x1f40, 0x1f60,
+    0x1f80, 0x1fa0, 0x1fc0, 0x1fe0,
+];
+
+// Paradox 'nordan' and 'nordan40', code page 865: Æ, Ø and Å follow Z
+const NORWEGIAN_DANISH: Weights = [
+    0x0000, 0x0020, 0x0040, 0x0060, 0x0080, 0x00a0, 0x00c0, 0x00e0, 0x0100, 0x0120, 0x0140, 0x0160,
+    0x0180, 0x01a0, 0x01c0, 0x01e0, 0x0200, 0x0220, 0x0240, 0x0260, 0x0280, 0x02a0, 0x02c0, 0x02e0,
+    0x0300, 0x0320, 0x0340, 0x0360, 0x0380, 0x03a0, 0x03c0, 0x03e0, 0x0400, 0x0420, 0x0440, 0x0460,
+    0x0480, 0x04a0, 0x04c0, 0x04e0, 0x0500, 0x0520, 0x0540, 0x0560, 0x0580, 0x05a0, 0x05c0, 0x05e0,
+    0x0600, 0x0620, 0x0640, 0x0660, 0x0680, 0x06a0, 0x06c0, 0x06e0, 0x0700, 0x0720, 0x0740, 0x0760,
+    0x0780, 0x07a0, 0x07c0, 0x07e0, 0x0800, 0x2001, 0x2021, 0x2041, 0x2061, 0x2081, 0x20a1, 0x20c1,
+    0x20e1, 0x2101, 0x2121, 0x2141, 0x2161, 0x2181, 0x21a1, 0x21c1, 0x21e1, 0x2201, 0x2221, 0x2241,
+    0x2261, 0x2281, 0x22a1, 0x22c1, 0x22e1, 0x2301, 0x2321, 0x0b60, 0x0b80, 0x0ba0, 0x0bc0, 0x0be0,
+    0x0c00, 0x2000, 0x2020, 0x2040, 0x2060, 0x2080, 0x20a0, 0x20c0, 0x20e0, 0x2100, 0x2120, 0x2140,
+    0x2160, 0x2180, 0x21a0, 0x21c0, 0x21e0, 0x2200, 0x2220, 0x2240, 0x2260, 0x2280, 0x22a0, 0x22c0,
+    0x22e0, 0x2300, 0x2320, 0x0f60, 0x0f80, 0x0fa0, 0x0fc0, 0x0fe0, 0x2043, 0x2306, 0x2082, 0x2006,
+    0x2342, 0x2004, 0x2380, 0x2042, 0x2086, 0x2088, 0x2084, 0x2108, 0x2106, 0x2104, 0x2343, 0x2381,
+    0x2083, 0x2340, 0x2341, 0x21c6, 0x2362, 0x21c4, 0x2286, 0x2284, 0x2304, 0x2363, 0x2307, 0x2360,
+    0x1380, 0x2361, 0x13c0, 0x13e0, 0x2002, 0x2102, 0x21c2, 0x2282, 0x21a2, 0x21a3, 0x14c0, 0x14e0,
+    0x1500, 0x1520, 0x1540, 0x1560, 0x1580, 0x15a0, 0x15c0, 0x15e0, 0x1600, 0x1620, 0x1640, 0x1660,
+    0x1680, 0x16a0, 0x16c0, 0x16e0, 0x1700, 0x1720, 0x1740, 0x1760, 0x1780, 0x17a0, 0x17c0, 0x17e0,
+    0x1800, 0x1820, 0x1840, 0x1860, 0x1880, 0x18a0, 0x18c0, 0x18e0, 0x1900, 0x1920, 0x1940, 0x1960,
+    0x1980, 0x19a0, 0x19c0, 0x19e0, 0x1a00, 0x1a20, 0x1a40, 0x1a60, 0x1a80, 0x1aa0, 0x1ac0, 0x1ae0,
+    0x1b00, 0x1b20, 0x1b40, 0x1b60, 0x1b80, 0x1ba0, 0x1bc0, 0x1be0, 0x1c00, 0x225e, 0x1c40, 0x1c60,
+    0x1c80, 0x1ca0, 0x1cc0, 0x1ce0, 0x1d00, 0x1d20, 0x1d40, 0x1d60, 0x1d80, 0x1da0, 0x1dc0, 0x1de0,
+    0x1e00, 0x1e20, 0x1e40, 0x1e60, 0x1e80, 0x1ea0, 0x1ec0, 0x1ee0, 0x1f00, 0x1f20, 0x1f40, 0x1f60,
+    0x1f80, 0x1fa0, 0x1fc0, 0x1fe0,
+];
+
+// Paradox 'swedfin', code page 437: Å, Ä and Ö follow Z, Ü sorts as Y
+const SWEDISH_FINNISH: Weights = [
+    0x0000, 0x0020, 0x0040, 0x0060, 0x0080, 0x00a0, 0x00c0, 0x00e0, 0x0100, 0x0120, 0x0140, 0x0160,
+    0x0180, 0x01a0, 0x01c0, 0x01e0, 0x0200, 0x0220, 0x0240, 0x0260, 0x0280, 0x02a0, 0x02c0, 0x02e0,
+    0x0300, 0x0320, 0x0340, 0x0360, 0x0380, 0x03a0, 0x03c0, 0x03e0, 0x0400, 0x0420, 0x0440, 0x0460,
+    0x0480, 0x04a0, 0x04c0, 0x04e0, 0x0500, 0x0520, 0x0540, 0x0560, 0x0580, 0x05a0, 0x05c0, 0x05e0,
+    0x0600, 0x0620, 0x0640, 0x0660, 0x0680, 0x06a0, 0x06c0, 0x06e0, 0x0700, 0x0720, 0x0740, 0x0760,
+    0x0780, 0x07a0, 0x07c0, 0x07e0, 0x0800, 0x2001, 0x2021, 0x2041, 0x2061, 0x2081, 0x20a1, 0x20c1,
+    0x20e1, 0x2101, 0x2121, 0x2141, 0x2161, 0x2181, 0x21a1, 0x21c1, 0x21e1, 0x2201, 0x2221, 0x2241,
+    0x2261, 0x2281, 0x22a1, 0x22c1, 0x22e1, 0x2301, 0x2321, 0x0b60, 0x0b80, 0x0ba0, 0x0bc0, 0x0be0,
+    0x0c00, 0x2000, 0x2020, 0x2040, 0x2060, 0x2080, 0x20a0, 0x20c0, 0x20e0, 0x2100, 0x2120, 0x2140,
+    0x2160, 0x2180, 0x21a0, 0x21c0, 0x21e0, 0x2200, 0x2220, 0x2240, 0x2260, 0x2280, 0x22a0, 0x22c0,
+    0x22e0, 0x2300, 0x2320, 0x0f60, 0x0f80, 0x0fa0, 0x0fc0, 0x0fe0, 0x2043, 0x2306, 0x2082, 0x2006,
+    0x2360, 0x2004, 0x2340, 0x2042, 0x2086, 0x2088, 0x2084, 0x2108, 0x2106, 0x2104, 0x2361, 0x2341,
+    0x2083, 0x2362, 0x2363, 0x21c6, 0x2380, 0x21c4, 0x2286, 0x2284, 0x2304, 0x2381, 0x2307, 0x1360,
+    0x1380, 0x13a0, 0x13c0, 0x13e0, 0x2002, 0x2102, 0x21c2, 0x2282, 0x21a2, 0x21a3, 0x14c0, 0x14e0,
+    0x1500, 0x1520, 0x1540, 0x1560, 0x1580, 0x15a0, 0x15c0, 0x15e0, 0x1600, 0x1620, 0x1640, 0x1660,
+    0x1680, 0x16a0, 0x16c0, 0x16e0, 0x1700, 0x1720, 0x1740, 0x1760, 0x1780, 0x17a0, 0x17c0, 0x17e0,
+    0x1800, 0x1820, 0x1840, 0x1860, 0x1880, 0x18a0, 0x18c0, 0x18e0, 0x1900, 0x1920, 0x1940, 0x1960,
+    0x1980, 0x19a0, 0x19c0, 0x19e0, 0x1a00, 0x1a20, 0x1a40, 0x1a60, 0x1a80, 0x1aa0, 0x1ac0, 0x1ae0,
+    0x1b00, 0x1b20, 0x1b40, 0x1b60, 0x1b80, 0x1ba0, 0x1bc0, 0x1be0, 0x1c00, 0x225e, 0x1c40, 0x1c60,
+    0x1c80, 0x1ca0, 0x1cc0, 0x1ce0, 0x1d00, 0x1d20, 0x1d40, 0x1d60, 0x1d80, 0x1da0, 0x1dc0, 0x1de0,
+    0x1e00, 0x1e20, 0x1e40, 0x1e60, 0x1e80, 0x1ea0, 0x1ec0, 0x1ee0, 0x1f00, 0x1f20, 0x1f40, 0x1f60,
+    0x1f80, 0x1fa0, 0x1fc0, 0x1fe0,
+];
+
+// Paradox 'spanish', code page 437: Ñ follows N
+const SPANISH: Weights = [
+    0x0000, 0x0020, 0x0040, 0x0060, 0x0080, 0x00a0, 0x00c0, 0x00e0, 0x0100, 0x0120, 0x0140, 0x0160,
+    0x0180, 0x01a0, 0x01c0, 0x01e0, 0x0200, 0x0220, 0x0240, 0x0260, 0x0280, 0x02a0, 0x02c0, 0x02e0,
+    0x0300, 0x0320, 0x0340, 0x0360, 0x0380, 0x03a0, 0x03c0, 0x03e0, 0x0400, 0x0420, 0x0440, 0x0460,
+    0x0480, 0x04a0, 0x04c0, 0x04e0, 0x0500, 0x0520, 0x0540, 0x0560, 0x0580, 0x05a0, 0x05c0, 0x05e0,
+    0x0600, 0x0620, 0x0640, 0x0660, 0x0680, 0x06a0, 0x06c0, 0x06e0, 0x0700, 0x0720, 0x0740, 0x0760,
+    0x0780, 0x07a0, 0x07c0, 0x07e0, 0x0800, 0x2001, 0x2021, 0x2041, 0x2061, 0x2081, 0x20a1, 0x20c1,
+    0x20e1, 0x2101, 0x2121, 0x2141, 0x2161, 0x2181, 0x21a1, 0x21e1, 0x2201, 0x2221, 0x2241, 0x2261,
+    0x2281, 0x22a1, 0x22c1, 0x22e1, 0x2301, 0x2321, 0x2341, 0x0b60, 0x0b80, 0x0ba0, 0x0bc0, 0x0be0,
+    0x0c00, 0x2000, 0x2020, 0x2040, 0x2060, 0x2080, 0x20a0, 0x20c0, 0x20e0, 0x2100, 0x2120, 0x2140,
+    0x2160, 0x2180, 0x21a0, 0x21e0, 0x2200, 0x2220, 0x2240, 0x2260, 0x2280, 0x22a0, 0x22c0, 0x22e0,
+    0x2300, 0x2320, 0x2340, 0x0f60, 0x0f80, 0x0fa0, 0x0fc0, 0x0fe0, 0x2043, 0x22a8, 0x2082, 0x2006,
+    0x2008, 0x2004, 0x200c, 0x2042, 0x2086, 0x2088, 0x2084, 0x2108, 0x2106, 0x2104, 0x2009, 0x200d,
+    0x2083, 0x200e, 0x200f, 0x21e6, 0x21e8, 0x21e4, 0x22a6, 0x22a4, 0x2324, 0x21e9, 0x22a9, 0x1360,
+    0x1380, 0x13a0, 0x13c0, 0x13e0, 0x2002, 0x2102, 0x21e2, 0x22a2, 0x21c0, 0x21c1, 0x14c0, 0x14e0,
+    0x1500, 0x1520, 0x1540, 0x1560, 0x1580, 0x15a0, 0x15c0, 0x15e0, 0x1600, 0x1620, 0x1640, 0x1660,
+    0x1680, 0x16a0, 0x16c0, 0x16e0, 0x1700, 0x1720, 0x1740, 0x1760, 0x1780, 0x17a0, 0x17c0, 0x17e0,
+    0x1800, 0x1820, 0x1840, 0x1860, 0x1880, 0x18a0, 0x18c0, 0x18e0, 0x1900, 0x1920, 0x1940, 0x1960,
+    0x1980, 0x19a0, 0x19c0, 0x19e0, 0x1a00, 0x1a20, 0x1a40, 0x1a60, 0x1a80, 0x1aa0, 0x1ac0, 0x1ae0,
+    0x1b00, 0x1b20, 0x1b40, 0x1b60, 0x1b80, 0x1ba0, 0x1bc0, 0x1be0, 0x1c00, 0x227e, 0x1c40, 0x1c60,
+    0x1c80, 0x1ca0, 0x1cc0, 0x1ce0, 0x1d00, 0x1d20, 0x1d40, 0x1d60, 0x1d80, 0x1da0, 0x1dc0, 0x1de0,
+    0x1e00, 0x1e20, 0x1e40, 0x1e60, 0x1e80, 0x1ea0, 0x1ec0, 0x1ee0, 0x1f00, 0x1f20, 0x1f40, 0x1f60,
+    0x1f80, 0x1fa0, 0x1fc0, 0x1fe0,
+];
+
+// Paradox 'ANSI intl', code page 1252
+const ANSI_INTERNATIONAL: Weights = [
+    0x0000, 0x0020, 0x0040, 0x0060, 0x0080, 0x00a0, 0x00c0, 0x00e0, 0x0100, 0x0120, 0x0140, 0x0160,
+    0x0180, 0x01a0, 0x01c0, 0x01e0, 0x0200, 0x0220, 0x0240, 0x0260, 0x0280, 0x02a0, 0x02c0, 0x02e0,
+    0x0300, 0x0320, 0x0340, 0x0360, 0x0380, 0x03a0, 0x03c0, 0x03e0, 0x0400, 0x0420, 0x0440, 0x0460,
+    0x0480, 0x04a0, 0x04c0, 0x04e0, 0x0500, 0x0520, 0x0540, 0x0560, 0x0580, 0x05a0, 0x05c0, 0x05e0,
+    0x0600, 0x0620, 0x0640, 0x0660, 0x0680, 0x06a0, 0x06c0, 0x06e0, 0x0700, 0x0720, 0x0740, 0x0760,
+    0x0780, 0x07a0, 0x07c0, 0x07e0, 0x0800, 0x2001, 0x2021, 0x2041, 0x2061, 0x2081, 0x20a1, 0x20c1,
+    0x20e1, 0x2101, 0x2121, 0x2141, 0x2161, 0x2181, 0x21a1, 0x21c1, 0x21e1, 0x2201, 0x2221, 0x2241,
+    0x2261, 0x2281, 0x22a1, 0x22c1, 0x22e1, 0x2301, 0x2321, 0x0b60, 0x0b80, 0x0ba0, 0x0bc0, 0x0be0,
+    0x0c00, 0x2000, 0x2020, 0x2040, 0x2060, 0x2080, 0x20a0, 0x20c0, 0x20e0, 0x2100, 0x2120, 0x2140,
+    0x2160, 0x2180, 0x21a0, 0x21c0, 0x21e0, 0x2200, 0x2220, 0x2240, 0x2260, 0x2280, 0x22a0, 0x22c0,
+    0x22e0, 0x2300, 0x2320, 0x0f60, 0x0f80, 0x0fa0, 0x0fc0, 0x0fe0, 0x1000, 0x1020, 0x1040, 0x1060,
+    0x1080, 0x10a0, 0x10c0, 0x10e0, 0x1100, 0x1120, 0x2243, 0x1160, 0x21cf, 0x11a0, 0x2323, 0x11e0,
+    0x1200, 0x1220, 0x1240, 0x1260, 0x1280, 0x12a0, 0x12c0, 0x12e0, 0x1300, 0x1320, 0x2242, 0x1360,
+    0x21ce, 0x13a0, 0x2322, 0x2305, 0x1400, 0x1420, 0x1440, 0x1460, 0x1480, 0x14a0, 0x14c0, 0x14e0,
+    0x1500, 0x1520, 0x1540, 0x1560, 0x1580, 0x15a0, 0x15c0, 0x15e0, 0x1600, 0x1620, 0x1640, 0x1660,
+    0x1680, 0x16a0, 0x16c0, 0x16e0, 0x1700, 0x1720, 0x1740, 0x1760, 0x1780, 0x17a0, 0x17c0, 0x17e0,
+    0x2005, 0x2003, 0x2007, 0x200b, 0x2009, 0x200d, 0x200f, 0x2043, 0x2085, 0x2083, 0x2087, 0x2089,
+    0x2105, 0x2103, 0x2107, 0x2109, 0x2063, 0x21a3, 0x21c5, 0x21c3, 0x21c7, 0x21cb, 0x21c9, 0x1ae0,
+    0x21cd, 0x2285, 0x2283, 0x2287, 0x2289, 0x2303, 0x2263, 0x225e, 0x2004, 0x2002, 0x2006, 0x200a,
+    0x2008, 0x200c, 0x200e, 0x2042, 0x2084, 0x2082, 0x2086, 0x2088, 0x2104, 0x2102, 0x2106, 0x2108,
+    0x2062, 0x21a2, 0x21c4, 0x21c2, 0x21c6, 0x21ca, 0x21c8, 0x1ee0, 0x21cc, 0x2284, 0x2282, 0x2286,
+    0x2288, 0x2302, 0x2262, 0x2304,
+];
+
+impl Collation {
+    // None for sort orders pxrs does not know
+    pub fn from_sort_order(sort_order: u8) -> Option<Self> {
+        Some(match sort_order {
+            0x00 => Collation::Ascii,
+            0xb7 => Collation::International,
+            0x82 => Collation::NorwegianDanish,
+            0xe6 => Collation::NorwegianDanish40,
+            0x0b => Collation::SwedishFinnish,
+            0x5d => Collation::Spanish,
+            0x62 => Collation::AnsiInternational,
+            _ => return None,
+        })
+    }
+
+    pub fn sort_order(&self) -> u8 {
+        match self {
+            Collation::Ascii => 0x00,
+            Collation::International => 0xb7,
+            Collation::NorwegianDanish => 0x82,
+            Collation::NorwegianDanish40 => 0xe6,
+            Collation::SwedishFinnish => 0x0b,
+            Collation::Spanish => 0x5d,
+            Collation::AnsiInternational => 0x62,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        crate::types::sort_order_name(self.sort_order())
+    }
+
+    // Code page the weights are given for
+    pub fn code_page(&self) -> u16 {
+        match self {
+            Collation::NorwegianDanish | Collation::NorwegianDanish40 => 865,
+            Collation::AnsiInternational => 1252,
+            _ => 437,
+        }
+    }
+
+    fn weights(&self) -> Option<&'static Weights> {
+        Some(match self {
+            Collation::Ascii => return None,
+            Collation::International => &INTERNATIONAL,
+            Collation::NorwegianDanish | Collation::NorwegianDanish40 => &NORWEGIAN_DANISH,
+            Collation::SwedishFinnish => &SWEDISH_FINNISH,
+            Collation::Spanish => &SPANISH,
+            Collation::AnsiInternational => &ANSI_INTERNATIONAL,
+        })
+    }
+
+    // Compares two Alpha values. Strings are compared by their letters
+    // first, accents and case only break ties, and strings differing in
+    // any byte are never equal.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let Some(weights) = self.weights() else {
+            return a.cmp(b);
+        };
+
+        let level = |s, level| weights_of(s, weights, level);
+        level(a, primary)
+            .cmp(level(b, primary))
+            .then_with(|| level(a, accent).cmp(level(b, accent)))
+            .then_with(|| level(a, case).cmp(level(b, case)))
+            .then_with(|| a.cmp(b))
+    }
+
+    // `expr::compare_values` with Alpha values in collation order
+    pub fn compare_values(&self, a: &Value, b: &Value) -> Result<Option<Ordering>> {
+        match (a, b) {
+            (Value::Alpha(x) | Value::Memo(x), Value::Alpha(y) | Value::Memo(y)) => {
+                Ok(Some(self.compare(x, y)))
+            }
+            _ => compare_values(a, b),
+        }
+    }
+
+    // Orders the values of a key field, Alpha values by the collation and
+    // blanks before any other value.
+    pub fn key_order(&self, a: &Value, b: &Value) -> Result<Ordering> {
+        Ok(match self.compare_values(a, b)? {
+            Some(ordering) => ordering,
+            None => (*a != Value::Null).cmp(&(*b != Value::Null)),
+        })
+    }
+}
@@ -1,242 +1,452 @@
+use crate::currency::PxCurrency;
+use crate::datetime::{PxDate, PxTime, PxTimestamp};
+use crate::error::{Error, Result};
 use crate::types::*;
-use std::ffi::CStr;
-use std::fs::File;
-use std::io::{self, Read, Seek, SeekFrom};
-use std::mem;
-use std::ptr;
-use std::time::SystemTime;
-
-// Helper functions for endian conversions
-fn copy_from_be<T: Default + Copy>(dst: &mut T, src: &[u8], len: usize) {
-    let mut buf: [u8; 8] = [0; 8];
-    buf[..len].copy_from_slice(&src[..len]);
-    *dst = unsafe { ptr::read(buf.as_ptr() as *const T) };
+use byte::{BytesExt, LE};
+
+// Numbers are stored big endian with the high bit of the first byte
+// flipped, so an all zero field is a blank (null) value.
+fn is_null(data: &[u8]) -> bool {
+    data.iter().all(|&b| b == 0)
 }
 
-fn copy_from_le<T: Default + Copy>(dst: &mut T, src: &[u8], len: usize) {
-    let mut buf: [u8; 8] = [0; 8];
-    buf[..len].copy_from_slice(&src[..len]);
-    *dst = unsafe { ptr::read(buf.as_ptr() as *const T) };
+fn field_data(data: &[u8], len: usize) -> Result<&[u8]> {
+    data.get(..len)
+        .ok_or_else(|| Error::corrupt("Field data too short"))
 }
 
-// Sign manipulation functions
-fn fix_sign(dst: &mut [u8], len: usize) {
-    dst[len - 1] &= 0x7f;
+// Convert PX number to long
+pub fn px_to_long(data: &[u8], field_type: u8) -> Result<Option<i64>> {
+    let len = match field_type {
+        PX_FIELD_TYPE_LOGICAL => 1,
+        PX_FIELD_TYPE_SHORT_INT => 2,
+        PX_FIELD_TYPE_LONG_INT | PX_FIELD_TYPE_INCREMENTAL => 4,
+        PX_FIELD_TYPE_DATE | PX_FIELD_TYPE_TIME => 4,
+        _ => return Err(Error::invalid("Unsupported type")),
+    };
+
+    let s = field_data(data, len)?;
+    if is_null(s) {
+        return Ok(None);
+    }
+
+    let mut retval = (s[0] ^ 0x80) as i8 as i64;
+    for &b in &s[1..] {
+        retval = (retval << 8) | b as i64;
+    }
+
+    Ok(Some(retval))
 }
 
-fn set_sign(dst: &mut [u8], len: usize) {
-    dst[len - 1] |= 0x80;
+// Convert PX number to double
+pub fn px_to_double(data: &[u8], field_type: u8) -> Result<Option<f64>> {
+    match field_type {
+        PX_FIELD_TYPE_CURRENCY | PX_FIELD_TYPE_NUMBER | PX_FIELD_TYPE_TIMESTAMP => {}
+        _ => return Err(Error::invalid("Unsupported type")),
+    }
+
+    let s = field_data(data, 8)?;
+    if is_null(s) {
+        return Ok(None);
+    }
+
+    let mut d = [0u8; 8];
+    d.copy_from_slice(s);
+
+    if d[0] & 0x80 != 0 {
+        d[0] &= 0x7f;
+    } else {
+        // Negative values have all their bits inverted
+        d.iter_mut().for_each(|x| *x ^= 0xff);
+    }
+
+    Ok(Some(f64::from_be_bytes(d)))
 }
 
-// Convert PX number to long
-fn px_to_long(number: u64, ret: &mut u64, field_type: u8) -> Result<(), &'static str> {
-    let mut retval = 0u64;
-    let s = number.to_le_bytes();
-    let d = retval.to_le_bytes();
+// Formats Date, Time and Timestamp values as ISO 8601 strings.
+pub fn px_to_iso_string(value: &Value) -> Result<String> {
+    Ok(match value {
+        Value::Date(_) => PxDate::try_from(value)?.to_string(),
+        Value::Time(_) => PxTime::try_from(value)?.to_string(),
+        Value::Timestamp(_) => PxTimestamp::try_from(value)?.to_string(),
+        _ => return Err(Error::invalid("Unsupported type")),
+    })
+}
 
-    match field_type {
-        PX_FIELD_TYPE_LOGICAL => {
-            copy_from_be(&mut retval, &s, 1);
-            if s[0] & 0x80 != 0 {
-                fix_sign(&mut retval.to_le_bytes(), 1);
-            } else if retval == 0 {
-                return Err("Value is null");
-            } else {
-                set_sign(&mut retval.to_le_bytes(), 1);
-            }
-        }
-        PX_FIELD_TYPE_SHORT_INT => {
-            copy_from_be(&mut retval, &s, 2);
-            if s[0] & 0x80 != 0 {
-                fix_sign(&mut retval.to_le_bytes(), 2);
-            } else if retval == 0 {
-                return Err("Value is null");
-            } else {
-                set_sign(&mut retval.to_le_bytes(), 2);
-            }
-        }
-        PX_FIELD_TYPE_LONG_INT | PX_FIELD_TYPE_INCREMENTAL => {
-            copy_from_be(&mut retval, &s, 4);
-            if s[0] & 0x80 != 0 {
-                fix_sign(&mut retval.to_le_bytes(), 4);
-            } else if retval == 0 {
-                return Err("Value is null");
-            } else {
-                set_sign(&mut retval.to_le_bytes(), 4);
-            }
-        }
-        _ => return Err("Unsupported type"),
+// BCD fields are 17 bytes: a sign/precision byte followed by 32 digits,
+// the last `decimals` of them after the decimal point.
+pub fn px_to_bcd(data: &[u8], decimals: u8) -> Result<Option<String>> {
+    let s = field_data(data, 17)?;
+    if is_null(s) {
+        return Ok(None);
     }
 
-    *ret = retval;
-    Ok(())
+    let (negative, mask) = if s[0] & 0x80 != 0 {
+        (false, 0x00)
+    } else {
+        (true, 0x0f)
+    };
+
+    let digit = |i: usize| {
+        let b = s[i / 2];
+        let nibble = if i.is_multiple_of(2) {
+            b >> 4
+        } else {
+            b & 0x0f
+        };
+        (b'0' + ((nibble ^ mask) % 10)) as char
+    };
+
+    let decimals = (decimals as usize).min(32);
+    let int_part: String = (2..34 - decimals).map(digit).collect();
+    let int_part = int_part.trim_start_matches('0');
+
+    let mut string = String::new();
+    if negative {
+        string.push('-');
+    }
+    string.push_str(if int_part.is_empty() { "0" } else { int_part });
+    if decimals > 0 {
+        string.push('.');
+        string.extend((34 - decimals..34).map(digit));
+    }
+
+    Ok(Some(string))
 }
 
-// Convert PX number to double
-fn px_to_double(number: u64, ret: &mut f64, field_type: u8) -> Result<(), &'static str> {
-    let mut retval = 0f64;
-    let s = number.to_le_bytes();
-    let mut d = retval.to_le_bytes();
+// Blob fields are a leader holding the start of the data, followed by
+// 10 little endian bytes pointing into the .MB file.
+pub fn px_to_blob(data: &[u8], field: &PxFieldInfo) -> Result<Value> {
+    let size = field.size as usize;
+    if size < 10 {
+        return Err(Error::corrupt("Blob field too short"));
+    }
 
-    match field_type {
-        PX_FIELD_TYPE_CURRENCY | PX_FIELD_TYPE_NUMBER => {
-            copy_from_be(&mut retval, &s, 8);
-            if s[0] & 0x80 != 0 {
-                fix_sign(&mut retval.to_le_bytes(), 8);
-            } else if retval == 0.0 {
-                return Err("Value is null");
-            } else {
-                // Apply fix for negative values
-                d.iter_mut().for_each(|x| *x ^= 0xff);
+    let s = field_data(data, size)?;
+    let mut offset = size - 10;
+
+    let mb_offset: u32 = s
+        .read_with(&mut offset, LE)
+        .map_err(|_| Error::corrupt("Blob field too short"))?;
+    let length: u32 = s
+        .read_with(&mut offset, LE)
+        .map_err(|_| Error::corrupt("Blob field too short"))?;
+    let mod_number: u16 = s
+        .read_with(&mut offset, LE)
+        .map_err(|_| Error::corrupt("Blob field too short"))?;
 
-                retval = unsafe { mem::transmute::<[u8; 8], f64>(d) };
+    if length == 0 {
+        return Ok(Value::Null);
+    }
+
+    if mb_offset == 0 {
+        // The entire blob is contained in the leader
+        let leader = s
+            .get(..length as usize)
+            .ok_or_else(|| Error::corrupt("Blob length exceeds the leader"))?;
+
+        return Ok(match field.field_type {
+            PX_FIELD_TYPE_MEMO_BLOB | PX_FIELD_TYPE_DUNNO => {
+                Value::Memo(leader.iter().map(|&c| c as char).collect())
             }
-        }
-        _ => return Err("Unsupported type"),
+            _ => Value::Blob(leader.to_vec()),
+        });
     }
 
-    *ret = retval;
-    Ok(())
+    Ok(Value::BlobRef(BlobPointer {
+        offset: mb_offset & 0xffffff00,
+        index: (mb_offset & 0xff) as u8,
+        length,
+        mod_number,
+    }))
 }
 
-// Convert PX number to time (tm structure)
-fn px_to_tm(number: u64, tm: &mut libc::tm, field_type: u8) -> Result<(), &'static str> {
-    let mut retval = 0u64;
-    let s = number.to_le_bytes();
-    let d = retval.to_le_bytes();
+// Decodes a single field from the start of `data`.
+pub fn decode_field(data: &[u8], field: &PxFieldInfo) -> Result<Value> {
+    decode(data, field).map_err(|e| e.in_field(&field.name))
+}
 
-    match field_type {
-        PX_FIELD_TYPE_DATE => {
-            copy_from_be(&mut retval, &s, 4);
-            if s[0] & 0x80 != 0 {
-                fix_sign(&mut retval.to_le_bytes(), 4);
-            } else if retval == 0 {
-                return Err("Value is null");
-            }
-            // Date conversion logic (Y2K workaround)
-            let jd = 719528 + retval - 1;
-            let (y, m, d) = gdate(jd);
-            tm.tm_year = y as i32 - 1900;
-            tm.tm_mon = m as i32 - 1;
-            tm.tm_mday = d as i32;
-        }
-        PX_FIELD_TYPE_TIME => {
-            copy_from_be(&mut retval, &s, 4);
-            if s[0] & 0x80 != 0 {
-                fix_sign(&mut retval.to_le_bytes(), 4);
-                retval /= 1000; // discard milliseconds
-                tm.tm_sec = (retval % 60) as i32;
-                retval /= 60;
-                tm.tm_min = (retval % 60) as i32;
-                tm.tm_hour = (retval / 60) as i32;
-            } else if retval == 0 {
-                return Err("Value is null");
+fn decode(data: &[u8], field: &PxFieldInfo) -> Result<Value> {
+    let long = || px_to_long(data, field.field_type);
+    let double = || px_to_double(data, field.field_type);
+
+    let value = match field.field_type {
+        PX_FIELD_TYPE_ALPHA => {
+            let s = field_data(data, field.size as usize)?;
+            let string = crate::parse::read_cstr(s);
+            if string.is_empty() {
+                Value::Null
+            } else {
+                Value::Alpha(string)
             }
         }
-        PX_FIELD_TYPE_TIMESTAMP => {
-            copy_from_be(&mut retval, &s, 8);
-            if s[0] & 0x80 != 0 {
-                fix_sign(&mut retval.to_le_bytes(), 8);
-                retval >>= 8;
-                retval /= 500; // resolution of 1/500s
-                let t = retval as i64 - 37603860709183;
-                *tm = unsafe { *libc::gmtime(&t) };
-            } else if retval == 0 {
-                return Err("Value is null");
+        PX_FIELD_TYPE_DATE => long()?.map_or(Value::Null, |v| Value::Date(v as i32)),
+        PX_FIELD_TYPE_SHORT_INT => long()?.map_or(Value::Null, |v| Value::Short(v as i16)),
+        PX_FIELD_TYPE_LONG_INT => long()?.map_or(Value::Null, |v| Value::Long(v as i32)),
+        PX_FIELD_TYPE_INCREMENTAL => long()?.map_or(Value::Null, |v| Value::AutoInc(v as i32)),
+        PX_FIELD_TYPE_TIME => long()?.map_or(Value::Null, |v| Value::Time(v as i32)),
+        PX_FIELD_TYPE_LOGICAL => long()?.map_or(Value::Null, |v| Value::Logical(v != 0)),
+        PX_FIELD_TYPE_CURRENCY => match double()? {
+            Some(v) => Value::Currency(
+                PxCurrency::from_f64(v)
+                    .ok_or_else(|| Error::value(format!("Currency value {} is out of range", v)))?,
+            ),
+            None => Value::Null,
+        },
+        PX_FIELD_TYPE_NUMBER => double()?.map_or(Value::Null, Value::Number),
+        PX_FIELD_TYPE_TIMESTAMP => double()?.map_or(Value::Null, Value::Timestamp),
+        PX_FIELD_TYPE_BCD => px_to_bcd(data, field.size)?.map_or(Value::Null, Value::Bcd),
+        PX_FIELD_TYPE_BYTES => {
+            let s = field_data(data, field.size as usize)?;
+            if is_null(s) {
+                Value::Null
+            } else {
+                Value::Bytes(s.to_vec())
             }
         }
-        _ => return Err("Unsupported type"),
+        PX_FIELD_TYPE_MEMO_BLOB
+        | PX_FIELD_TYPE_BIN_BLOB
+        | PX_FIELD_TYPE_DUNNO
+        | PX_FIELD_TYPE_OLE
+        | PX_FIELD_TYPE_GRAPHIC => px_to_blob(data, field)?,
+        _ => {
+            return Err(Error::unsupported(format!(
+                "Unsupported field type {:02x}",
+                field.field_type
+            )))
+        }
+    };
+
+    Ok(value)
+}
+
+// Convert long to PX number, `out` being the whole field
+pub fn px_from_long(value: i64, out: &mut [u8]) -> Result<()> {
+    let len = out.len();
+    let bits = 8 * len as u32;
+    let min = -(1i64 << (bits - 1));
+
+    // The smallest value encodes as all zeroes, which is a blank
+    if value <= min || value > !min {
+        return Err(Error::value("Value out of range"));
     }
 
+    out.copy_from_slice(&value.to_be_bytes()[8 - len..]);
+    out[0] ^= 0x80;
+
     Ok(())
 }
 
-// Memo handling - this function retrieves a memo blob from a file
-fn px_memo_to_string(
-    blob: &[u8],
-    size: usize,
-    blobname: Option<&str>,
-) -> io::Result<Option<String>> {
-    if size < 10 {
-        return Ok(None);
+// Convert double to PX number
+pub fn px_from_double(value: f64, out: &mut [u8]) {
+    let mut d = value.to_be_bytes();
+
+    if value.is_sign_negative() {
+        d.iter_mut().for_each(|x| *x ^= 0xff);
+    } else {
+        d[0] |= 0x80;
     }
 
-    let mut offset: u32 = 0;
-    let mut length: u32 = 0;
-    let mut mod_number: u16 = 0;
-    let mut index: u8 = 0;
+    out.copy_from_slice(&d);
+}
 
-    copy_from_le(&mut offset, &blob[size - 10..], 4);
-    copy_from_le(&mut length, &blob[size - 6..], 4);
-    copy_from_le(&mut mod_number, &blob[size - 2..], 2);
-    copy_from_le(&mut index, &blob[size - 10..], 1);
+// Convert a decimal string to a PX BCD number with `decimals` digits after
+// the decimal point. Negative numbers have all their bits inverted.
+pub fn px_from_bcd(value: &str, decimals: u8, out: &mut [u8]) -> Result<()> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
 
-    offset &= 0xffffff00;
+    let (int_part, frac_part) = value.split_once('.').unwrap_or((value, ""));
+    let is_digits = |s: &str| s.bytes().all(|c| c.is_ascii_digit());
+    if !is_digits(int_part)
+        || !is_digits(frac_part)
+        || (int_part.is_empty() && frac_part.is_empty())
+    {
+        return Err(Error::value("Invalid BCD number"));
+    }
 
-    if index == 0x00 {
-        return Ok(None);
+    let decimals = (decimals as usize).min(32);
+    let int_part = int_part.trim_start_matches('0');
+    if int_part.len() > 32 - decimals {
+        return Err(Error::value("Value out of range"));
     }
 
-    if let Some(blobname) = blobname {
-        let mut file = File::open(blobname)?;
+    // Extra decimals are truncated
+    let digits = format!(
+        "{:0>width$}{:0<decimals$.decimals$}",
+        int_part,
+        frac_part,
+        width = 32 - decimals,
+    );
+
+    out.fill(0);
+    out[0] = 0x80 | decimals as u8;
+    for (i, digit) in digits.bytes().enumerate() {
+        let nibble = digit - b'0';
+        let n = i + 2;
+        out[n / 2] |= if n.is_multiple_of(2) {
+            nibble << 4
+        } else {
+            nibble
+        };
+    }
+
+    if negative {
+        out.iter_mut().for_each(|x| *x ^= 0xff);
+    }
+
+    Ok(())
+}
+
+// Encodes a value into `out`, which must have the size of the field.
+pub fn encode_field(value: &Value, field: &PxFieldInfo, out: &mut [u8]) -> Result<()> {
+    encode(value, field, out).map_err(|e| e.in_field(&field.name))
+}
+
+fn encode(value: &Value, field: &PxFieldInfo, out: &mut [u8]) -> Result<()> {
+    let invalid = |msg: &str| Error::value(msg);
+    let mismatch = || {
+        invalid(&format!(
+            "Cannot store {:?} in a {} field",
+            value,
+            field.type_name()
+        ))
+    };
+    let latin1 = |s: &str| -> Result<Vec<u8>> {
+        s.chars()
+            .map(|c| u8::try_from(c).map_err(|_| invalid("Character is not latin-1")))
+            .collect()
+    };
+
+    out.fill(0);
 
-        if index == 0xff {
-            // Type 02 block
-            let mut header = [0u8; 9];
-            file.seek(SeekFrom::Start(offset as u64))?;
-            file.read_exact(&mut header)?;
+    let integer = match *value {
+        Value::Short(v) => Some(v as i64),
+        Value::Long(v) | Value::AutoInc(v) => Some(v as i64),
+        _ => None,
+    };
 
-            let mut idx = MbType2Pointer {
-                type_: 0,
-                size_div_4k: 0,
-                length: 0,
-                mod_count: 0,
+    match (field.field_type, value) {
+        (_, Value::Null) => {}
+        (PX_FIELD_TYPE_ALPHA, Value::Alpha(s) | Value::Memo(s)) => {
+            let data = latin1(s)?;
+            out.get_mut(..data.len())
+                .ok_or_else(|| invalid("String longer than the field"))?
+                .copy_from_slice(&data);
+        }
+        (PX_FIELD_TYPE_SHORT_INT | PX_FIELD_TYPE_LONG_INT | PX_FIELD_TYPE_INCREMENTAL, _)
+            if integer.is_some() =>
+        {
+            px_from_long(integer.unwrap_or_default(), out)?
+        }
+        (PX_FIELD_TYPE_DATE, Value::Date(v)) | (PX_FIELD_TYPE_TIME, Value::Time(v)) => {
+            px_from_long(*v as i64, out)?
+        }
+        (PX_FIELD_TYPE_LOGICAL, Value::Logical(v)) => out[0] = 0x80 | *v as u8,
+        (PX_FIELD_TYPE_CURRENCY | PX_FIELD_TYPE_NUMBER, Value::Currency(v)) => {
+            px_from_double(v.to_f64(), out)
+        }
+        (PX_FIELD_TYPE_CURRENCY | PX_FIELD_TYPE_NUMBER, Value::Number(v)) => {
+            px_from_double(*v, out)
+        }
+        (PX_FIELD_TYPE_CURRENCY | PX_FIELD_TYPE_NUMBER, _) if integer.is_some() => {
+            px_from_double(integer.unwrap_or_default() as f64, out)
+        }
+        (PX_FIELD_TYPE_TIMESTAMP, Value::Timestamp(v)) => px_from_double(*v, out),
+        (PX_FIELD_TYPE_BCD, Value::Bcd(s)) => px_from_bcd(s, field.size, out)?,
+        // Currency values keep their exact decimals
+        (PX_FIELD_TYPE_BCD, Value::Currency(v)) => {
+            px_from_bcd(&format!("{:.*}", field.size as usize, v), field.size, out)?
+        }
+        (PX_FIELD_TYPE_BCD, Value::Number(v)) => {
+            let s = format!("{:.*}", field.size as usize, v);
+            px_from_bcd(&s, field.size, out)?
+        }
+        (PX_FIELD_TYPE_BYTES, Value::Bytes(data) | Value::Blob(data)) => {
+            out.get_mut(..data.len())
+                .ok_or_else(|| invalid("Data longer than the field"))?
+                .copy_from_slice(data);
+        }
+        (
+            PX_FIELD_TYPE_MEMO_BLOB
+            | PX_FIELD_TYPE_BIN_BLOB
+            | PX_FIELD_TYPE_DUNNO
+            | PX_FIELD_TYPE_OLE
+            | PX_FIELD_TYPE_GRAPHIC,
+            Value::Memo(_) | Value::Alpha(_) | Value::Blob(_) | Value::Bytes(_),
+        ) => {
+            let data = match value {
+                Value::Memo(s) | Value::Alpha(s) => latin1(s)?,
+                Value::Blob(data) | Value::Bytes(data) => data.clone(),
+                _ => unreachable!(),
             };
-            copy_from_le(&mut idx.type_, &header[0..], 1);
-            copy_from_le(&mut idx.size_div_4k, &header[1..], 2);
-            copy_from_le(&mut idx.length, &header[3..], 4);
-            copy_from_le(&mut idx.mod_count, &header[7..], 2);
-
-            if idx.type_ != 0x02 || idx.length != length {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Type 02 blob length mismatch",
-                ));
-            }
 
-            let mut string = vec![0u8; length as usize];
-            file.read_exact(&mut string)?;
+            // Only blobs that fit in the leader can be written, the .MB file
+            // is left alone.
+            let leader = out.len().saturating_sub(10);
+            if data.len() > leader {
+                return Err(invalid("Blob does not fit in the leader"));
+            }
 
-            return Ok(Some(String::from_utf8(string).unwrap_or_default()));
-        } else {
-            // Handle type 03 block here (similar to type 02 but with different logic)
-            // Implement as per your specific logic needs
+            out[..data.len()].copy_from_slice(&data);
+            out[leader + 4..leader + 8].copy_from_slice(&(data.len() as u32).to_le_bytes());
         }
+        _ => return Err(mismatch()),
     }
 
-    Ok(None)
+    Ok(())
 }
 
-// Helper function for Julian date to Gregorian date conversion
-fn gdate(jd: u64) -> (i32, i32, i32) {
-    let mut jd = jd as i64 - 1721119;
-    let j = (4 * jd - 1) / 146097;
-    jd = (4 * jd - 1) % 146097;
-    let t = jd / 4;
+// Blob handling - retrieves the data of a blob from the .MB file contents
+pub fn px_blob_data(mb: &[u8], pointer: &BlobPointer) -> Result<Vec<u8>> {
+    let invalid = |msg: &str| Error::corrupt(msg).at_offset(pointer.offset as u64);
 
-    jd = (4 * t + 3) / 1461;
-    let t = (4 * t + 3) % 1461;
-    let t = (t + 4) / 4;
+    let block = mb
+        .get(pointer.offset as usize..)
+        .ok_or_else(|| invalid("Blob offset beyond the end of the .MB file"))?;
 
-    let m = (5 * t - 3) / 153;
-    let t = (5 * t - 3) % 153;
-    let t = (t + 5) / 5;
+    let data = if pointer.index == 0xff {
+        // Type 02 block
+        let mut offset = 0;
+        let mut read = || -> byte::Result<MbType2Pointer> {
+            Ok(MbType2Pointer {
+                type_: block.read_with(&mut offset, LE)?,
+                size_div_4k: block.read_with(&mut offset, LE)?,
+                length: block.read_with(&mut offset, LE)?,
+                mod_count: block.read_with(&mut offset, LE)?,
+            })
+        };
+        let idx = read().map_err(|_| invalid("Truncated type 02 blob header"))?;
 
-    let y = 100 * j + jd;
+        if idx.type_ != 0x02 || idx.length != pointer.length {
+            return Err(invalid("Type 02 blob length mismatch"));
+        }
 
-    if m < 10 {
-        (y as i32, m as i32 + 3, t as i32)
+        block.get(offset..offset + idx.length as usize)
     } else {
-        (y as i32 + 1, m as i32 - 9, t as i32)
-    }
+        // Type 03 block: 12 byte header followed by 5 byte blob pointers
+        if block.first() != Some(&0x03) {
+            return Err(invalid("Not a type 03 blob block"));
+        }
+
+        let entry = 12 + 5 * pointer.index as usize;
+        let entry = block
+            .get(entry..entry + 5)
+            .ok_or_else(|| invalid("Truncated type 03 blob block"))?;
+
+        let start = entry[0] as usize * 16;
+        let length = (entry[1] as usize).saturating_sub(1) * 16 + entry[4] as usize;
+
+        if length != pointer.length as usize {
+            return Err(invalid("Type 03 blob length mismatch"));
+        }
+
+        block.get(start..start + length)
+    };
+
+    data.map(<[u8]>::to_vec)
+        .ok_or_else(|| invalid("Blob data beyond the end of the .MB file"))
 }
@@ -0,0 +1,322 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::types::Value;
+
+// A Currency value as an exact number of ten-thousandths, the precision
+// of the BDE's currency type. Paradox stores Currency fields as doubles,
+// which are rounded to the nearest ten-thousandth without going through
+// floating point arithmetic.
+//
+// Values display with two decimals, or more when they have them, as
+// Paradox shows Currency fields. `{:.N}` rounds to N decimals and `{:#}`
+// adds thousands separators and puts negative values in parentheses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PxCurrency(i64);
+
+const UNIT: i64 = 10_000;
+
+impl PxCurrency {
+    pub const ZERO: PxCurrency = PxCurrency(0);
+    pub const DECIMALS: u32 = 4;
+
+    // A number of ten-thousandths
+    pub fn from_units(units: i64) -> Self {
+        PxCurrency(units)
+    }
+
+    pub fn units(self) -> i64 {
+        self.0
+    }
+
+    // The exact value of the double rounded half away from zero, None for
+    // NaN, infinities and values beyond the range of the type
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+
+        // value = mantissa * 2^exponent
+        let bits = value.to_bits();
+        let fraction = bits & ((1 << 52) - 1);
+        let (mantissa, exponent) = match ((bits >> 52) & 0x7ff) as i32 {
+            0 => (fraction, -1074),
+            biased => (fraction | 1 << 52, biased - 1075),
+        };
+        if mantissa == 0 {
+            return Some(Self::ZERO);
+        }
+        // Whole doubles are at least 2^52, out of range
+        if exponent >= 0 {
+            return None;
+        }
+
+        let scaled = mantissa as u128 * UNIT as u128;
+        let shift = exponent.unsigned_abs();
+        let units = match shift {
+            128.. => 0,
+            _ => {
+                let whole = scaled >> shift;
+                let rest = scaled - (whole << shift);
+                whole + (rest >= 1 << (shift - 1)) as u128
+            }
+        };
+
+        let units = i64::try_from(units).ok()?;
+        Some(PxCurrency(match value.is_sign_negative() {
+            true => -units,
+            false => units,
+        }))
+    }
+
+    // The double nearest to the value, as Paradox stores it, ties going to
+    // the even one
+    pub fn to_f64(self) -> f64 {
+        let units = self.0.unsigned_abs() as u128;
+        if units == 0 {
+            return 0.0;
+        }
+
+        // The quotient scaled by 2^shift to the 53 bits of a double, whose
+        // division by a power of two is exact
+        let unit = UNIT as u128;
+        let mut shift = 66 - (128 - units.leading_zeros() as i32);
+        let mut quotient = (units << shift) / unit;
+        while quotient < 1 << 52 {
+            shift += 1;
+            quotient = (units << shift) / unit;
+        }
+        let rest = (units << shift) % unit;
+        if 2 * rest > unit || (2 * rest == unit && quotient & 1 == 1) {
+            quotient += 1;
+        }
+
+        let value = quotient as f64 / 2f64.powi(shift);
+        match self.0 < 0 {
+            true => -value,
+            false => value,
+        }
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(PxCurrency)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(PxCurrency)
+    }
+}
+
+fn group_thousands(digits: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() * 4 / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+impl Display for PxCurrency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut units = self.0.unsigned_abs() as u128;
+        if let Some(decimals @ 0..=3) = f.precision() {
+            let step = 10u128.pow(Self::DECIMALS - decimals as u32);
+            units = (units + step / 2) / step * step;
+        }
+
+        let fraction = format!("{:04}", units % UNIT as u128);
+        let fraction = match f.precision() {
+            Some(decimals) => format!("{:0<decimals$}", &fraction[..decimals.min(4)]),
+            None => format!("{:0<2}", fraction.trim_end_matches('0')),
+        };
+
+        let whole = (units / UNIT as u128).to_string();
+        let whole = match f.alternate() {
+            true => group_thousands(&whole),
+            false => whole,
+        };
+
+        let number = match fraction.is_empty() {
+            true => whole,
+            false => format!("{}.{}", whole, fraction),
+        };
+        match (self.0 < 0 && units > 0, f.alternate()) {
+            (false, _) => f.write_str(&number),
+            (true, false) => write!(f, "-{}", number),
+            (true, true) => write!(f, "({})", number),
+        }
+    }
+}
+
+// Decimal numbers with an optional sign, or in parentheses when negative,
+// and commas between the thousands. Decimals beyond the fourth round the
+// value half away from zero.
+impl FromStr for PxCurrency {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let parse = || {
+            let trimmed = text.trim();
+            let (negative, number) = match trimmed.strip_prefix('(') {
+                Some(inner) => (true, inner.strip_suffix(')')?.trim()),
+                None => match trimmed.strip_prefix('-') {
+                    Some(number) => (true, number),
+                    None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+                },
+            };
+
+            let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+            let whole = whole.replace(',', "");
+            let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+            if whole.is_empty() && fraction.is_empty() || !digits(&whole) || !digits(fraction) {
+                return None;
+            }
+
+            let mut units: i128 = 0;
+            for b in whole
+                .bytes()
+                .chain(fraction.bytes().chain([b'0'; 4]).take(4))
+            {
+                units = units.checked_mul(10)?.checked_add((b - b'0') as i128)?;
+            }
+            if fraction.as_bytes().get(4).is_some_and(|&b| b >= b'5') {
+                units += 1;
+            }
+
+            i64::try_from(if negative { -units } else { units })
+                .ok()
+                .map(PxCurrency)
+        };
+        parse().ok_or_else(|| Error::value(format!("Invalid currency value '{}'", text)))
+    }
+}
+
+impl From<PxCurrency> for Value {
+    fn from(currency: PxCurrency) -> Self {
+        Value::Currency(currency)
+    }
+}
+
+// Numbers are rounded to the nearest ten-thousandth like Currency values,
+// BCD values are read from their digits
+impl TryFrom<&Value> for PxCurrency {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        match value {
+            Value::Currency(v) => Ok(*v),
+            Value::Number(v) => PxCurrency::from_f64(*v)
+                .ok_or_else(|| Error::value(format!("{:?} is out of range", value))),
+            Value::Short(v) => Ok(PxCurrency(*v as i64 * UNIT)),
+            Value::Long(v) | Value::AutoInc(v) => Ok(PxCurrency(*v as i64 * UNIT)),
+            Value::Bcd(s) => s.parse(),
+            _ => Err(Error::value(format!("{:?} is not a number", value))),
+        }
+    }
+}
+
+// Serialized as exact decimal strings, deserialized from strings and
+// numbers
+impl serde::Serialize for PxCurrency {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for PxCurrency {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = PxCurrency;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a currency value")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> std::result::Result<PxCurrency, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> std::result::Result<PxCurrency, E> {
+                v.checked_mul(UNIT)
+                    .map(PxCurrency)
+                    .ok_or_else(|| E::custom("currency value out of range"))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> std::result::Result<PxCurrency, E> {
+                let v = i64::try_from(v).map_err(|_| E::custom("currency value out of range"))?;
+                self.visit_i64(v)
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, v: f64) -> std::result::Result<PxCurrency, E> {
+                PxCurrency::from_f64(v).ok_or_else(|| E::custom("currency value out of range"))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+mod decimal_conversions {
+    use rust_decimal::{Decimal, RoundingStrategy};
+
+    use super::PxCurrency;
+    use crate::error::{Error, Result};
+
+    impl From<PxCurrency> for Decimal {
+        fn from(currency: PxCurrency) -> Self {
+            Decimal::new(currency.0, PxCurrency::DECIMALS)
+        }
+    }
+
+    // Rounded half away from zero to four decimals
+    impl TryFrom<Decimal> for PxCurrency {
+        type Error = Error;
+
+        fn try_from(decimal: Decimal) -> Result<Self> {
+            let mut decimal = decimal.round_dp_with_strategy(
+                PxCurrency::DECIMALS,
+                RoundingStrategy::MidpointAwayFromZero,
+            );
+            decimal.rescale(PxCurrency::DECIMALS);
+            i64::try_from(decimal.mantissa())
+                .map(PxCurrency)
+                .map_err(|_| Error::value(format!("{} is out of range", decimal)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubles_are_the_nearest_to_the_exact_value() {
+        let mut units = 1i64;
+        let mut samples = vec![0, 1, 5, 9_999, 10_000, 12_345, i64::MAX, i64::MIN + 1];
+        while let Some(next) = units.checked_mul(7) {
+            samples.extend([next, next + 1, -next, next / 3]);
+            units = next;
+        }
+
+        for units in samples {
+            let currency = PxCurrency::from_units(units);
+            // Parsing a decimal string rounds correctly
+            let expected: f64 = currency.to_string().parse().unwrap();
+            assert_eq!(currency.to_f64(), expected, "{}", currency);
+            if units.unsigned_abs() < 1 << 50 {
+                assert_eq!(PxCurrency::from_f64(currency.to_f64()), Some(currency));
+            }
+        }
+    }
+}
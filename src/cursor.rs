@@ -0,0 +1,209 @@
+use crate::error::{Error, Result};
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+use serde::{Deserialize, Serialize};
+
+use crate::table::{Record, Table};
+use crate::types::{PxBlocks, Value, PX_FILETYPE_DB_INDEXED};
+
+// A saved cursor position: the data block and the slot of the record in
+// it. It stays valid as long as the table is not modified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub block: u16,
+    pub slot: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Position<'a> {
+    BeforeFirst,
+    On(PxBlocks<'a>, usize),
+    AfterLast,
+}
+
+// Moves over the records of a table in either direction, following the
+// linked list of data blocks. A new cursor is before the first record,
+// `next` then moves to it, as `prev` does to the last one from after the
+// end.
+#[derive(Clone, Copy)]
+pub struct Cursor<'a> {
+    table: &'a Table,
+    position: Position<'a>,
+}
+
+fn broken_chain() -> Error {
+    Error::corrupt("Block chain is longer than the number of blocks")
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(table: &'a Table) -> Self {
+        Cursor {
+            table,
+            position: Position::BeforeFirst,
+        }
+    }
+
+    pub fn current(&self) -> Option<Record<'a>> {
+        match &self.position {
+            Position::On(block, slot) => self.table.record(block, *slot),
+            _ => None,
+        }
+    }
+
+    pub fn first(&mut self) -> Result<Option<Record<'a>>> {
+        self.forward(self.table.header().first_block)
+    }
+
+    pub fn last(&mut self) -> Result<Option<Record<'a>>> {
+        self.backward(self.table.header().last_block)
+    }
+
+    // Not an iterator: moving can fail and goes both ways
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<Record<'a>>> {
+        match self.position {
+            Position::BeforeFirst => self.first(),
+            Position::On(block, slot) if slot + 1 < block.num_recs_in_block => {
+                self.position = Position::On(block, slot + 1);
+                Ok(self.current())
+            }
+            Position::On(block, _) => self.forward(block.next_block),
+            Position::AfterLast => Ok(None),
+        }
+    }
+
+    pub fn prev(&mut self) -> Result<Option<Record<'a>>> {
+        match self.position {
+            Position::AfterLast => self.last(),
+            Position::On(block, slot) if slot > 0 => {
+                self.position = Position::On(block, slot - 1);
+                Ok(self.current())
+            }
+            Position::On(block, _) => self.backward(block.prev_block),
+            Position::BeforeFirst => Ok(None),
+        }
+    }
+
+    // Moves to the first record of block `number` or of the blocks after
+    // it, empty blocks having no record to stop at.
+    fn forward(&mut self, mut number: u16) -> Result<Option<Record<'a>>> {
+        for _ in 0..=self.table.header().file_blocks {
+            if number == 0 {
+                self.position = Position::AfterLast;
+                return Ok(None);
+            }
+
+            let block = self.table.block(number)?;
+            if block.num_recs_in_block > 0 {
+                self.position = Position::On(block, 0);
+                return Ok(self.current());
+            }
+            number = block.next_block;
+        }
+
+        Err(broken_chain())
+    }
+
+    // Moves to the last record of block `number` or of the blocks before it.
+    fn backward(&mut self, mut number: u16) -> Result<Option<Record<'a>>> {
+        for _ in 0..=self.table.header().file_blocks {
+            if number == 0 {
+                self.position = Position::BeforeFirst;
+                return Ok(None);
+            }
+
+            let block = self.table.block(number)?;
+            if block.num_recs_in_block > 0 {
+                self.position = Position::On(block, block.num_recs_in_block - 1);
+                return Ok(self.current());
+            }
+            number = block.prev_block;
+        }
+
+        Err(broken_chain())
+    }
+
+    pub fn bookmark(&self) -> Option<Bookmark> {
+        match &self.position {
+            Position::On(block, slot) => Some(Bookmark {
+                block: block.number,
+                slot: *slot,
+            }),
+            _ => None,
+        }
+    }
+
+    // Moves back to a saved position.
+    pub fn goto(&mut self, bookmark: Bookmark) -> Result<Record<'a>> {
+        let block = self.table.block(bookmark.block)?;
+        if bookmark.slot >= block.num_recs_in_block {
+            return Err(Error::invalid("Bookmark does not point to a record"));
+        }
+
+        self.position = Position::On(block, bookmark.slot);
+        self.current()
+            .ok_or_else(|| Error::corrupt("Record is outside of its block"))
+    }
+
+    // Moves to the first record whose key is not below `key`, which gives
+    // the leading key fields. As with a nearest match, the key of that
+    // record can differ, the cursor is after the last record if every key
+    // is below.
+    pub fn seek_key(&mut self, key: &[Value]) -> Result<Option<Record<'a>>> {
+        let header = self.table.header();
+        if header.file_type != PX_FILETYPE_DB_INDEXED
+            || key.is_empty()
+            || key.len() > header.primary_key_fields.max(0) as usize
+        {
+            return Err(Error::invalid(format!(
+                "Expected between 1 and {} key values",
+                header.primary_key_fields.max(0)
+            )));
+        }
+
+        let start = match self.table.usable_index() {
+            Some(index) => index.first_block(Bound::Included(&key[0]))?.unwrap_or(0),
+            None => header.first_block,
+        };
+
+        // Block by block, a chain longer than the file is broken
+        self.forward(start)?;
+        for _ in 0..=header.file_blocks {
+            let Position::On(block, first) = self.position else {
+                return Ok(None);
+            };
+            for slot in first..block.num_recs_in_block {
+                self.position = Position::On(block, slot);
+                let found = self
+                    .current()
+                    .ok_or_else(|| Error::corrupt("Record is outside of its block"))?;
+                if compare_key(&found, key)?.is_ge() {
+                    return Ok(Some(found));
+                }
+            }
+            self.forward(block.next_block)?;
+        }
+
+        Err(broken_chain())
+    }
+}
+
+fn compare_key(record: &Record, key: &[Value]) -> Result<Ordering> {
+    for (i, value) in key.iter().enumerate() {
+        let ordering = record
+            .table()
+            .collation()
+            .key_order(&record.get(i)?, value)?;
+        if ordering.is_ne() {
+            return Ok(ordering);
+        }
+    }
+    Ok(Ordering::Equal)
+}
+
+impl Table {
+    pub fn cursor(&self) -> Cursor<'_> {
+        Cursor::new(self)
+    }
+}
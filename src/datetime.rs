@@ -0,0 +1,425 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::types::Value;
+
+// Date, Time and Timestamp values. Paradox counts days in the proleptic
+// Gregorian calendar with 1/1/1 as day 1, times in milliseconds since
+// midnight, and timestamps in milliseconds since the start of day 0, so
+// `days * MS_PER_DAY + time`.
+
+pub const MS_PER_DAY: i64 = 86_400_000;
+
+// Days since 1/1/1, counting it as day 1
+const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Years starting in March put the leap day at their end
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 305
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 305;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (
+        yoe + era * 400 + (month <= 2) as i64,
+        month as u32,
+        day as u32,
+    )
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+// A Date value between 1/1/100 and 12/31/9999, the range PARADOX4.TXT
+// gives for dates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PxDate(i32);
+
+impl PxDate {
+    pub const MIN: PxDate = PxDate(days_from_civil(100, 1, 1) as i32);
+    pub const MAX: PxDate = PxDate(days_from_civil(9999, 12, 31) as i32);
+
+    // The day number as stored, None outside of the range
+    pub fn from_days(days: i32) -> Option<Self> {
+        (Self::MIN.0..=Self::MAX.0)
+            .contains(&days)
+            .then_some(PxDate(days))
+    }
+
+    pub fn from_ymd(year: i32, month: u32, day: u32) -> Option<Self> {
+        if day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        let days = days_from_civil(year as i64, month as i64, day as i64);
+        Self::from_days(i32::try_from(days).ok()?)
+    }
+
+    pub fn days(self) -> i32 {
+        self.0
+    }
+
+    pub fn ymd(self) -> (i32, u32, u32) {
+        let (year, month, day) = civil_from_days(self.0 as i64);
+        (year as i32, month, day)
+    }
+}
+
+// A Time value, milliseconds since midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PxTime(u32);
+
+impl PxTime {
+    pub const MIDNIGHT: PxTime = PxTime(0);
+
+    // None for negative values and values of a day or more
+    pub fn from_ms(ms: i32) -> Option<Self> {
+        (0..MS_PER_DAY as i32)
+            .contains(&ms)
+            .then_some(PxTime(ms as u32))
+    }
+
+    pub fn from_hms_milli(hour: u32, minute: u32, second: u32, milli: u32) -> Option<Self> {
+        if hour > 23 || minute > 59 || second > 59 || milli > 999 {
+            return None;
+        }
+        Some(PxTime(((hour * 60 + minute) * 60 + second) * 1000 + milli))
+    }
+
+    pub fn ms(self) -> i32 {
+        self.0 as i32
+    }
+
+    pub fn hms_milli(self) -> (u32, u32, u32, u32) {
+        let seconds = self.0 / 1000;
+        (
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            self.0 % 1000,
+        )
+    }
+}
+
+// A Timestamp value, a date and a time of day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PxTimestamp {
+    pub date: PxDate,
+    pub time: PxTime,
+}
+
+impl PxTimestamp {
+    pub fn new(date: PxDate, time: PxTime) -> Self {
+        PxTimestamp { date, time }
+    }
+
+    // Timestamps are stored as doubles, rounded here to the nearest
+    // millisecond
+    pub fn from_ms(ms: f64) -> Option<Self> {
+        if !ms.is_finite() {
+            return None;
+        }
+        let ms = ms.round() as i64;
+        let date = PxDate::from_days(i32::try_from(ms.div_euclid(MS_PER_DAY)).ok()?)?;
+        let time = PxTime::from_ms(ms.rem_euclid(MS_PER_DAY) as i32)?;
+        Some(PxTimestamp { date, time })
+    }
+
+    pub fn ms(self) -> f64 {
+        (self.date.0 as i64 * MS_PER_DAY + self.time.0 as i64) as f64
+    }
+}
+
+impl Display for PxDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+impl Display for PxTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (hour, minute, second, milli) = self.hms_milli();
+        write!(f, "{:02}:{:02}:{:02}.{:03}", hour, minute, second, milli)
+    }
+}
+
+impl Display for PxTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}T{}", self.date, self.time)
+    }
+}
+
+fn invalid(kind: &str, text: &str) -> Error {
+    Error::value(format!("Invalid {} '{}'", kind, text))
+}
+
+// YYYY-MM-DD
+impl FromStr for PxDate {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let parse = || {
+            let mut parts = text.trim().splitn(3, '-');
+            let year = parts.next()?.parse().ok()?;
+            let month = parts.next()?.parse().ok()?;
+            let day = parts.next()?.parse().ok()?;
+            PxDate::from_ymd(year, month, day)
+        };
+        parse().ok_or_else(|| invalid("date", text))
+    }
+}
+
+// HH:MM[:SS[.mmm]], fractions being read as milliseconds whatever number
+// of digits they have
+impl FromStr for PxTime {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let parse = || {
+            let (time, fraction) = text.trim().split_once('.').unwrap_or((text.trim(), "0"));
+            if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let mut parts = time.splitn(3, ':');
+            let hour = parts.next()?.parse().ok()?;
+            let minute = parts.next()?.parse().ok()?;
+            let second = parts.next().unwrap_or("0").parse().ok()?;
+            let milli = format!("{:0<3}", fraction).get(..3)?.parse().ok()?;
+            PxTime::from_hms_milli(hour, minute, second, milli)
+        };
+        parse().ok_or_else(|| invalid("time", text))
+    }
+}
+
+// The date and the time separated by T or a space
+impl FromStr for PxTimestamp {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let (date, time) = text
+            .trim()
+            .split_once(['T', ' '])
+            .ok_or_else(|| invalid("timestamp", text))?;
+        Ok(PxTimestamp::new(date.parse()?, time.parse()?))
+    }
+}
+
+impl From<PxDate> for Value {
+    fn from(date: PxDate) -> Self {
+        Value::Date(date.0)
+    }
+}
+
+impl From<PxTime> for Value {
+    fn from(time: PxTime) -> Self {
+        Value::Time(time.ms())
+    }
+}
+
+impl From<PxTimestamp> for Value {
+    fn from(timestamp: PxTimestamp) -> Self {
+        Value::Timestamp(timestamp.ms())
+    }
+}
+
+fn out_of_range(value: &Value) -> Error {
+    Error::value(format!("{:?} is out of range", value))
+}
+
+// Timestamps give their date
+impl TryFrom<&Value> for PxDate {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        match *value {
+            Value::Date(days) => PxDate::from_days(days).ok_or_else(|| out_of_range(value)),
+            Value::Timestamp(_) => PxTimestamp::try_from(value).map(|t| t.date),
+            _ => Err(Error::value(format!("{:?} is not a date", value))),
+        }
+    }
+}
+
+// Timestamps give their time of day
+impl TryFrom<&Value> for PxTime {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        match *value {
+            Value::Time(ms) => PxTime::from_ms(ms).ok_or_else(|| out_of_range(value)),
+            Value::Timestamp(_) => PxTimestamp::try_from(value).map(|t| t.time),
+            _ => Err(Error::value(format!("{:?} is not a time", value))),
+        }
+    }
+}
+
+// Dates are taken at midnight
+impl TryFrom<&Value> for PxTimestamp {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        match *value {
+            Value::Timestamp(ms) => PxTimestamp::from_ms(ms).ok_or_else(|| out_of_range(value)),
+            Value::Date(_) => Ok(PxTimestamp::new(value.try_into()?, PxTime::MIDNIGHT)),
+            _ => Err(Error::value(format!("{:?} is not a timestamp", value))),
+        }
+    }
+}
+
+// Paradox days are chrono's days from the common era
+#[cfg(feature = "chrono")]
+mod chrono_conversions {
+    use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+    use super::{PxDate, PxTime, PxTimestamp};
+    use crate::error::{Error, Result};
+
+    impl TryFrom<PxDate> for NaiveDate {
+        type Error = Error;
+
+        fn try_from(date: PxDate) -> Result<Self> {
+            let (year, month, day) = date.ymd();
+            NaiveDate::from_ymd_opt(year, month, day)
+                .ok_or_else(|| Error::value(format!("{} is not a valid date", date)))
+        }
+    }
+
+    impl TryFrom<NaiveDate> for PxDate {
+        type Error = Error;
+
+        fn try_from(date: NaiveDate) -> Result<Self> {
+            PxDate::from_days(date.num_days_from_ce())
+                .ok_or_else(|| Error::value(format!("{} is out of range", date)))
+        }
+    }
+
+    impl TryFrom<PxTime> for NaiveTime {
+        type Error = Error;
+
+        fn try_from(time: PxTime) -> Result<Self> {
+            let (hour, minute, second, milli) = time.hms_milli();
+            NaiveTime::from_hms_milli_opt(hour, minute, second, milli)
+                .ok_or_else(|| Error::value(format!("{} is not a valid time", time)))
+        }
+    }
+
+    // Leap seconds and sub-millisecond precision are dropped
+    impl From<NaiveTime> for PxTime {
+        fn from(time: NaiveTime) -> Self {
+            let milli = (time.nanosecond() / 1_000_000).min(999);
+            PxTime::from_hms_milli(time.hour(), time.minute(), time.second(), milli)
+                .unwrap_or(PxTime::MIDNIGHT)
+        }
+    }
+
+    impl TryFrom<PxTimestamp> for NaiveDateTime {
+        type Error = Error;
+
+        fn try_from(timestamp: PxTimestamp) -> Result<Self> {
+            let date = NaiveDate::try_from(timestamp.date)?;
+            Ok(date.and_time(timestamp.time.try_into()?))
+        }
+    }
+
+    impl TryFrom<NaiveDateTime> for PxTimestamp {
+        type Error = Error;
+
+        fn try_from(timestamp: NaiveDateTime) -> Result<Self> {
+            Ok(PxTimestamp::new(
+                timestamp.date().try_into()?,
+                timestamp.time().into(),
+            ))
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_conversions {
+    use time::{Date, Month, PrimitiveDateTime, Time};
+
+    use super::{PxDate, PxTime, PxTimestamp};
+    use crate::error::{Error, Result};
+
+    impl TryFrom<PxDate> for Date {
+        type Error = Error;
+
+        fn try_from(date: PxDate) -> Result<Self> {
+            let (year, month, day) = date.ymd();
+            Month::try_from(month as u8)
+                .and_then(|month| Date::from_calendar_date(year, month, day as u8))
+                .map_err(|e| Error::value(format!("{} is not a valid date: {}", date, e)))
+        }
+    }
+
+    impl TryFrom<Date> for PxDate {
+        type Error = Error;
+
+        fn try_from(date: Date) -> Result<Self> {
+            PxDate::from_ymd(date.year(), date.month() as u32, date.day() as u32)
+                .ok_or_else(|| Error::value(format!("{} is out of range", date)))
+        }
+    }
+
+    impl TryFrom<PxTime> for Time {
+        type Error = Error;
+
+        fn try_from(time: PxTime) -> Result<Self> {
+            let (hour, minute, second, milli) = time.hms_milli();
+            Time::from_hms_milli(hour as u8, minute as u8, second as u8, milli as u16)
+                .map_err(|e| Error::value(format!("{} is not a valid time: {}", time, e)))
+        }
+    }
+
+    // Sub-millisecond precision is dropped
+    impl From<Time> for PxTime {
+        fn from(time: Time) -> Self {
+            PxTime::from_hms_milli(
+                time.hour() as u32,
+                time.minute() as u32,
+                time.second() as u32,
+                time.millisecond() as u32,
+            )
+            .unwrap_or(PxTime::MIDNIGHT)
+        }
+    }
+
+    impl TryFrom<PxTimestamp> for PrimitiveDateTime {
+        type Error = Error;
+
+        fn try_from(timestamp: PxTimestamp) -> Result<Self> {
+            Ok(PrimitiveDateTime::new(
+                timestamp.date.try_into()?,
+                timestamp.time.try_into()?,
+            ))
+        }
+    }
+
+    impl TryFrom<PrimitiveDateTime> for PxTimestamp {
+        type Error = Error;
+
+        fn try_from(timestamp: PrimitiveDateTime) -> Result<Self> {
+            Ok(PxTimestamp::new(
+                timestamp.date().try_into()?,
+                timestamp.time().into(),
+            ))
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Error as _, IntoDeserializer, MapAccess, SeqAccess,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::convert::px_to_iso_string;
+use crate::error::{Error, Result};
+use crate::table::{Record, Table};
+use crate::types::Value;
+
+impl Table {
+    // Deserializes every record into `T`, matching Paradox field names to
+    // struct fields (use `#[serde(rename = "...")]` for names with spaces).
+    pub fn deserialize<T: DeserializeOwned>(&self) -> impl Iterator<Item = Result<T>> + '_ {
+        self.records().map(|record| {
+            let record = record?;
+
+            T::deserialize(RecordDeserializer { record })
+                .map_err(|e| e.at_block(record.block()).at_record(record.slot()))
+        })
+    }
+}
+
+pub struct RecordDeserializer<'a> {
+    record: Record<'a>,
+}
+
+impl<'a> RecordDeserializer<'a> {
+    pub fn new(record: Record<'a>) -> Self {
+        RecordDeserializer { record }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for RecordDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(FieldAccess {
+            record: self.record,
+            index: 0,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(FieldAccess {
+            record: self.record,
+            index: 0,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct enum identifier
+        ignored_any
+    }
+}
+
+struct FieldAccess<'a> {
+    record: Record<'a>,
+    index: usize,
+}
+
+impl<'a> FieldAccess<'a> {
+    // The current field, decoded only once it is deserialized
+    fn value(&mut self) -> LazyValue<'a> {
+        let index = self.index;
+        self.index += 1;
+
+        LazyValue {
+            record: self.record,
+            index,
+        }
+    }
+
+    fn field_name(&self) -> &str {
+        &self.record.table().fields()[self.index - 1].name
+    }
+}
+
+impl<'de> MapAccess<'de> for FieldAccess<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.record.table().fields().get(self.index) {
+            Some(field) => seed
+                .deserialize(field.name.as_str().into_deserializer())
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = self.value();
+        seed.deserialize(value)
+            .map_err(|e| e.in_field(self.field_name()))
+    }
+}
+
+impl<'de> SeqAccess<'de> for FieldAccess<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.index >= self.record.table().fields().len() {
+            return Ok(None);
+        }
+
+        let value = self.value();
+        seed.deserialize(value)
+            .map(Some)
+            .map_err(|e| e.in_field(self.field_name()))
+    }
+}
+
+// A field of a record that is decoded, and its blob read from the .MB
+// file, only when the target type asks for it. Fields the struct ignores
+// are skipped without reading them.
+struct LazyValue<'a> {
+    record: Record<'a>,
+    index: usize,
+}
+
+impl LazyValue<'_> {
+    fn fetch(&self) -> Result<ValueDeserializer> {
+        let value = self.record.fetch(self.index)?;
+        Ok(ValueDeserializer { value })
+    }
+}
+
+macro_rules! forward_to_value {
+    ($($method:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            self.fetch()?.$method(visitor)
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for LazyValue<'_> {
+    type Error = Error;
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.fetch()?.deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.fetch()?.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.fetch()?.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        self.fetch()?.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.fetch()?.deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.fetch()?.deserialize_struct(name, fields, visitor)
+    }
+
+    forward_to_value! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16
+        deserialize_i32 deserialize_i64 deserialize_i128 deserialize_u8
+        deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_option deserialize_unit deserialize_seq deserialize_map
+        deserialize_identifier
+    }
+}
+
+// Deserializes a single decoded field. Dates and times are handed out as
+// ISO 8601 strings, which is also what the chrono types deserialize from.
+// Currency values are exact decimal strings for anything but floats.
+pub struct ValueDeserializer {
+    value: Value,
+}
+
+impl ValueDeserializer {
+    fn iso_string(&self) -> Result<String> {
+        px_to_iso_string(&self.value)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Alpha(s) | Value::Memo(s) | Value::Bcd(s) => visitor.visit_string(s),
+            Value::Short(v) => visitor.visit_i16(v),
+            Value::Long(v) | Value::AutoInc(v) => visitor.visit_i32(v),
+            Value::Currency(_) => visitor.visit_string(self.value.to_string()),
+            Value::Number(v) => visitor.visit_f64(v),
+            Value::Logical(v) => visitor.visit_bool(v),
+            Value::Blob(v) | Value::Bytes(v) => visitor.visit_byte_buf(v),
+            Value::Date(_) | Value::Time(_) | Value::Timestamp(_) => {
+                visitor.visit_string(self.iso_string()?)
+            }
+            Value::BlobRef(_) => Err(Error::value("Blob data was not resolved")),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    // Integer targets get the raw day and millisecond counts of dates and times
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Date(v) | Value::Time(v) => visitor.visit_i32(v),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Date(v) | Value::Time(v) => visitor.visit_i64(v as i64),
+            Value::Timestamp(v) => visitor.visit_i64(v as i64),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match &self.value {
+            Value::Bcd(s) => visitor.visit_f64(s.parse().map_err(Error::custom)?),
+            Value::Currency(v) => visitor.visit_f64(v.to_f64()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Alpha values select unit variants by name
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.value {
+            Value::Alpha(s) => visitor.visit_enum(s.into_deserializer()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i128 u8 u16 u32 u64 u128 char str string bytes
+        byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::testing::{field, write_table, TempDir};
+    use crate::types::{PxHeader, Value, PX_FIELD_TYPE_LONG_INT, PX_FIELD_TYPE_MEMO_BLOB};
+    use crate::Table;
+
+    #[derive(Deserialize)]
+    struct Id {
+        #[serde(rename = "Id")]
+        id: i32,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct WithNotes {
+        #[serde(rename = "Id")]
+        id: i32,
+        #[serde(rename = "Notes")]
+        notes: String,
+    }
+
+    // A record whose memo lives in a .MB file the table does not have
+    fn table_with_missing_memo() -> Table {
+        let dir = TempDir::new("de-memo");
+        let fields = vec![
+            field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+            field("Notes", PX_FIELD_TYPE_MEMO_BLOB, 10),
+        ];
+        let rows = [vec![Value::Long(7), Value::Null]];
+        let table = write_table(
+            &dir.join("DE.DB"),
+            &PxHeader::new_table("DE"),
+            fields,
+            0,
+            &rows,
+        );
+
+        let mut data = table.raw().to_vec();
+        // MB offset 0x100 and length 5 in the memo leader
+        let leader = table.header().header_size as usize + 6 + 4;
+        data[leader..leader + 4].copy_from_slice(&0x100u32.to_le_bytes());
+        data[leader + 4..leader + 8].copy_from_slice(&5u32.to_le_bytes());
+        Table::from_bytes(data).unwrap()
+    }
+
+    #[test]
+    fn ignored_fields_are_not_fetched() {
+        let table = table_with_missing_memo();
+
+        let ids = table.deserialize::<Id>().collect::<Result<Vec<_>, _>>();
+        assert_eq!(
+            ids.unwrap().into_iter().map(|r| r.id).collect::<Vec<_>>(),
+            [7]
+        );
+
+        let error = table
+            .deserialize::<WithNotes>()
+            .next()
+            .unwrap()
+            .err()
+            .unwrap();
+        assert_eq!(error.location().field.as_deref(), Some("Notes"));
+    }
+}
@@ -0,0 +1,321 @@
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+
+use crate::table::Table;
+use crate::types::{Value, PX_FILETYPE_DB_INDEXED};
+
+// Field names with their values, serialized as a map in field order
+#[derive(Debug, Default)]
+pub struct Row(pub Vec<(String, Value)>);
+
+impl Row {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // The row as JSON, which tells records apart by their key in
+    // `diff_tables` and `cdc::capture`
+    pub(crate) fn id(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| Error::value(e.to_string()))
+    }
+}
+
+impl Serialize for Row {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in &self.0 {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}={}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldDiff {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+// Positions are 1 based, in the record order of the table the record is
+// found in. Unkeyed tables have an empty key.
+#[derive(Debug, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum RecordChange {
+    Inserted {
+        position: usize,
+        #[serde(skip_serializing_if = "Row::is_empty")]
+        key: Row,
+        record: Row,
+    },
+    Deleted {
+        position: usize,
+        #[serde(skip_serializing_if = "Row::is_empty")]
+        key: Row,
+        record: Row,
+    },
+    Modified {
+        position: usize,
+        key: Row,
+        fields: Vec<FieldDiff>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchedBy {
+    PrimaryKey,
+    RowHash,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DataDiff {
+    pub matched_by: MatchedBy,
+    pub changes: Vec<RecordChange>,
+    // Why the records could not be matched by key
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl DataDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+// Names of the primary key fields, if the table is keyed
+fn key_fields(table: &Table) -> Option<Vec<(String, u8)>> {
+    let header = table.header();
+    if header.file_type != PX_FILETYPE_DB_INDEXED || header.primary_key_fields <= 0 {
+        return None;
+    }
+
+    let keys = table
+        .fields()
+        .iter()
+        .take(header.primary_key_fields as usize)
+        .map(|f| (f.name.clone(), f.field_type))
+        .collect();
+
+    Some(keys)
+}
+
+// Every record with all fields decoded, blobs included.
+fn load(table: &Table) -> Result<Vec<Vec<Value>>> {
+    table
+        .records()
+        .map(|record| record?.fetch_values())
+        .collect()
+}
+
+fn row(table: &Table, values: &[Value], columns: &[usize]) -> Row {
+    Row(columns
+        .iter()
+        .map(|&i| (table.fields()[i].name.clone(), values[i].clone()))
+        .collect())
+}
+
+// Compares the records of two versions of a table. Records are matched by
+// primary key when both tables have the same key, otherwise by the
+// contents of the columns found in both tables, in which case records can
+// only be inserted or deleted.
+pub fn diff_tables(old: &Table, new: &Table) -> Result<DataDiff> {
+    // Columns found in both tables, as (old index, new index)
+    let common = old
+        .fields()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, field)| new.field_index(&field.name).map(|j| (i, j)))
+        .collect::<Vec<_>>();
+
+    let mut warnings = vec![];
+    let keys = match (key_fields(old), key_fields(new)) {
+        (Some(a), Some(b)) if a == b => Some(a.len()),
+        (Some(_), Some(_)) => {
+            warnings.push("the primary keys differ, comparing whole records".to_string());
+            None
+        }
+        (Some(_), None) => {
+            warnings.push("only the old table is keyed, comparing whole records".to_string());
+            None
+        }
+        (None, Some(_)) => {
+            warnings.push("only the new table is keyed, comparing whole records".to_string());
+            None
+        }
+        (None, None) => None,
+    };
+    let matched_by = match keys {
+        Some(_) => MatchedBy::PrimaryKey,
+        None => MatchedBy::RowHash,
+    };
+
+    // The key fields come first in both tables
+    let key_columns = (0..keys.unwrap_or(0)).collect::<Vec<_>>();
+    let old_all = (0..old.fields().len()).collect::<Vec<_>>();
+    let new_all = (0..new.fields().len()).collect::<Vec<_>>();
+
+    let old_rows = load(old)?;
+    let new_rows = load(new)?;
+
+    // Both sides name the columns as the new table does
+    let key_of = |values: &[Value], old_side: bool| {
+        let key = match keys {
+            Some(_) => row(new, values, &key_columns),
+            None => Row(common
+                .iter()
+                .map(|&(i, j)| {
+                    let value = values[if old_side { i } else { j }].clone();
+                    (new.fields()[j].name.clone(), value)
+                })
+                .collect()),
+        };
+        key.id()
+    };
+
+    // Duplicates are possible without a key, so every key maps to a queue
+    let mut unmatched: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, values) in old_rows.iter().enumerate().rev() {
+        unmatched.entry(key_of(values, true)?).or_default().push(i);
+    }
+
+    let mut changes = vec![];
+    let mut deleted = vec![true; old_rows.len()];
+
+    for (j, values) in new_rows.iter().enumerate() {
+        let matched = unmatched
+            .get_mut(&key_of(values, false)?)
+            .and_then(|queue| queue.pop());
+
+        let Some(i) = matched else {
+            changes.push(RecordChange::Inserted {
+                position: j + 1,
+                key: row(new, values, &key_columns),
+                record: row(new, values, &new_all),
+            });
+            continue;
+        };
+        deleted[i] = false;
+
+        let fields = common
+            .iter()
+            .filter(|&&(a, b)| old_rows[i][a] != values[b])
+            .map(|&(a, b)| FieldDiff {
+                field: new.fields()[b].name.clone(),
+                old: old_rows[i][a].clone(),
+                new: values[b].clone(),
+            })
+            .collect::<Vec<_>>();
+
+        if !fields.is_empty() {
+            changes.push(RecordChange::Modified {
+                position: j + 1,
+                key: row(new, values, &key_columns),
+                fields,
+            });
+        }
+    }
+
+    for (i, values) in old_rows.iter().enumerate() {
+        if deleted[i] {
+            changes.push(RecordChange::Deleted {
+                position: i + 1,
+                key: row(old, values, &key_columns),
+                record: row(old, values, &old_all),
+            });
+        }
+    }
+
+    Ok(DataDiff {
+        matched_by,
+        changes,
+        warnings,
+    })
+}
+
+impl Display for RecordChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let title = |f: &mut fmt::Formatter<'_>, sign, position, key: &Row| {
+            if key.is_empty() {
+                writeln!(f, "{} record {}", sign, position)
+            } else {
+                writeln!(f, "{} {}", sign, key)
+            }
+        };
+
+        match self {
+            RecordChange::Inserted {
+                position,
+                key,
+                record,
+            } => {
+                title(f, '+', position, key)?;
+                record
+                    .0
+                    .iter()
+                    .try_for_each(|(name, value)| writeln!(f, "    {}: {}", name, value))
+            }
+            RecordChange::Deleted {
+                position,
+                key,
+                record,
+            } => {
+                title(f, '-', position, key)?;
+                record
+                    .0
+                    .iter()
+                    .try_for_each(|(name, value)| writeln!(f, "    {}: {}", name, value))
+            }
+            RecordChange::Modified {
+                position,
+                key,
+                fields,
+            } => {
+                title(f, '~', position, key)?;
+                fields.iter().try_for_each(|diff| {
+                    writeln!(f, "    {}: {} -> {}", diff.field, diff.old, diff.new)
+                })
+            }
+        }
+    }
+}
+
+impl Display for DataDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "Records are identical");
+        }
+
+        let count =
+            |pred: fn(&RecordChange) -> bool| self.changes.iter().filter(|c| pred(c)).count();
+        writeln!(
+            f,
+            "{} inserted, {} deleted, {} modified (matched by {})",
+            count(|c| matches!(c, RecordChange::Inserted { .. })),
+            count(|c| matches!(c, RecordChange::Deleted { .. })),
+            count(|c| matches!(c, RecordChange::Modified { .. })),
+            match self.matched_by {
+                MatchedBy::PrimaryKey => "primary key",
+                MatchedBy::RowHash => "row contents",
+            }
+        )?;
+
+        self.changes.iter().try_for_each(|c| write!(f, "{}", c))
+    }
+}
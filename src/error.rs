@@ -0,0 +1,261 @@
+use std::fmt::{self, Display};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Where in a file an error happened, as far as it is known. Records are
+// given by their slot in the block, offsets are absolute positions in the
+// file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Location {
+    pub file: Option<PathBuf>,
+    pub block: Option<u16>,
+    pub record: Option<usize>,
+    pub field: Option<String>,
+    pub offset: Option<u64>,
+}
+
+impl Location {
+    pub fn is_empty(&self) -> bool {
+        *self == Location::default()
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(file) = &self.file {
+            parts.push(format!("file {}", file.display()));
+        }
+        if let Some(block) = self.block {
+            parts.push(format!("block {}", block));
+        }
+        if let Some(record) = self.record {
+            parts.push(format!("record {}", record));
+        }
+        if let Some(field) = &self.field {
+            parts.push(format!("field '{}'", field));
+        }
+        if let Some(offset) = self.offset {
+            parts.push(format!("offset 0x{:x}", offset));
+        }
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    // Reading or writing a file failed
+    Io {
+        source: io::Error,
+        location: Box<Location>,
+    },
+    // The file is not laid out as a Paradox file should be
+    Corrupt {
+        message: String,
+        location: Box<Location>,
+    },
+    // A valid file using a feature pxrs does not handle
+    Unsupported {
+        message: String,
+        location: Box<Location>,
+    },
+    // A field value that cannot be decoded, or a value that cannot be
+    // stored in a field
+    Value {
+        message: String,
+        location: Box<Location>,
+    },
+    // Invalid arguments: unknown fields, malformed expressions or queries,
+    // mismatching structs
+    Invalid {
+        message: String,
+        location: Box<Location>,
+    },
+    // Another program has the table locked
+    Locked {
+        message: String,
+        location: Box<Location>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn corrupt(message: impl Into<String>) -> Self {
+        Error::Corrupt {
+            message: message.into(),
+            location: Box::default(),
+        }
+    }
+
+    pub fn unsupported(message: impl Into<String>) -> Self {
+        Error::Unsupported {
+            message: message.into(),
+            location: Box::default(),
+        }
+    }
+
+    pub fn value(message: impl Into<String>) -> Self {
+        Error::Value {
+            message: message.into(),
+            location: Box::default(),
+        }
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Error::Invalid {
+            message: message.into(),
+            location: Box::default(),
+        }
+    }
+
+    pub fn locked(message: impl Into<String>) -> Self {
+        Error::Locked {
+            message: message.into(),
+            location: Box::default(),
+        }
+    }
+
+    pub fn location(&self) -> &Location {
+        match self {
+            Error::Io { location, .. }
+            | Error::Corrupt { location, .. }
+            | Error::Unsupported { location, .. }
+            | Error::Value { location, .. }
+            | Error::Invalid { location, .. }
+            | Error::Locked { location, .. } => location,
+        }
+    }
+
+    fn location_mut(&mut self) -> &mut Location {
+        match self {
+            Error::Io { location, .. }
+            | Error::Corrupt { location, .. }
+            | Error::Unsupported { location, .. }
+            | Error::Value { location, .. }
+            | Error::Invalid { location, .. }
+            | Error::Locked { location, .. } => location,
+        }
+    }
+
+    // The message without the location
+    pub fn message(&self) -> String {
+        match self {
+            Error::Io { source, .. } => source.to_string(),
+            Error::Corrupt { message, .. }
+            | Error::Unsupported { message, .. }
+            | Error::Value { message, .. }
+            | Error::Invalid { message, .. }
+            | Error::Locked { message, .. } => message.clone(),
+        }
+    }
+
+    // Context is added on the way up, the innermost, most precise value
+    // is kept.
+    pub fn in_file(mut self, path: &Path) -> Self {
+        let location = self.location_mut();
+        location.file.get_or_insert_with(|| path.to_path_buf());
+        self
+    }
+
+    pub fn at_block(mut self, block: u16) -> Self {
+        self.location_mut().block.get_or_insert(block);
+        self
+    }
+
+    pub fn at_record(mut self, record: usize) -> Self {
+        self.location_mut().record.get_or_insert(record);
+        self
+    }
+
+    pub fn in_field(mut self, field: &str) -> Self {
+        let location = self.location_mut();
+        location.field.get_or_insert_with(|| field.to_string());
+        self
+    }
+
+    pub fn at_offset(mut self, offset: u64) -> Self {
+        self.location_mut().offset.get_or_insert(offset);
+        self
+    }
+}
+
+// I/O errors are not Clone, their copy keeps the kind and the message
+impl Clone for Error {
+    fn clone(&self) -> Self {
+        match self {
+            Error::Io { source, location } => Error::Io {
+                source: io::Error::new(source.kind(), source.to_string()),
+                location: location.clone(),
+            },
+            Error::Corrupt { message, location } => Error::Corrupt {
+                message: message.clone(),
+                location: location.clone(),
+            },
+            Error::Unsupported { message, location } => Error::Unsupported {
+                message: message.clone(),
+                location: location.clone(),
+            },
+            Error::Value { message, location } => Error::Value {
+                message: message.clone(),
+                location: location.clone(),
+            },
+            Error::Invalid { message, location } => Error::Invalid {
+                message: message.clone(),
+                location: location.clone(),
+            },
+            Error::Locked { message, location } => Error::Locked {
+                message: message.clone(),
+                location: location.clone(),
+            },
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())?;
+        if !self.location().is_empty() {
+            write!(f, " ({})", self.location())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Error::Io {
+            source,
+            location: Box::default(),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        let kind = match &error {
+            Error::Io { source, .. } => source.kind(),
+            Error::Corrupt { .. } | Error::Value { .. } => io::ErrorKind::InvalidData,
+            Error::Unsupported { .. } => io::ErrorKind::Unsupported,
+            Error::Invalid { .. } => io::ErrorKind::InvalidInput,
+            Error::Locked { .. } => io::ErrorKind::ResourceBusy,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
+impl serde::de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::value(msg.to_string())
+    }
+}
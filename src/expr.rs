@@ -0,0 +1,722 @@
+use crate::error::{Error, Result};
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+
+use crate::collation::Collation;
+use crate::datetime::{PxDate, PxTime, PxTimestamp};
+use crate::table::Record;
+use crate::types::{PxFieldInfo, Value};
+
+// Filter expressions over the fields of a record, e.g.
+//
+//   "Balance" > 1000 AND City = 'Madrid' AND "Created" >= date '1998-01-01'
+//
+// Field names are double quoted or bare words, strings single quoted.
+// Blank fields are null, as in Paradox: comparing them yields unknown,
+// which does not match, so they can only be found with IS NULL.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Word(String),
+    Quoted(String),
+    Str(String),
+    Number(f64),
+    Symbol(&'static str),
+}
+
+// Longest first, so that "<=" is not read as "<"
+const SYMBOLS: [&str; 14] = [
+    "<=", ">=", "<>", "!=", "=", "<", ">", "(", ")", ",", ".", "*", "-", "+",
+];
+
+// Words that can not be used as bare field names
+const KEYWORDS: [&str; 11] = [
+    "AND",
+    "OR",
+    "NOT",
+    "IS",
+    "NULL",
+    "LIKE",
+    "TRUE",
+    "FALSE",
+    "DATE",
+    "TIME",
+    "TIMESTAMP",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = vec![];
+    let mut chars = source.char_indices().peekable();
+
+    // Reads up to the closing quote, a doubled quote standing for itself
+    let quoted = |chars: &mut std::iter::Peekable<std::str::CharIndices>, quote, start| {
+        let mut text = String::new();
+        loop {
+            match chars.next() {
+                Some((_, c)) if c == quote => {
+                    if chars.peek().map(|&(_, c)| c) != Some(quote) {
+                        return Ok(text);
+                    }
+                    chars.next();
+                    text.push(quote);
+                }
+                Some((_, c)) => text.push(c),
+                None => return Err(syntax_error("Unterminated quote", start)),
+            }
+        }
+    };
+
+    while let Some(&(start, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '"' | '\'' => {
+                chars.next();
+                let text = quoted(&mut chars, c, start)?;
+                if c == '"' {
+                    Token::Quoted(text)
+                } else {
+                    Token::Str(text)
+                }
+            }
+            c if c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(&(_, c)) = chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    number.push(c);
+                    chars.next();
+                }
+                let number = number
+                    .parse()
+                    .map_err(|_| syntax_error("Invalid number", start))?;
+                Token::Number(number)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = String::new();
+                while let Some(&(_, c)) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                Token::Word(word)
+            }
+            _ => {
+                let rest = &source[start..];
+                let symbol = SYMBOLS
+                    .iter()
+                    .find(|s| rest.starts_with(*s))
+                    .ok_or_else(|| syntax_error(&format!("Unexpected '{}'", c), start))?;
+                for _ in 0..symbol.len() {
+                    chars.next();
+                }
+                Token::Symbol(symbol)
+            }
+        };
+
+        tokens.push((token, start));
+    }
+
+    Ok(tokens)
+}
+
+fn syntax_error(msg: &str, position: usize) -> Error {
+    Error::invalid(format!("{} at position {}", msg, position + 1))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn test(self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Eq => ordering.is_eq(),
+            CompareOp::Ne => ordering.is_ne(),
+            CompareOp::Lt => ordering.is_lt(),
+            CompareOp::Le => ordering.is_le(),
+            CompareOp::Gt => ordering.is_gt(),
+            CompareOp::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    // A field by name, optionally qualified by a table, replaced by its
+    // column by `bind`
+    Field(Option<String>, String),
+    Column(usize),
+    Literal(Value),
+    Compare(Box<Expr>, CompareOp, Box<Expr>),
+    // The bool is set for NOT LIKE and IS NOT NULL
+    Like(Box<Expr>, String, bool),
+    IsNull(Box<Expr>, bool),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+pub struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    pub fn new(source: &str) -> Result<Self> {
+        Ok(Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+            end: source.len(),
+        })
+    }
+
+    pub fn peek(&self) -> Option<&Token> {
+        self.peek_at(0)
+    }
+
+    pub fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset).map(|(token, _)| token)
+    }
+
+    pub fn next_token(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    // Error located at the current token
+    pub fn error(&self, msg: &str) -> Error {
+        let position = self.tokens.get(self.pos).map_or(self.end, |&(_, p)| p);
+        syntax_error(msg, position)
+    }
+
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    pub fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    pub fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if !self.eat_keyword(keyword) {
+            return Err(self.error(&format!("Expected {}", keyword)));
+        }
+        Ok(())
+    }
+
+    pub fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    pub fn expect_symbol(&mut self, symbol: &str) -> Result<()> {
+        if !self.eat_symbol(symbol) {
+            return Err(self.error(&format!("Expected '{}'", symbol)));
+        }
+        Ok(())
+    }
+
+    // Parses a whole expression, which must use up the input.
+    pub fn parse(source: &str) -> Result<Expr> {
+        let mut parser = Parser::new(source)?;
+        let expr = parser.parse_expr()?;
+        if !parser.at_end() {
+            return Err(parser.error("Unexpected input"));
+        }
+        Ok(expr)
+    }
+
+    pub fn parse_expr(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.eat_keyword("OR") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_not()?;
+        while self.eat_keyword("AND") {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_predicate()
+    }
+
+    fn parse_predicate(&mut self) -> Result<Expr> {
+        let left = self.parse_operand()?;
+
+        let op = match self.peek() {
+            Some(Token::Symbol("=")) => Some(CompareOp::Eq),
+            Some(Token::Symbol("<>" | "!=")) => Some(CompareOp::Ne),
+            Some(Token::Symbol("<")) => Some(CompareOp::Lt),
+            Some(Token::Symbol("<=")) => Some(CompareOp::Le),
+            Some(Token::Symbol(">")) => Some(CompareOp::Gt),
+            Some(Token::Symbol(">=")) => Some(CompareOp::Ge),
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.pos += 1;
+            let right = self.parse_operand()?;
+            return Ok(Expr::Compare(Box::new(left), op, Box::new(right)));
+        }
+
+        if self.eat_keyword("IS") {
+            let negated = self.eat_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Expr::IsNull(Box::new(left), negated));
+        }
+
+        let negated = self.is_keyword("NOT")
+            && matches!(self.peek_at(1), Some(Token::Word(w)) if w.eq_ignore_ascii_case("LIKE"));
+        if negated {
+            self.pos += 1;
+        }
+        if self.eat_keyword("LIKE") {
+            return match self.next_token() {
+                Some(Token::Str(pattern)) => Ok(Expr::Like(Box::new(left), pattern, negated)),
+                _ => {
+                    self.pos -= 1;
+                    Err(self.error("Expected a pattern string"))
+                }
+            };
+        }
+
+        Ok(left)
+    }
+
+    fn parse_operand(&mut self) -> Result<Expr> {
+        let Some(token) = self.next_token() else {
+            return Err(self.error("Unexpected end of expression"));
+        };
+
+        let literal = |value| Ok(Expr::Literal(value));
+
+        match token {
+            Token::Symbol("(") => {
+                let expr = self.parse_expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Token::Symbol("-") => match self.next_token() {
+                Some(Token::Number(n)) => literal(Value::Number(-n)),
+                _ => {
+                    self.pos -= 1;
+                    Err(self.error("Expected a number"))
+                }
+            },
+            Token::Number(n) => literal(Value::Number(n)),
+            // Paradox does not tell empty strings from blanks
+            Token::Str(s) if s.is_empty() => literal(Value::Null),
+            Token::Str(s) => literal(Value::Alpha(s)),
+            Token::Quoted(name) => Ok(self.field(name)),
+            Token::Word(word) => {
+                let keyword = word.to_ascii_uppercase();
+                match keyword.as_str() {
+                    "NULL" => literal(Value::Null),
+                    "TRUE" => literal(Value::Logical(true)),
+                    "FALSE" => literal(Value::Logical(false)),
+                    "DATE" | "TIME" | "TIMESTAMP" => self.typed_literal(&keyword),
+                    _ if KEYWORDS.contains(&keyword.as_str()) => {
+                        self.pos -= 1;
+                        Err(self.error(&format!("Unexpected {}", keyword)))
+                    }
+                    _ => Ok(self.field(word)),
+                }
+            }
+            Token::Symbol(_) => {
+                self.pos -= 1;
+                Err(self.error("Expected a field or a value"))
+            }
+        }
+    }
+
+    // A field name, or a table name if followed by a dot and a field
+    fn field(&mut self, name: String) -> Expr {
+        let field = match (self.peek(), self.peek_at(1)) {
+            (Some(Token::Symbol(".")), Some(Token::Word(field) | Token::Quoted(field))) => {
+                field.clone()
+            }
+            _ => return Expr::Field(None, name),
+        };
+
+        self.pos += 2;
+        Expr::Field(Some(name), field)
+    }
+
+    // date 'YYYY-MM-DD', time 'HH:MM:SS[.mmm]' and timestamp with both
+    fn typed_literal(&mut self, keyword: &str) -> Result<Expr> {
+        let Some(Token::Str(text)) = self.peek().cloned() else {
+            return Err(self.error(&format!("Expected a string after {}", keyword)));
+        };
+
+        let value = match keyword {
+            "DATE" => text.parse::<PxDate>().map(Value::from),
+            "TIME" => text.parse::<PxTime>().map(Value::from),
+            _ => text.parse::<PxTimestamp>().map(Value::from),
+        }
+        .ok();
+
+        let value = value.ok_or_else(|| self.error(&format!("Invalid {} literal", keyword)))?;
+        self.pos += 1;
+        Ok(Expr::Literal(value))
+    }
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Alpha(_) | Value::Memo(_) => "text",
+        Value::Date(_) => "date",
+        Value::Time(_) => "time",
+        Value::Timestamp(_) => "timestamp",
+        Value::Logical(_) => "logical",
+        Value::Blob(_) | Value::Bytes(_) | Value::BlobRef(_) => "binary",
+        _ => "number",
+    }
+}
+
+pub fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Short(v) => Some(*v as f64),
+        Value::Long(v) | Value::AutoInc(v) => Some(*v as f64),
+        Value::Currency(v) => Some(v.to_f64()),
+        Value::Number(v) => Some(*v),
+        Value::Bcd(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+// Milliseconds since 1/1/1 of dates and timestamps
+fn timestamp(value: &Value) -> Option<f64> {
+    match value {
+        Value::Date(days) => Some(*days as f64 * 86_400_000.0),
+        Value::Timestamp(ms) => Some(*ms),
+        _ => None,
+    }
+}
+
+// Orders two values of compatible types, None if either is null.
+pub fn compare_values(a: &Value, b: &Value) -> Result<Option<Ordering>> {
+    let ordering = match (a, b) {
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Alpha(x) | Value::Memo(x), Value::Alpha(y) | Value::Memo(y)) => Some(x.cmp(y)),
+        (Value::Logical(x), Value::Logical(y)) => Some(x.cmp(y)),
+        (Value::Time(x), Value::Time(y)) => Some(x.cmp(y)),
+        (Value::Date(x), Value::Date(y)) => Some(x.cmp(y)),
+        (Value::Currency(x), Value::Currency(y)) => Some(x.cmp(y)),
+        (Value::Blob(x) | Value::Bytes(x), Value::Blob(y) | Value::Bytes(y)) => Some(x.cmp(y)),
+        _ => {
+            let pair = as_number(a)
+                .zip(as_number(b))
+                .or_else(|| timestamp(a).zip(timestamp(b)));
+            match pair {
+                Some((x, y)) => x.partial_cmp(&y),
+                None => {
+                    return Err(Error::invalid(format!(
+                        "Can not compare {} with {}",
+                        kind(a),
+                        kind(b)
+                    )))
+                }
+            }
+        }
+    };
+
+    Ok(ordering)
+}
+
+// SQL LIKE: % matches any run of characters, _ a single one
+fn like(text: &str, pattern: &str) -> bool {
+    let text = text.chars().collect::<Vec<_>>();
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let (mut t, mut p) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '_' || pattern[p] == text[t]) {
+            t += 1;
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == '%' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((bp, bt)) = backtrack {
+            backtrack = Some((bp, bt + 1));
+            p = bp + 1;
+            t = bt + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '%')
+}
+
+// Truth value of a condition, None when unknown
+fn truth(value: &Value) -> Result<Option<bool>> {
+    match value {
+        Value::Logical(b) => Ok(Some(*b)),
+        Value::Null => Ok(None),
+        value => Err(Error::invalid(format!(
+            "Expected a condition, got a {} value",
+            kind(value)
+        ))),
+    }
+}
+
+fn logical(value: Option<bool>) -> Value {
+    value.map_or(Value::Null, Value::Logical)
+}
+
+impl Expr {
+    // Replaces field names by columns, `resolve` looking up the index of a
+    // (table, field) pair.
+    pub fn bind(&mut self, resolve: &dyn Fn(Option<&str>, &str) -> Result<usize>) -> Result<()> {
+        match self {
+            Expr::Field(table, name) => {
+                *self = Expr::Column(resolve(table.as_deref(), name)?);
+                Ok(())
+            }
+            Expr::Column(_) | Expr::Literal(_) => Ok(()),
+            Expr::Like(e, _, _) | Expr::IsNull(e, _) | Expr::Not(e) => e.bind(resolve),
+            Expr::Compare(a, _, b) | Expr::And(a, b) | Expr::Or(a, b) => {
+                a.bind(resolve)?;
+                b.bind(resolve)
+            }
+        }
+    }
+
+    // Evaluates the expression, `column` returning the value of a bound
+    // column. Conditions evaluate to logical values or null when unknown.
+    pub fn eval(&self, column: &mut dyn FnMut(usize) -> Result<Value>) -> Result<Value> {
+        self.eval_in(Collation::Ascii, column)
+    }
+
+    // Like `eval`, comparing Alpha values in `collation` order
+    pub fn eval_in(
+        &self,
+        collation: Collation,
+        column: &mut dyn FnMut(usize) -> Result<Value>,
+    ) -> Result<Value> {
+        let mut eval = |e: &Expr| e.eval_in(collation, column);
+        Ok(match self {
+            Expr::Field(_, name) => {
+                return Err(Error::invalid(format!("Field '{}' is not bound", name)))
+            }
+            Expr::Column(i) => column(*i)?,
+            Expr::Literal(value) => value.clone(),
+            Expr::Compare(a, op, b) => {
+                let (a, b) = (eval(a)?, eval(b)?);
+                let ordering = collation.compare_values(&a, &b)?;
+                logical(ordering.map(|o| op.test(o)))
+            }
+            Expr::Like(e, pattern, negated) => match eval(e)? {
+                Value::Null => Value::Null,
+                Value::Alpha(s) | Value::Memo(s) => Value::Logical(like(&s, pattern) != *negated),
+                value => {
+                    return Err(Error::invalid(format!(
+                        "LIKE needs a text value, got a {} value",
+                        kind(&value)
+                    )))
+                }
+            },
+            Expr::IsNull(e, negated) => Value::Logical((eval(e)? == Value::Null) != *negated),
+            Expr::Not(e) => logical(truth(&eval(e)?)?.map(|b| !b)),
+            Expr::And(a, b) => match truth(&eval(a)?)? {
+                Some(false) => Value::Logical(false),
+                left => match truth(&eval(b)?)? {
+                    Some(false) => Value::Logical(false),
+                    right => logical(left.zip(right).map(|(a, b)| a && b)),
+                },
+            },
+            Expr::Or(a, b) => match truth(&eval(a)?)? {
+                Some(true) => Value::Logical(true),
+                left => match truth(&eval(b)?)? {
+                    Some(true) => Value::Logical(true),
+                    right => logical(left.zip(right).map(|(a, b)| a || b)),
+                },
+            },
+        })
+    }
+
+    // Bound columns the expression reads
+    pub fn columns(&self) -> Vec<usize> {
+        match self {
+            Expr::Column(i) => vec![*i],
+            Expr::Field(..) | Expr::Literal(_) => vec![],
+            Expr::Like(e, _, _) | Expr::IsNull(e, _) | Expr::Not(e) => e.columns(),
+            Expr::Compare(a, _, b) | Expr::And(a, b) | Expr::Or(a, b) => {
+                let mut columns = a.columns();
+                columns.extend(b.columns());
+                columns
+            }
+        }
+    }
+
+    // Whether the condition holds, unknown counting as false
+    pub fn matches(&self, column: &mut dyn FnMut(usize) -> Result<Value>) -> Result<bool> {
+        self.matches_in(Collation::Ascii, column)
+    }
+
+    pub fn matches_in(
+        &self,
+        collation: Collation,
+        column: &mut dyn FnMut(usize) -> Result<Value>,
+    ) -> Result<bool> {
+        Ok(truth(&self.eval_in(collation, column)?)? == Some(true))
+    }
+}
+
+// A parsed `--where` expression bound to the fields of a table
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    pub fn new(source: &str, fields: &[PxFieldInfo]) -> Result<Self> {
+        let mut expr = Parser::parse(source)?;
+        expr.bind(&|table, name| match table {
+            None => field_position(fields, name).ok_or_else(|| unknown_field(name)),
+            Some(table) => Err(unknown_field(&format!("{}.{}", table, name))),
+        })?;
+        Ok(Filter { expr })
+    }
+
+    // Alpha values compare in `collation`, the one of the table
+    pub fn matches(&self, collation: Collation, record: &Record) -> Result<bool> {
+        self.expr.matches_in(collation, &mut |i| record.fetch(i))
+    }
+
+    // Matches the values of a record already decoded
+    pub fn matches_values(&self, collation: Collation, values: &[Value]) -> Result<bool> {
+        self.expr
+            .matches_in(collation, &mut |i| Ok(values[i].clone()))
+    }
+}
+
+// Paradox field names are case insensitive
+pub fn field_position(fields: &[PxFieldInfo], name: &str) -> Option<usize> {
+    fields.iter().position(|f| f.name == name).or_else(|| {
+        fields
+            .iter()
+            .position(|f| f.name.eq_ignore_ascii_case(name))
+    })
+}
+
+pub fn unknown_field(name: &str) -> Error {
+    Error::invalid(format!("Unknown field '{}'", name))
+}
+
+impl Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            CompareOp::Eq => "=",
+            CompareOp::Ne => "<>",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+// Writes the expression back in the syntax it is parsed from
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |f: &mut fmt::Formatter<'_>, name: &str| {
+            let bare = name.chars().all(|c| c.is_alphanumeric() || c == '_')
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && !KEYWORDS.contains(&name.to_ascii_uppercase().as_str());
+            if bare {
+                write!(f, "{}", name)
+            } else {
+                write!(f, "\"{}\"", name.replace('"', "\"\""))
+            }
+        };
+        // AND and OR operands are parenthesized to keep the grouping
+        let operand = |f: &mut fmt::Formatter<'_>, e: &Expr| match e {
+            Expr::And(..) | Expr::Or(..) => write!(f, "({})", e),
+            e => write!(f, "{}", e),
+        };
+
+        match self {
+            Expr::Field(table, field) => {
+                if let Some(table) = table {
+                    name(f, table)?;
+                    write!(f, ".")?;
+                }
+                name(f, field)
+            }
+            Expr::Column(i) => write!(f, "#{}", i),
+            Expr::Literal(value) => match value {
+                Value::Null => write!(f, "NULL"),
+                Value::Alpha(s) => write!(f, "'{}'", s.replace('\'', "''")),
+                Value::Date(_) => write!(f, "date '{}'", value),
+                Value::Time(_) => write!(f, "time '{}'", value),
+                Value::Timestamp(_) => write!(f, "timestamp '{}'", value),
+                Value::Logical(b) => write!(f, "{}", if *b { "TRUE" } else { "FALSE" }),
+                value => write!(f, "{}", value),
+            },
+            Expr::Compare(a, op, b) => write!(f, "{} {} {}", a, op, b),
+            Expr::Like(e, pattern, negated) => write!(
+                f,
+                "{} {}LIKE '{}'",
+                e,
+                if *negated { "NOT " } else { "" },
+                pattern.replace('\'', "''")
+            ),
+            Expr::IsNull(e, negated) => {
+                write!(f, "{} IS {}NULL", e, if *negated { "NOT " } else { "" })
+            }
+            Expr::Not(e) => {
+                write!(f, "NOT ")?;
+                operand(f, e)
+            }
+            Expr::And(a, b) | Expr::Or(a, b) => {
+                operand(f, a)?;
+                write!(
+                    f,
+                    " {} ",
+                    if matches!(self, Expr::And(..)) {
+                        "AND"
+                    } else {
+                        "OR"
+                    }
+                )?;
+                operand(f, b)
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+use crate::table::Table;
+
+// What a table looked like when it was read, to tell whether it changed
+// since. Paradox bumps the change counters and the update time of the
+// header on most writes, the checksums catch the writes that don't.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub file_size: u64,
+    pub change_count1: u8,
+    pub change_count2: u8,
+    pub change_count4: u16,
+    pub file_update_time: u32,
+    pub num_records: u32,
+    // Of the header and of every block, in file order
+    pub checksum: u64,
+    // Of the .MB file, None without one
+    pub blob_checksum: Option<u64>,
+}
+
+// FNV-1a, which unlike the hasher of std gives the same value in every
+// build, so fingerprints can be stored
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+pub(crate) fn checksum(data: &[u8]) -> u64 {
+    let mut hash = Fnv::new();
+    hash.write(data);
+    hash.0
+}
+
+impl Table {
+    pub fn fingerprint(&self) -> Fingerprint {
+        let header = self.header();
+        let data = self.raw();
+        let header_size = (header.header_size.max(0) as usize).min(data.len());
+        let block_size = header.block_size().max(1);
+
+        // Combines the checksums of the header and of every block, a
+        // partial block at the end counting as one
+        let mut hash = Fnv::new();
+        hash.write(&checksum(&data[..header_size]).to_le_bytes());
+        for block in data[header_size..].chunks(block_size) {
+            hash.write(&checksum(block).to_le_bytes());
+        }
+
+        Fingerprint {
+            file_size: data.len() as u64,
+            change_count1: header.change_count1,
+            change_count2: header.change_count2,
+            change_count4: header.change_count4,
+            file_update_time: header.file_update_time,
+            num_records: header.num_records,
+            checksum: hash.0,
+            blob_checksum: self.raw_blobs().map(checksum),
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use crate::error::{Error, Result};
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+use crate::collation::Collation;
+use crate::table::{companion_path, Record, Records, Table};
+use crate::types::{PxBlocks, Value, PX_FILETYPE_DB_INDEXED, PX_FILETYPE_PX};
+
+// An entry of the bottom level of the primary index: the first key of a
+// data block, the number of the block and of the records in it.
+#[derive(Debug, Clone)]
+pub struct IndexEntry {
+    pub key: Vec<Value>,
+    pub block: u16,
+    pub count: u16,
+}
+
+// What `Table::primary_index` found next to the table
+pub(crate) enum CachedIndex {
+    Missing,
+    OutOfDate,
+    Unreadable(Error),
+    Ready(Box<PrimaryIndex>),
+}
+
+impl CachedIndex {
+    fn ready(&self) -> Option<&PrimaryIndex> {
+        match self {
+            CachedIndex::Ready(index) => Some(index),
+            _ => None,
+        }
+    }
+}
+
+// The primary index of a keyed table: a tree of index blocks in a .PX
+// file. Every entry holds the first key of the block it points to and the
+// number of records below it. Entries of the last level point to data
+// blocks, those above it to index blocks of the next level.
+pub struct PrimaryIndex {
+    px: Table,
+    keys: usize,
+    collation: Collation,
+}
+
+// Index records end with the block number, the count and an unknown short
+fn short(value: &Value) -> u16 {
+    match value {
+        Value::Short(v) => *v as u16,
+        _ => 0,
+    }
+}
+
+// Counts above the last level are shorts that cannot hold large sums,
+// pxrs writes the largest short then
+const UNKNOWN_COUNT: u16 = i16::MAX as u16;
+
+impl PrimaryIndex {
+    pub fn from_table(px: Table) -> Result<Self> {
+        if px.header().file_type != PX_FILETYPE_PX {
+            return Err(Error::corrupt("Not a primary index file"));
+        }
+        let keys = px
+            .fields()
+            .len()
+            .checked_sub(3)
+            .ok_or_else(|| Error::corrupt("Index records lack their block and count"))?;
+
+        Ok(PrimaryIndex {
+            collation: px.collation(),
+            px,
+            keys,
+        })
+    }
+
+    // The order of the keys, from the sort order of the .PX header
+    pub fn collation(&self) -> Collation {
+        self.collation
+    }
+
+    fn levels(&self) -> u8 {
+        self.px.header().index_levels.max(1)
+    }
+
+    fn root(&self) -> Option<u16> {
+        match self.px.header().index_root_block {
+            0 => None,
+            root => Some(root),
+        }
+    }
+
+    // The entries of an index block, blank ones skipped
+    fn block_entries(&self, number: u16) -> Result<Vec<IndexEntry>> {
+        let block = self.px.block(number)?;
+        let mut entries = vec![];
+
+        for slot in 0..block.num_recs_in_block {
+            let Some(record) = self.px.record(&block, slot) else {
+                break;
+            };
+            let mut values = record.values()?;
+            let block = short(&values[self.keys]);
+            let count = short(&values[self.keys + 1]);
+            values.truncate(self.keys);
+
+            if block != 0 {
+                entries.push(IndexEntry {
+                    key: values,
+                    block,
+                    count,
+                });
+            }
+        }
+
+        Ok(entries)
+    }
+
+    // Records below an entry at `level`, the last level being 1
+    fn count(&self, entry: &IndexEntry, level: u8) -> Result<u64> {
+        if level == 1 || entry.count != UNKNOWN_COUNT {
+            return Ok(entry.count as u64);
+        }
+
+        let mut count = 0;
+        for child in self.block_entries(entry.block)? {
+            count += self.count(&child, level - 1)?;
+        }
+        Ok(count)
+    }
+
+    // The entries of the last level, in key order
+    pub fn entries(&self) -> Result<Vec<IndexEntry>> {
+        let mut level = match self.root() {
+            Some(root) => self.block_entries(root)?,
+            None => return Ok(vec![]),
+        };
+
+        for _ in 1..self.levels() {
+            let mut next = vec![];
+            for entry in &level {
+                next.extend(self.block_entries(entry.block)?);
+            }
+            level = next;
+        }
+
+        Ok(level)
+    }
+
+    // Number of records the index accounts for
+    pub fn num_records(&self) -> Result<u64> {
+        let Some(root) = self.root() else {
+            return Ok(0);
+        };
+
+        let mut count = 0;
+        for entry in self.block_entries(root)? {
+            count += self.count(&entry, self.levels())?;
+        }
+        Ok(count)
+    }
+
+    // The data block where records whose first key field is not below
+    // `lo` start, found by descending from the root. At every level the
+    // entry before the first one whose key is not below `lo` is taken: with
+    // composite keys the first field alone can repeat across blocks, so
+    // the block starting at `lo` could miss some.
+    pub fn first_block(&self, lo: Bound<&Value>) -> Result<Option<u16>> {
+        let Some(mut number) = self.root() else {
+            return Ok(None);
+        };
+
+        for _ in 0..self.levels() {
+            let entries = self.block_entries(number)?;
+            let mut below = 0;
+            if let Bound::Included(lo) | Bound::Excluded(lo) = lo {
+                for entry in &entries {
+                    let first = entry.key.first().unwrap_or(&Value::Null);
+                    if self.collation.key_order(first, lo)?.is_ge() {
+                        break;
+                    }
+                    below += 1;
+                }
+            }
+
+            match entries.get(below.max(1) - 1) {
+                Some(entry) => number = entry.block,
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(number))
+    }
+
+    // Data block and slot of the record at position `n` in key order,
+    // descending from the root by the record counts
+    pub fn record_position(&self, mut n: u64) -> Result<Option<(u16, usize)>> {
+        let Some(mut number) = self.root() else {
+            return Ok(None);
+        };
+
+        'levels: for level in (1..=self.levels()).rev() {
+            for entry in self.block_entries(number)? {
+                let count = self.count(&entry, level)?;
+                if n < count {
+                    number = entry.block;
+                    continue 'levels;
+                }
+                n -= count;
+            }
+            return Ok(None);
+        }
+
+        Ok(Some((number, n as usize)))
+    }
+}
+
+impl Table {
+    // The primary index from the .PX file next to a keyed table, read on
+    // first use and kept with the table. An index that does not account for
+    // every record is out of date and ignored. One that cannot be read is
+    // not read again, every call returns the error.
+    pub fn primary_index(&self) -> Result<Option<&PrimaryIndex>> {
+        let index = self.index.get_or_init(|| {
+            self.read_primary_index()
+                .unwrap_or_else(CachedIndex::Unreadable)
+        });
+        match index {
+            CachedIndex::Unreadable(e) => Err(e.clone()),
+            index => Ok(index.ready()),
+        }
+    }
+
+    // The primary index for lookups that can walk the block chain instead.
+    // An unreadable index does not fail them, `warnings` reports it.
+    pub(crate) fn usable_index(&self) -> Option<&PrimaryIndex> {
+        self.primary_index().ok().flatten()
+    }
+
+    // Whether the table has a primary index that `primary_index` ignores
+    pub(crate) fn primary_index_out_of_date(&self) -> bool {
+        matches!(self.index.get(), Some(CachedIndex::OutOfDate))
+    }
+
+    fn read_primary_index(&self) -> Result<CachedIndex> {
+        if self.header().file_type != PX_FILETYPE_DB_INDEXED {
+            return Ok(CachedIndex::Missing);
+        }
+        let Some(path) = self.path().and_then(|p| companion_path(p, "PX")) else {
+            return Ok(CachedIndex::Missing);
+        };
+
+        let index = PrimaryIndex::from_table(Table::open(path)?)?;
+        if index.num_records()? != self.header().num_records as u64 {
+            return Ok(CachedIndex::OutOfDate);
+        }
+
+        Ok(CachedIndex::Ready(Box::new(index)))
+    }
+}
+
+// Records of a keyed table whose first key field lies within a range, in
+// key order.
+pub struct KeyRange<'a> {
+    records: Records<'a>,
+    lo: Bound<Value>,
+    hi: Bound<Value>,
+    collation: Collation,
+    done: bool,
+}
+
+impl<'a> KeyRange<'a> {
+    // Position of a key relative to the range
+    fn locate(&self, key: &Value) -> Result<Ordering> {
+        let key_order = |a, b| self.collation.key_order(a, b);
+        let below = match &self.lo {
+            Bound::Included(lo) => key_order(key, lo)?.is_lt(),
+            Bound::Excluded(lo) => key_order(key, lo)?.is_le(),
+            Bound::Unbounded => false,
+        };
+        let above = match &self.hi {
+            Bound::Included(hi) => key_order(key, hi)?.is_gt(),
+            Bound::Excluded(hi) => key_order(key, hi)?.is_ge(),
+            Bound::Unbounded => false,
+        };
+
+        Ok(match (below, above) {
+            (true, _) => Ordering::Less,
+            (_, true) => Ordering::Greater,
+            _ => Ordering::Equal,
+        })
+    }
+}
+
+impl<'a> Iterator for KeyRange<'a> {
+    type Item = Result<Record<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let record = match self.records.next()? {
+                Ok(record) => record,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+
+            match record.get(0).and_then(|key| self.locate(&key)) {
+                Ok(Ordering::Less) => continue,
+                Ok(Ordering::Equal) => return Some(Ok(record)),
+                Ok(Ordering::Greater) => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl Table {
+    // Iterates over the records whose first key field lies within `range`.
+    // The primary index gives the block to start from, the block chain is
+    // then followed until a key is past the end of the range. Without a
+    // usable index, missing, out of date or unreadable, the chain is walked
+    // from its start.
+    pub fn range<R: RangeBounds<Value>>(&self, range: R) -> Result<KeyRange<'_>> {
+        if self.header().file_type != PX_FILETYPE_DB_INDEXED
+            || self.header().primary_key_fields <= 0
+        {
+            return Err(Error::invalid("Key ranges need a keyed table"));
+        }
+
+        let lo = range.start_bound().cloned();
+        let hi = range.end_bound().cloned();
+
+        let first = match (&lo, self.usable_index()) {
+            (Bound::Unbounded, _) | (_, None) => Some(self.header().first_block),
+            (lo, Some(index)) => index.first_block(lo.as_ref())?,
+        };
+
+        Ok(KeyRange {
+            records: self.records_from(first.unwrap_or(0)),
+            lo,
+            hi,
+            collation: self.collation(),
+            done: false,
+        })
+    }
+}
+
+impl Table {
+    // Block and slot of the record at position `n` of the linked list,
+    // counting from 0. The record counts of the primary index lead there
+    // without reading the data blocks in between, unkeyed tables sum up
+    // the counts of the block headers.
+    fn find_record(&self, n: usize) -> Result<Option<(PxBlocks<'_>, usize)>> {
+        if let Some(index) = self.usable_index() {
+            return match index.record_position(n as u64)? {
+                Some((block, slot)) => Ok(Some((self.block(block)?, slot))),
+                None => Ok(None),
+            };
+        }
+
+        let mut skipped = 0;
+        for block in self.blocks() {
+            let block = block?;
+            if n < skipped + block.num_recs_in_block {
+                return Ok(Some((block, n - skipped)));
+            }
+            skipped += block.num_recs_in_block;
+        }
+
+        Ok(None)
+    }
+
+    // The record at position `n` of the linked list, counting from 0.
+    pub fn record_at(&self, n: usize) -> Result<Option<Record<'_>>> {
+        Ok(self
+            .find_record(n)?
+            .and_then(|(block, slot)| self.record(&block, slot)))
+    }
+
+    // Iterates over the records from position `n` on.
+    pub fn seek(&self, n: usize) -> Result<Records<'_>> {
+        Ok(match self.find_record(n)? {
+            Some((block, slot)) => self.records_at(block, slot),
+            None => self.records_from(0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{field, write_table, TempDir};
+    use crate::types::{PxHeader, PX_FIELD_TYPE_ALPHA, PX_FIELD_TYPE_LONG_INT};
+
+    const RECORDS: i32 = 2000;
+
+    fn key(n: i32) -> Value {
+        Value::Alpha(format!("k{:05}", n * 2))
+    }
+
+    // Wide keys leave room for 8 entries per index block, giving an index
+    // of three levels
+    fn keyed_table(dir: &TempDir) -> Table {
+        let fields = vec![
+            field("Key", PX_FIELD_TYPE_ALPHA, 120),
+            field("N", PX_FIELD_TYPE_LONG_INT, 4),
+        ];
+        let rows = (0..RECORDS)
+            .map(|n| vec![key(n), Value::Long(n)])
+            .collect::<Vec<_>>();
+        write_table(
+            &dir.join("KEYS.DB"),
+            &PxHeader::new_table("KEYS"),
+            fields,
+            1,
+            &rows,
+        )
+    }
+
+    fn px_path(table: &Table) -> std::path::PathBuf {
+        companion_path(table.path().unwrap(), "PX").unwrap()
+    }
+
+    fn number(record: Record) -> i32 {
+        match record.get(1).unwrap() {
+            Value::Long(n) => n,
+            value => panic!("unexpected {:?}", value),
+        }
+    }
+
+    #[test]
+    fn descends_to_records_and_keys() {
+        let dir = TempDir::new("index-descend");
+        let table = keyed_table(&dir);
+        let index = table.primary_index().unwrap().unwrap();
+        // The index is read once and kept with the table
+        std::fs::remove_file(px_path(&table)).unwrap();
+        assert!(std::ptr::eq(index, table.primary_index().unwrap().unwrap()));
+        assert_eq!(index.px.header().index_levels, 3);
+        assert_eq!(index.num_records().unwrap(), RECORDS as u64);
+        assert_eq!(index.entries().unwrap().len(), 250);
+
+        for n in [0, 1, 7, 8, 63, 64, 511, 512, 1999] {
+            assert_eq!(number(table.record_at(n as usize).unwrap().unwrap()), n);
+        }
+        assert!(table.record_at(RECORDS as usize).unwrap().is_none());
+
+        // Keys between the stored ones and on them, at block boundaries too
+        for (lo, hi) in [(0, 10), (15, 16), (127, 130), (1020, 1100), (3990, 4100)] {
+            let bound = |n: i32| Value::Alpha(format!("k{:05}", n));
+            let found = table
+                .range((Bound::Included(bound(lo)), Bound::Excluded(bound(hi))))
+                .unwrap()
+                .map(|record| number(record.unwrap()))
+                .collect::<Vec<_>>();
+            let expected = (0..RECORDS)
+                .filter(|&n| (lo..hi).contains(&(n * 2)))
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn sums_the_children_of_unknown_counts() {
+        let dir = TempDir::new("index-counts");
+        let table = keyed_table(&dir);
+        let path = px_path(&table);
+        let mut data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let px = Table::from_bytes(data.clone()).unwrap();
+        let (root, size) = (
+            px.header().index_root_block,
+            px.header().record_size as usize,
+        );
+        let start = px.block_offset(root) + 6;
+        let entries = px.block(root).unwrap().num_recs_in_block;
+        for i in 0..entries {
+            let count = start + i * size + 122;
+            data[count..count + 2].copy_from_slice(&[0xff, 0xff]);
+        }
+
+        let index = PrimaryIndex::from_table(Table::from_bytes(data).unwrap()).unwrap();
+        assert_eq!(index.num_records().unwrap(), RECORDS as u64);
+        assert_eq!(index.record_position(1999).unwrap(), Some((250, 7)));
+    }
+
+    #[test]
+    fn unreadable_indexes_fall_back_to_the_chain() {
+        let dir = TempDir::new("index-unreadable");
+        let path = keyed_table(&dir).path().unwrap().to_path_buf();
+        let px = companion_path(&path, "PX").unwrap();
+        std::fs::write(&px, b"not an index").unwrap();
+
+        let table = Table::open(&path).unwrap();
+        let unreadable = |table: &Table| match table.primary_index() {
+            Err(e) => e.to_string(),
+            Ok(_) => panic!("the index was read"),
+        };
+        let error = unreadable(&table);
+        // The error is kept, the file is not read again
+        std::fs::remove_file(&px).unwrap();
+        assert_eq!(unreadable(&table), error);
+        assert!(table.warnings().iter().any(|w| w.contains(&error)));
+
+        assert_eq!(number(table.record_at(1234).unwrap().unwrap()), 1234);
+        let found = table
+            .range(key(1500)..key(1503))
+            .unwrap()
+            .map(|record| number(record.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(found, [1500, 1501, 1502]);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::error::{Error, Result};
+use crate::parse::{read_cstr, PX_BLOCK_HEADER_SIZE};
+use crate::table::Table;
+use crate::types::{
+    code_page_name, field_type_name, file_type_name, sort_order_name, HeaderField, PxHeader,
+    Version, PX_HEADER_FIELDS, PX_HEADER_SIZE, PX_HEADER_SIZE_V4,
+};
+
+// A labelled range of bytes of a file with the value decoded from it.
+// Spans without bytes mark where a record starts.
+#[derive(Debug, Clone)]
+pub struct Span<'a> {
+    pub offset: usize,
+    pub bytes: &'a [u8],
+    pub label: String,
+    pub value: String,
+}
+
+fn span<'a>(data: &'a [u8], offset: usize, len: usize, label: &str, value: String) -> Span<'a> {
+    let end = (offset + len).min(data.len());
+    Span {
+        offset,
+        bytes: data.get(offset.min(end)..end).unwrap_or_default(),
+        label: label.to_string(),
+        value,
+    }
+}
+
+// The raw value of a header field followed by its meaning, if it has one
+fn header_value(field: &HeaderField, value: i64) -> String {
+    let name = match field.name {
+        "file_type" => file_type_name(value as u8),
+        "sort_order" => sort_order_name(value as u8),
+        "file_version_id" => Version(value as u8).name(),
+        "dos_global_code_page" => code_page_name(value as u16),
+        _ => return value.to_string(),
+    };
+    format!("{} ({})", value, name)
+}
+
+// Annotates the header at the start of `data`: the fixed fields, the field
+// descriptions, the table name and the field names. Only the raw bytes are
+// needed, so headers `Table` refuses to open can be looked at as well.
+pub fn header_spans(data: &[u8]) -> Vec<Span<'_>> {
+    let header = PxHeader::from_reader(&mut &data[..]).ok();
+    let fixed = match &header {
+        Some(header) if header.has_v4_header() => PX_HEADER_SIZE_V4,
+        _ => PX_HEADER_SIZE,
+    };
+
+    let mut spans: Vec<_> = PX_HEADER_FIELDS
+        .iter()
+        .filter(|field| field.offset < fixed)
+        .map_while(|field| {
+            let value = field.read(data)?;
+            Some(span(
+                data,
+                field.offset,
+                field.size,
+                field.name,
+                header_value(field, value),
+            ))
+        })
+        .collect();
+
+    let Some(header) = header else {
+        return spans;
+    };
+    let end = (header.header_size.max(0) as usize).min(data.len());
+    let num_fields = header.num_fields.max(0) as usize;
+    let mut i = fixed;
+
+    for n in 0..num_fields {
+        let Some(&[field_type, size]) = data.get(i..i + 2) else {
+            break;
+        };
+        let label = format!("type and size of field {}", n + 1);
+        let value = format!("{}, {}", field_type_name(field_type), size);
+        spans.push(span(data, i, 2, &label, value));
+        i += 2;
+    }
+
+    spans.push(span(data, i, 4, "table name pointer", String::new()));
+    i += 4;
+    if !header.is_index() {
+        let len = 4 * num_fields;
+        spans.push(span(data, i, len, "field name pointers", String::new()));
+        i += len;
+    }
+
+    let len = if header.file_version_id.0 >= 0x0c {
+        261
+    } else {
+        79
+    };
+    let name = read_cstr(data.get(i..end).unwrap_or_default());
+    spans.push(span(data, i, len, "table name", name));
+    i += len;
+
+    if !header.is_index() {
+        for n in 0..num_fields {
+            let name = read_cstr(data.get(i..end).unwrap_or_default());
+            let len = name.len() + 1;
+            spans.push(span(
+                data,
+                i,
+                len,
+                &format!("name of field {}", n + 1),
+                name,
+            ));
+            i += len;
+        }
+    }
+
+    if i < end {
+        spans.push(span(data, i, end - i, "unused", String::new()));
+    }
+
+    spans
+}
+
+// Annotates data block `number`: its header, then every record with the
+// raw bytes and decoded value of each field, then the free space. Blocks
+// whose records cannot be parsed show the error instead.
+pub fn block_spans(table: &Table, number: u16) -> Result<Vec<Span<'_>>> {
+    let data = table.raw();
+    let start = table.block_offset(number);
+    let end = start + table.header().block_size();
+
+    if number == 0 || end > data.len() {
+        let error = Error::corrupt("Block is outside of the file")
+            .at_block(number)
+            .at_offset(start as u64);
+        return Err(match table.path() {
+            Some(path) => error.in_file(path),
+            None => error,
+        });
+    }
+
+    let mut spans = vec![];
+    let mut i = start;
+    for name in ["next_block", "prev_block", "add_data_size"] {
+        let value = i16::from_le_bytes([data[i], data[i + 1]]);
+        let value = match name {
+            "add_data_size" => value.to_string(),
+            _ => (value as u16).to_string(),
+        };
+        spans.push(span(data, i, 2, name, value));
+        i += 2;
+    }
+
+    let block = match table.block(number) {
+        Ok(block) => block,
+        Err(e) => {
+            spans.push(span(data, i, end - i, "records", e.message()));
+            return Ok(spans);
+        }
+    };
+
+    for slot in 0..block.num_recs_in_block {
+        let Some(record) = table.record(&block, slot) else {
+            break;
+        };
+        spans.push(span(data, i, 0, &format!("record {}", slot), String::new()));
+
+        for (index, field) in table.fields().iter().enumerate() {
+            let value = match record.get(index) {
+                Ok(value) => value.to_string(),
+                Err(e) => format!("error: {}", e.message()),
+            };
+            spans.push(span(data, i, field.data_size(), &field.name, value));
+            i += field.data_size();
+        }
+    }
+
+    let used = start + PX_BLOCK_HEADER_SIZE + block.records.len();
+    if used < end {
+        spans.push(span(data, used, end - used, "free space", String::new()));
+    }
+
+    Ok(spans)
+}
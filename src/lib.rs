@@ -0,0 +1,47 @@
+// Reading and writing Paradox database files.
+//
+// `Table` opens a .DB file (or any other Paradox file sharing its layout)
+// and gives access to the header, the field descriptions and the records.
+// Typed access goes through `typed::PxRecord` or serde, new tables are
+// written with `writer::TableWriter`.
+
+// The derived code names the crate as `::pxrs`, inside it too
+extern crate self as pxrs;
+
+pub mod cdc;
+pub mod check;
+pub mod collation;
+pub mod convert;
+pub mod currency;
+pub mod cursor;
+pub mod datetime;
+pub mod de;
+pub mod diff;
+pub mod error;
+pub mod expr;
+pub mod fingerprint;
+pub mod index;
+pub mod inspect;
+pub mod lock;
+mod parse;
+pub mod schema_diff;
+pub mod sql;
+pub mod table;
+#[cfg(test)]
+mod testing;
+pub mod typed;
+pub mod types;
+pub mod version;
+pub mod writer;
+
+pub use collation::Collation;
+pub use currency::PxCurrency;
+pub use cursor::{Bookmark, Cursor};
+pub use datetime::{PxDate, PxTime, PxTimestamp};
+pub use error::{Error, Location, Result};
+pub use fingerprint::Fingerprint;
+pub use index::{KeyRange, PrimaryIndex};
+pub use table::{BlockOrder, Record, Records, Table};
+pub use typed::{PxRecord, PxValue, TypedWriter};
+pub use types::{BlobPointer, PxFieldInfo as FieldInfo, PxHeader, Value};
+pub use writer::TableWriter;
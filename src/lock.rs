@@ -0,0 +1,719 @@
+use std::fmt::{self, Display};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::{Error, Result};
+use crate::table::Table;
+
+// Lock and network control files Paradox and the BDE keep next to shared
+// tables:
+//
+//   PDOXUSRS.NET  the network control file, with a session per user
+//   PDOXUSRS.LCK  the users working in a directory and their control file
+//   PARADOX.LCK   the lock on the directory itself
+//   <TABLE>.LCK   the table and record locks of one table
+//
+// The layout of their records is not documented. pxrs reads the lock
+// records of <TABLE>.LCK, see `parse_locks`, and takes the user names of
+// the other files from the text they hold. Writing lock records of its own
+// is off unless turned on with `set_lock_records`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockFileKind {
+    NetControl,
+    DirectoryUsers,
+    Directory,
+    Table,
+}
+
+impl LockFileKind {
+    fn from_name(name: &str, table: &str) -> Option<Self> {
+        let name = name.to_ascii_uppercase();
+        Some(match name.as_str() {
+            "PDOXUSRS.NET" => LockFileKind::NetControl,
+            "PDOXUSRS.LCK" => LockFileKind::DirectoryUsers,
+            "PARADOX.LCK" => LockFileKind::Directory,
+            _ if name.strip_suffix(".LCK")? == table.to_ascii_uppercase() => LockFileKind::Table,
+            _ => return None,
+        })
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            LockFileKind::NetControl => "network control file",
+            LockFileKind::DirectoryUsers => "users of the directory",
+            LockFileKind::Directory => "directory lock",
+            LockFileKind::Table => "table and record locks",
+        }
+    }
+}
+
+// The locks Paradox sessions hold on a table. A session opening a table
+// prevents full locks, so nobody gets it exclusively while it is open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockType {
+    Full,
+    Write,
+    PreventWrite,
+    PreventFull,
+    Record,
+    Unknown(u16),
+}
+
+impl LockType {
+    fn from_code(code: u16) -> Self {
+        match code {
+            1 => LockType::Full,
+            2 => LockType::Write,
+            3 => LockType::PreventWrite,
+            4 => LockType::PreventFull,
+            5 => LockType::Record,
+            code => LockType::Unknown(code),
+        }
+    }
+
+    fn code(&self) -> u16 {
+        match self {
+            LockType::Full => 1,
+            LockType::Write => 2,
+            LockType::PreventWrite => 3,
+            LockType::PreventFull => 4,
+            LockType::Record => 5,
+            LockType::Unknown(code) => *code,
+        }
+    }
+
+    // Whether two sessions can hold the locks at the same time. Record
+    // locks only exclude full locks, locks pxrs does not know exclude all.
+    pub fn conflicts(&self, other: LockType) -> bool {
+        use LockType::*;
+        matches!(
+            (*self, other),
+            (Unknown(_), _)
+                | (_, Unknown(_))
+                | (Full, _)
+                | (_, Full)
+                | (Write, Write | PreventWrite)
+                | (PreventWrite, Write)
+        )
+    }
+}
+
+impl Display for LockType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockType::Full => write!(f, "full lock"),
+            LockType::Write => write!(f, "write lock"),
+            LockType::PreventWrite => write!(f, "prevent write lock"),
+            LockType::PreventFull => write!(f, "prevent full lock"),
+            LockType::Record => write!(f, "record lock"),
+            LockType::Unknown(code) => write!(f, "unknown lock {:04x}", code),
+        }
+    }
+}
+
+// A lock record of <TABLE>.LCK
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lock {
+    pub lock_type: LockType,
+    pub session: u16,
+    // Record number of record locks
+    pub record: Option<u32>,
+    pub user: String,
+}
+
+impl Display for Lock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.lock_type)?;
+        if let Some(record) = self.record {
+            write!(f, " on record {}", record)?;
+        }
+        match self.user.is_empty() {
+            true => Ok(()),
+            false => write!(f, " by {} (session {})", self.user, self.session),
+        }
+    }
+}
+
+// <TABLE>.LCK holds a 32 byte header, whose first two bytes count the lock
+// records, and 32 byte lock records:
+//
+//   0x00  lock type, see `LockType::from_code`
+//   0x02  session number
+//   0x04  record number of record locks
+//   0x10  user name, NUL padded
+//
+// All numbers are little-endian. The layout is reconstructed from lock
+// files found next to shared tables.
+const LCK_HEADER_SIZE: usize = 32;
+const LCK_RECORD_SIZE: usize = 32;
+const LCK_USER: usize = 0x10;
+
+// None when the data does not have the layout pxrs knows
+fn parse_locks(data: &[u8]) -> Option<Vec<Lock>> {
+    if data.is_empty() {
+        return Some(vec![]);
+    }
+    let count = u16::from_le_bytes(data.get(..2)?.try_into().ok()?) as usize;
+    let records = data.get(LCK_HEADER_SIZE..)?;
+    if records.len() != count * LCK_RECORD_SIZE {
+        return None;
+    }
+
+    let locks = records
+        .chunks(LCK_RECORD_SIZE)
+        .map(|record| {
+            let u16_at = |i: usize| u16::from_le_bytes([record[i], record[i + 1]]);
+            let lock_type = LockType::from_code(u16_at(0));
+            let record_number = u32::from_le_bytes(record[4..8].try_into().unwrap_or_default());
+            Lock {
+                lock_type,
+                session: u16_at(2),
+                record: (lock_type == LockType::Record).then_some(record_number),
+                user: crate::parse::read_cstr(&record[LCK_USER..]),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    locks
+        .iter()
+        .all(|lock| !matches!(lock.lock_type, LockType::Unknown(_)))
+        .then_some(locks)
+}
+
+fn lock_bytes(locks: &[Lock]) -> Vec<u8> {
+    let mut data = vec![0; LCK_HEADER_SIZE + locks.len() * LCK_RECORD_SIZE];
+    data[..2].copy_from_slice(&(locks.len() as u16).to_le_bytes());
+    for (lock, record) in locks
+        .iter()
+        .zip(data[LCK_HEADER_SIZE..].chunks_mut(LCK_RECORD_SIZE))
+    {
+        record[..2].copy_from_slice(&lock.lock_type.code().to_le_bytes());
+        record[2..4].copy_from_slice(&lock.session.to_le_bytes());
+        record[4..8].copy_from_slice(&lock.record.unwrap_or(0).to_le_bytes());
+        let user = lock.user.as_bytes();
+        let len = user.len().min(LCK_RECORD_SIZE - LCK_USER - 1);
+        record[LCK_USER..LCK_USER + len].copy_from_slice(&user[..len]);
+    }
+    data
+}
+
+#[derive(Debug, Clone)]
+pub struct LockFile {
+    pub path: PathBuf,
+    pub kind: LockFileKind,
+    pub users: Vec<String>,
+    // The network control file PDOXUSRS.LCK refers to, as the users see it
+    pub net_file: Option<String>,
+    // The lock records of a table lock file. One of an unknown type stands
+    // for a file pxrs cannot read, the users are then guessed from its text.
+    pub locks: Vec<Lock>,
+}
+
+// Runs of printable characters between the binary parts of a lock file
+fn strings(data: &[u8]) -> impl Iterator<Item = &str> {
+    data.split(|b| !(0x20..=0x7e).contains(b))
+        .filter_map(|run| std::str::from_utf8(run).ok())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+// User names are short and, unlike the paths next to them, hold no
+// separators
+fn user_names(data: &[u8]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for s in strings(data) {
+        let name = (2..=31).contains(&s.len()) && !s.contains(['\\', '/', ':', '.']);
+        if name && !names.iter().any(|n| n == s) {
+            names.push(s.to_string());
+        }
+    }
+    names
+}
+
+impl LockFile {
+    fn read(path: PathBuf, kind: LockFileKind) -> Result<Self> {
+        let data = fs::read(&path).map_err(|e| Error::from(e).in_file(&path))?;
+        let net_file = match kind {
+            LockFileKind::DirectoryUsers => strings(&data)
+                .find(|s| s.to_ascii_uppercase().ends_with("PDOXUSRS.NET"))
+                .map(str::to_string),
+            _ => None,
+        };
+
+        let locks = match kind {
+            LockFileKind::Table => parse_locks(&data).unwrap_or_else(|| {
+                vec![Lock {
+                    lock_type: LockType::Unknown(0),
+                    session: 0,
+                    record: None,
+                    user: String::new(),
+                }]
+            }),
+            _ => vec![],
+        };
+        // Users are guessed from the text of files without lock records
+        let mut users = user_names(&data);
+        if !locks.is_empty() && locks.iter().all(|lock| !lock.user.is_empty()) {
+            users.clear();
+            for lock in &locks {
+                if !users.contains(&lock.user) {
+                    users.push(lock.user.clone());
+                }
+            }
+        }
+
+        Ok(LockFile {
+            users,
+            path,
+            kind,
+            net_file,
+            locks,
+        })
+    }
+}
+
+// The lock files found for a table
+#[derive(Debug, Clone, Default)]
+pub struct Locks {
+    pub files: Vec<LockFile>,
+}
+
+impl Locks {
+    // Looks for the lock files of the directory holding the table at
+    // `path` and for the one of the table itself. The table does not need
+    // to exist.
+    pub fn for_table<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let (locks, mut errors) = Self::for_reading(path);
+        match errors.is_empty() {
+            true => Ok(locks),
+            false => Err(errors.remove(0)),
+        }
+    }
+
+    // Like `for_table`, but lock files that cannot be read are left out and
+    // returned as errors, for readers that only warn about locks.
+    pub fn for_reading<P: AsRef<Path>>(path: P) -> (Self, Vec<Error>) {
+        let path = path.as_ref();
+        let table = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return (Locks::default(), vec![]),
+            Err(e) => return (Locks::default(), vec![Error::from(e).in_file(dir)]),
+        };
+
+        let mut files = vec![];
+        let mut errors = vec![];
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    errors.push(Error::from(e).in_file(dir));
+                    continue;
+                }
+            };
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(kind) = LockFileKind::from_name(&name, &table) {
+                match LockFile::read(entry.path(), kind) {
+                    Ok(file) => files.push(file),
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+        files.sort_by_key(|file| (file.kind as u8, file.path.clone()));
+
+        (Locks { files }, errors)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    // Every user named by the lock files
+    pub fn users(&self) -> Vec<&str> {
+        let mut users: Vec<&str> = vec![];
+        for user in self.files.iter().flat_map(|file| &file.users) {
+            if !users.contains(&user.as_str()) {
+                users.push(user);
+            }
+        }
+        users
+    }
+
+    // The table lock file, when it holds any locks
+    pub fn table_lock(&self) -> Option<&LockFile> {
+        self.files
+            .iter()
+            .find(|file| file.kind == LockFileKind::Table && !file.locks.is_empty())
+    }
+
+    // Fails when another session holds a lock that keeps pxrs from taking
+    // a lock of `lock_type`
+    pub fn check_lock(&self, lock_type: LockType) -> Result<()> {
+        let Some(file) = self.table_lock() else {
+            return Ok(());
+        };
+        let conflicts = file
+            .locks
+            .iter()
+            .filter(|lock| lock.lock_type.conflicts(lock_type))
+            .collect::<Vec<_>>();
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+
+        let message = match conflicts.iter().all(|lock| lock.user.is_empty()) {
+            true if file.users.is_empty() => "The table is locked by Paradox".to_string(),
+            true => format!("The table is locked by {}", file.users.join(", ")),
+            false => format!("The table is locked: {}", conflicts[0]),
+        };
+        Err(Error::locked(message).in_file(&file.path))
+    }
+
+    // Fails when the table must not be written as a whole, which takes a
+    // full lock
+    pub fn check_writable(&self) -> Result<()> {
+        self.check_lock(LockType::Full)
+    }
+}
+
+impl Table {
+    // The lock files for the table, none for tables read from memory
+    pub fn locks(&self) -> Result<Locks> {
+        match self.path() {
+            Some(path) => Locks::for_table(path),
+            None => Ok(Locks::default()),
+        }
+    }
+}
+
+// Whether pxrs records its own locks in the lock files of tables. Off by
+// default: the layout of the records is reconstructed, and Paradox may not
+// read them the way pxrs writes them. The locks of other sessions are
+// checked either way.
+static LOCK_RECORDS: AtomicBool = AtomicBool::new(false);
+
+// Turns writing lock records for Paradox on or off, for every writer of
+// the process
+pub fn set_lock_records(enabled: bool) {
+    LOCK_RECORDS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn lock_records() -> bool {
+    LOCK_RECORDS.load(Ordering::Relaxed)
+}
+
+// The session pxrs writes its lock records as. The process id cut to 16
+// bits may be the session of another process, `add_lock` picks the next
+// free number then.
+fn own_session() -> (u16, String) {
+    let user = std::env::var("USERNAME")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_default();
+    let user = user
+        .chars()
+        .filter(char::is_ascii_graphic)
+        .take(LCK_RECORD_SIZE - LCK_USER - 1)
+        .collect::<String>();
+    let user = if user.is_empty() {
+        "pxrs".to_string()
+    } else {
+        user
+    };
+    (std::process::id() as u16, user)
+}
+
+// The lock file of the table at `path`, in the case of the extension
+fn lock_path(path: &Path) -> PathBuf {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.chars().any(|c| c.is_ascii_lowercase()) => path.with_extension("lck"),
+        _ => path.with_extension("LCK"),
+    }
+}
+
+// Whether `file` is still the file at `path`, which another writer may
+// have replaced or removed
+#[cfg(unix)]
+fn is_current(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        (_, Err(e)) => e.kind() != io::ErrorKind::NotFound,
+        _ => true,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_current(_file: &File, _path: &Path) -> bool {
+    true
+}
+
+// Opens a table lock file, creating it if `create` is set, and takes an
+// exclusive OS lock on it, held until the file is closed
+fn open_lock_file(path: &Path, create: bool) -> io::Result<File> {
+    loop {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(create)
+            .truncate(false)
+            .open(path)?;
+        match file.lock() {
+            Ok(()) if !is_current(&file, path) => continue,
+            Ok(()) => return Ok(file),
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => return Ok(file),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Changes the lock records of a table lock file under an OS lock, so
+// sessions of pxrs take turns. The records are written to a temporary file
+// that then replaces the lock file, readers never see half of them. A
+// lock file left without records is removed.
+fn update_locks(
+    path: &Path,
+    create: bool,
+    update: impl FnOnce(&mut Vec<Lock>) -> Result<()>,
+) -> Result<()> {
+    let in_file = |e: io::Error| Error::from(e).in_file(path);
+    let mut file = match open_lock_file(path, create) {
+        Ok(file) => file,
+        Err(e) if !create && e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(in_file(e)),
+    };
+
+    let mut data = vec![];
+    file.read_to_end(&mut data).map_err(in_file)?;
+    let mut locks = parse_locks(&data)
+        .ok_or_else(|| Error::locked("Unknown lock file layout").in_file(path))?;
+    update(&mut locks)?;
+
+    if locks.is_empty() {
+        return fs::remove_file(path).map_err(in_file);
+    }
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".{}", std::process::id()));
+    let temp = PathBuf::from(temp);
+    let written = File::create(&temp)
+        .and_then(|mut file| file.write_all(&lock_bytes(&locks)))
+        .and_then(|()| fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written.map_err(in_file)
+}
+
+// Adds a lock record to a table lock file unless another session holds a
+// conflicting lock. Returns the record with the session number it got.
+fn add_lock(path: &Path, mut lock: Lock) -> Result<Lock> {
+    update_locks(path, true, |locks| {
+        if let Some(other) = locks.iter().find(|l| l.lock_type.conflicts(lock.lock_type)) {
+            return Err(Error::locked(format!("The table is locked: {}", other)).in_file(path));
+        }
+        while locks.iter().any(|l| l.session == lock.session) {
+            lock.session = lock.session.wrapping_add(1);
+        }
+        locks.push(lock.clone());
+        Ok(())
+    })?;
+    Ok(lock)
+}
+
+fn remove_lock(path: &Path, lock: &Lock) -> Result<()> {
+    update_locks(path, false, |locks| {
+        if let Some(i) = locks.iter().position(|l| l == lock) {
+            locks.remove(i);
+        }
+        Ok(())
+    })
+}
+
+// A lock pxrs holds on a table. With lock records on, it is recorded in
+// the lock file of the table and removed again when dropped.
+#[derive(Debug)]
+pub struct TableLock {
+    record: Option<(PathBuf, Lock)>,
+}
+
+impl TableLock {
+    // Checks that no other session holds a lock conflicting with one of
+    // `lock_type`, and records it when lock records are on
+    pub fn acquire(table: &Path, lock_type: LockType) -> Result<Self> {
+        let locks = Locks::for_table(table)?;
+        locks.check_lock(lock_type)?;
+        if !lock_records() {
+            return Ok(TableLock { record: None });
+        }
+
+        let path = match locks.files.iter().find(|f| f.kind == LockFileKind::Table) {
+            Some(file) => file.path.clone(),
+            None => lock_path(table),
+        };
+        let (session, user) = own_session();
+        let lock = Lock {
+            lock_type,
+            session,
+            record: None,
+            user,
+        };
+        let lock = add_lock(&path, lock)?;
+        Ok(TableLock {
+            record: Some((path, lock)),
+        })
+    }
+}
+
+impl Drop for TableLock {
+    fn drop(&mut self) {
+        // Paradox clears the locks of sessions that are gone
+        if let Some((path, lock)) = &self.record {
+            let _ = remove_lock(path, lock);
+        }
+    }
+}
+
+// Takes an exclusive lock on an open table file, held until the file is
+// closed, after checking the locks Paradox sessions hold on it. With lock
+// records on, a lock of `lock_type` is recorded in the lock file of the
+// table until the returned lock is dropped. File systems without locks are
+// written to unlocked.
+pub fn lock_table(file: &File, path: &Path, lock_type: LockType) -> Result<TableLock> {
+    let lock = TableLock::acquire(path, lock_type)?;
+    lock_file(file, path)?;
+    Ok(lock)
+}
+
+fn lock_file(file: &File, path: &Path) -> Result<()> {
+    match file.try_lock() {
+        Ok(()) => Ok(()),
+        Err(TryLockError::WouldBlock) => {
+            Err(Error::locked("The file is in use by another program").in_file(path))
+        }
+        Err(TryLockError::Error(e)) if e.kind() == io::ErrorKind::Unsupported => Ok(()),
+        Err(TryLockError::Error(e)) => Err(Error::from(e).in_file(path)),
+    }
+}
+
+// `lock_table` with a full lock, for writing the whole table
+pub fn lock_exclusive(file: &File, path: &Path) -> Result<TableLock> {
+    lock_table(file, path, LockType::Full)
+}
+
+// Creates or truncates a file, locking it until it is closed. Files of a
+// table pxrs holds a lock on need no lock record of their own.
+pub(crate) fn create_file(path: &Path) -> Result<File> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|e| Error::from(e).in_file(path))?;
+    lock_file(&file, path)?;
+    file.set_len(0).map_err(|e| Error::from(e).in_file(path))?;
+    Ok(file)
+}
+
+// Creates or truncates a table file after checking that Paradox does not
+// have it locked, keeping it locked until the file is closed and the lock
+// dropped.
+pub fn create_locked(path: &Path) -> Result<(File, TableLock)> {
+    Locks::for_table(path)?.check_writable()?;
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|e| Error::from(e).in_file(path))?;
+    let lock = lock_exclusive(&file, path)?;
+    file.set_len(0).map_err(|e| Error::from(e).in_file(path))?;
+
+    Ok((file, lock))
+}
+
+// Opens an existing table file for reading and writing with a write lock,
+// which sessions that only have the table open do not conflict with.
+pub fn open_locked(path: &Path) -> Result<(File, TableLock)> {
+    Locks::for_table(path)?.check_lock(LockType::Write)?;
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| Error::from(e).in_file(path))?;
+    let lock = lock_table(&file, path, LockType::Write)?;
+
+    Ok((file, lock))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{field, TempDir};
+    use crate::types::{PxHeader, Value, PX_FIELD_TYPE_LONG_INT};
+    use crate::writer::TableWriter;
+
+    #[test]
+    fn writers_record_their_locks_for_paradox() {
+        set_lock_records(true);
+        let dir = TempDir::new("lock-writers");
+        let path = dir.join("LOCKED.DB");
+        let fields = vec![field("Id", PX_FIELD_TYPE_LONG_INT, 4)];
+
+        let mut writer =
+            TableWriter::create(&path, &PxHeader::new_table("LOCKED"), fields).unwrap();
+        writer.write_values(&[Value::Long(1)]).unwrap();
+
+        let locks = Locks::for_table(&path).unwrap();
+        let file = locks.table_lock().unwrap();
+        assert_eq!(file.locks.len(), 1);
+        assert_eq!(file.locks[0].lock_type, LockType::Full);
+        assert!(locks.check_writable().is_err());
+        writer.finish().unwrap();
+        assert!(Locks::for_table(&path).unwrap().is_empty());
+
+        // A Paradox session that has the table open lets pxrs append but
+        // not rewrite it
+        let open = Lock {
+            lock_type: LockType::PreventFull,
+            session: 7,
+            record: None,
+            user: "ALICE".to_string(),
+        };
+        fs::write(
+            dir.join("LOCKED.LCK"),
+            lock_bytes(std::slice::from_ref(&open)),
+        )
+        .unwrap();
+        let locks = Locks::for_table(&path).unwrap();
+        assert_eq!(
+            locks.table_lock().unwrap().locks,
+            std::slice::from_ref(&open)
+        );
+        assert!(locks.check_writable().is_err());
+
+        let mut writer = TableWriter::append(&path).unwrap();
+        writer.write_values(&[Value::Long(2)]).unwrap();
+        let locks = Locks::for_table(&path).unwrap();
+        assert_eq!(locks.users(), ["ALICE", own_session().1.as_str()]);
+        writer.finish().unwrap();
+        assert_eq!(Locks::for_table(&path).unwrap().files[0].locks, [open]);
+
+        // Lock files pxrs cannot read keep it from writing at all
+        fs::write(dir.join("LOCKED.LCK"), b"ALICE\0garbage").unwrap();
+        let locks = Locks::for_table(&path).unwrap();
+        assert!(locks.check_lock(LockType::Write).is_err());
+        assert!(TableWriter::append(&path).is_err());
+    }
+}
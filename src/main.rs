@@ -1,67 +1,1335 @@
-mod convert;
-mod parse;
-mod types;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::de::DeserializeOwned;
+use serde::ser::{Error as _, SerializeMap, Serializer};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "mmap")]
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use clap::Parser;
-use std::fs::File;
-use std::io;
-use std::io::BufReader;
-use std::path::Path;
+use pxrs::inspect::{self, Span};
+use pxrs::lock::{self, Locks};
+use pxrs::types::{
+    code_page_name, file_type_name, sort_order_name, Version, PX_FILETYPE_DB_INDEXED,
+    PX_HEADER_FIELDS, PX_HEADER_SIZE,
+};
+use pxrs::{
+    cdc, check, diff, expr, schema_diff, sql, version, Error, FieldInfo, Fingerprint, PxHeader,
+    Record, Result, Table, Value,
+};
 
-use types::{PxFieldInfo, PxHeader};
+// Exit codes: 0 when everything went fine, 1 when a comparison found
+// differences or a check found problems, 2 when a file could not be read.
+const EXIT_DIFFERENT: i32 = 1;
+const EXIT_ERROR: i32 = 2;
 
-fn show_field_info(field_info: &PxFieldInfo) {
-    println!(
+fn show_field_info(out: &mut dyn Write, field_info: &FieldInfo) -> Result<()> {
+    writeln!(
+        out,
         "Name: {:<20}Type: {:<15}Size: {}",
-        String::from_utf8_lossy(&field_info.name),
-        match field_info.field_type {
-            0x01 => "Alpha",
-            0x02 => "Date",
-            0x03 => "Short Integer",
-            0x04 => "Long Integer",
-            0x05 => "Currency",
-            0x06 => "Number",
-            0x0c => "Memo BLOB",
-            0x10 => "Graphic",
-            0x0d => "BLOB",
-            0x09 => "Logical",
-            0x14 => "Time",
-            0x15 => "Timestamp",
-            0x16 => "Incremental",
-            _ => "Unknown",
-        },
+        field_info.name,
+        field_info.type_name(),
         field_info.size
-    );
+    )?;
+    Ok(())
 }
 
 #[derive(Parser)]
 #[command(name = "PXInfo")]
 #[command(version = "1.0")]
 #[command(about = "Displays header information of a Paradox database file")]
+#[command(arg_required_else_help = true)]
+#[command(
+    after_help = "Exit codes: 0 on success, 1 when differences or problems are found, 2 on errors.\nFILE arguments accept several files and glob patterns like 'data/*.DB'."
+)]
 struct Cli {
-    #[arg(short, long, value_name = "FILE", help = "Sets the input file to use")]
-    filename: String,
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        help = "Sets the input file to use, same as the info command with the fields"
+    )]
+    filename: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Records the locks taken while writing in the .LCK file of the table for Paradox"
+    )]
+    lock_records: bool,
+
+    #[cfg(feature = "mmap")]
+    #[arg(
+        long,
+        global = true,
+        help = "Maps tables into memory instead of reading them, a table truncated meanwhile crashes pxrs"
+    )]
+    mmap: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Csv,
+    Tsv,
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+    Yaml,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TargetVersion {
+    #[value(name = "3.5")]
+    V3_5,
+    #[value(name = "4")]
+    V4,
+    #[value(name = "5")]
+    V5,
+    #[value(name = "7")]
+    V7,
+}
+
+impl TargetVersion {
+    fn version(self) -> Version {
+        match self {
+            TargetVersion::V3_5 => version::PX_VERSION_3_5,
+            TargetVersion::V4 => version::PX_VERSION_4,
+            TargetVersion::V5 => version::PX_VERSION_5,
+            TargetVersion::V7 => version::PX_VERSION_7,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Prints the header of tables")]
+    Info {
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<String>,
+        #[arg(
+            long,
+            value_enum,
+            default_value = "text",
+            help = "json and yaml list every header field and the fields of all tables"
+        )]
+        output: OutputFormat,
+    },
+    #[command(about = "Prints the fields of tables with their types, sizes and keys")]
+    Schema {
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<String>,
+        #[arg(
+            long,
+            value_enum,
+            default_value = "text",
+            help = "json and yaml list the fields of all tables"
+        )]
+        output: OutputFormat,
+    },
+    #[command(about = "Prints the records of tables, one field per line")]
+    Dump {
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<String>,
+        #[arg(
+            long = "where",
+            value_name = "EXPR",
+            help = "Only prints the records matching the expression"
+        )]
+        filter: Option<String>,
+    },
+    #[command(about = "Exports the records of tables as CSV, TSV or JSON")]
+    Export {
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<String>,
+        #[arg(long, value_enum, default_value = "csv")]
+        format: ExportFormat,
+        #[arg(
+            long = "where",
+            value_name = "EXPR",
+            help = "Only exports the records matching the expression"
+        )]
+        filter: Option<String>,
+        #[arg(
+            long,
+            value_name = "DIR",
+            help = "Writes every table to DIR/<name>.<format> instead of stdout"
+        )]
+        out_dir: Option<PathBuf>,
+    },
+    #[command(about = "Lists the blobs of tables and where they are stored")]
+    Blobs {
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<String>,
+        #[arg(
+            long,
+            value_name = "DIR",
+            help = "Writes every blob to DIR/<name>-<record>-<field>"
+        )]
+        extract: Option<PathBuf>,
+    },
+    #[command(about = "Checks the structure of tables, exits with 1 if problems are found")]
+    Check {
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<String>,
+    },
+    #[command(
+        about = "Lists the Paradox lock files of tables and the users named in them, exits with 1 if a table is locked"
+    )]
+    Locks {
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<String>,
+    },
+    #[command(about = "Prints an annotated hex dump of the header or of data blocks")]
+    Inspect {
+        #[arg(value_name = "FILE")]
+        file: String,
+        #[arg(long, help = "Dumps the header, the default without --block")]
+        header: bool,
+        #[arg(
+            long = "block",
+            value_name = "N",
+            help = "Dumps data block N, can be repeated"
+        )]
+        blocks: Vec<u16>,
+    },
+    #[command(about = "Sets fields of the fixed header, printing what changes")]
+    SetHeader {
+        #[arg(value_name = "FILE")]
+        file: String,
+        #[arg(
+            value_name = "FIELD=VALUE",
+            required = true,
+            help = "Field named as in `info --output json`, the value in decimal or 0x hex"
+        )]
+        assignments: Vec<String>,
+        #[arg(long, help = "Only prints the changes, the file is left untouched")]
+        dry_run: bool,
+    },
+    #[command(about = "Writes a copy of a table in the format of another Paradox version")]
+    Convert {
+        #[arg(value_name = "FILE")]
+        file: String,
+        #[arg(
+            value_name = "OUT",
+            help = "The new table, keyed tables get their .PX file next to it"
+        )]
+        out: PathBuf,
+        #[arg(long, value_enum, value_name = "VERSION")]
+        to_version: TargetVersion,
+    },
+    #[command(about = "Compares the structure of two tables, exits with 1 if they differ")]
+    SchemaDiff {
+        #[arg(value_name = "OLD")]
+        old: String,
+        #[arg(value_name = "NEW")]
+        new: String,
+        #[arg(long, help = "Prints the differences as JSON")]
+        json: bool,
+    },
+    #[command(about = "Compares the records of two tables, exits with 1 if they differ")]
+    Diff {
+        #[arg(value_name = "OLD")]
+        old: String,
+        #[arg(value_name = "NEW")]
+        new: String,
+        #[arg(long, help = "Prints the differences as JSON")]
+        json: bool,
+    },
+    #[command(
+        about = "Lists the tables of a directory that changed since the last run, exits with 1 if any did"
+    )]
+    Changed {
+        #[arg(value_name = "DIR", default_value = ".")]
+        dir: PathBuf,
+        #[arg(
+            long,
+            value_name = "STATE",
+            help = "JSON file with the fingerprints of the last run, created if missing"
+        )]
+        since: PathBuf,
+        #[arg(
+            long,
+            help = "Only reports the changes, the state file is left untouched"
+        )]
+        dry_run: bool,
+    },
+    #[command(
+        about = "Prints the inserts, updates and deletes of a keyed table since the last run as NDJSON"
+    )]
+    Cdc {
+        #[arg(value_name = "FILE")]
+        file: String,
+        #[arg(
+            long,
+            value_name = "STATE",
+            help = "File with the record hashes of the last run, every record is an insert without it"
+        )]
+        state: PathBuf,
+        #[arg(
+            long,
+            help = "Only prints the changes, the state file is left untouched"
+        )]
+        dry_run: bool,
+    },
+    #[command(about = "Runs a SELECT query over the tables of a directory")]
+    Sql {
+        #[arg(value_name = "QUERY")]
+        query: String,
+        #[arg(
+            long,
+            value_name = "DIR",
+            default_value = ".",
+            help = "Directory holding the tables"
+        )]
+        dir: String,
+        #[arg(long, help = "Prints how every table is read to stderr")]
+        explain: bool,
+    },
+}
+
+// Tables locked by Paradox can change while they are read
+// Set by --mmap
+#[cfg(feature = "mmap")]
+static MMAP: AtomicBool = AtomicBool::new(false);
+
+fn open_table<P: AsRef<Path>>(filename: P) -> Result<Table> {
+    let filename = filename.as_ref();
+    #[cfg(feature = "mmap")]
+    let table = match MMAP.load(Ordering::Relaxed) {
+        true => Table::open_mmap(filename)?,
+        false => Table::open(filename)?,
+    };
+    #[cfg(not(feature = "mmap"))]
+    let table = Table::open(filename)?;
+    for warning in table.warnings() {
+        eprintln!("Warning: {}: {}", filename.display(), warning);
+    }
+    let (locks, errors) = Locks::for_reading(filename);
+    for error in errors {
+        eprintln!("Warning: {}", error);
+    }
+    if let Some(file) = locks.table_lock() {
+        eprintln!(
+            "Warning: {} is locked by {}, it may change while being read",
+            filename.display(),
+            match file.users.is_empty() {
+                true => "Paradox".to_string(),
+                false => file.users.join(", "),
+            }
+        );
+    }
+    Ok(table)
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string_pretty(value).map_err(|e| io::Error::from(e).into())
+}
+
+// Output closed early, e.g. piped into `head`
+fn is_broken_pipe(error: &Error) -> bool {
+    matches!(error, Error::Io { source, .. } if source.kind() == io::ErrorKind::BrokenPipe)
+}
+
+fn has_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+fn expand(pattern: &str) -> Result<Vec<PathBuf>> {
+    if !has_glob(pattern) {
+        return Ok(vec![PathBuf::from(pattern)]);
+    }
+
+    let paths = glob::glob(pattern)
+        .map_err(|e| Error::invalid(format!("Invalid pattern '{}': {}", pattern, e)))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| Error::from(io::Error::from(e)))?;
+
+    if paths.is_empty() {
+        return Err(Error::invalid(format!("No files match '{}'", pattern)));
+    }
+    Ok(paths)
+}
+
+// Runs `command` on every file the patterns expand to. Each file is
+// preceded by its name when there are several, `banner` being false for
+// commands that don't write to stdout. Errors are reported and the next
+// file is processed, the exit code is that of the worst outcome.
+fn for_each_file(
+    patterns: &[String],
+    out: &mut dyn Write,
+    banner: bool,
+    mut command: impl FnMut(&Path, &mut dyn Write) -> Result<bool>,
+) -> Result<i32> {
+    let mut code = 0;
+    let mut files = vec![];
+    for pattern in patterns {
+        match expand(pattern) {
+            Ok(paths) => files.extend(paths),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                code = EXIT_ERROR;
+            }
+        }
+    }
+
+    for (i, file) in files.iter().enumerate() {
+        if banner && files.len() > 1 {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "==> {} <==", file.display())?;
+        }
+
+        match command(file, out) {
+            Ok(true) => {}
+            Ok(false) => code = code.max(EXIT_DIFFERENT),
+            Err(e) if is_broken_pipe(&e) => return Err(e),
+            Err(e) => {
+                out.flush()?;
+                eprintln!("Error: {}", e);
+                code = EXIT_ERROR;
+            }
+        }
+    }
+
+    Ok(code)
+}
+
+// The records matching the filter, with their 1 based position
+fn matching_records<'a>(
+    table: &'a Table,
+    filter: Option<&str>,
+) -> Result<impl Iterator<Item = Result<(usize, Record<'a>)>>> {
+    let filter = filter
+        .map(|source| expr::Filter::new(source, table.fields()))
+        .transpose()?;
+    let collation = table.collation();
+
+    Ok(table.records().enumerate().filter_map(move |(n, record)| {
+        let matches = match (&record, &filter) {
+            (Ok(record), Some(filter)) => filter.matches(collation, record),
+            _ => Ok(true),
+        };
+        match matches {
+            Ok(true) => Some(record.map(|record| (n + 1, record))),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }))
+}
+
+fn show_info(out: &mut dyn Write, filename: &str) -> Result<()> {
+    let table = open_table(filename)?;
+
+    write!(out, "{}", table.header())?;
+
+    for field_info in table.fields() {
+        show_field_info(out, field_info)?;
+    }
+
+    Ok(())
+}
+
+// Number of leading fields forming the primary key
+fn key_fields(header: &PxHeader) -> usize {
+    match header.file_type {
+        PX_FILETYPE_DB_INDEXED => header.primary_key_fields.max(0) as usize,
+        _ => 0,
+    }
+}
+
+// Machine readable description of a table. The header carries the raw
+// value of every field next to the decoded names.
+#[derive(Serialize)]
+struct TableOutput<'a> {
+    file: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    header: Option<HeaderOutput<'a>>,
+    fields: Vec<FieldOutput<'a>>,
+}
+
+#[derive(Serialize)]
+struct HeaderOutput<'a> {
+    #[serde(flatten)]
+    header: HeaderFields<'a>,
+    file_version: &'static str,
+    file_type_name: &'static str,
+    sort_order_name: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code_page_name: Option<&'static str>,
+    block_size: usize,
+}
+
+// The fields of the fixed header in file order, without the Paradox 4+
+// extension for the files lacking it
+struct HeaderFields<'a>(&'a PxHeader);
+
+impl Serialize for HeaderFields<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let header = self.0;
+        let serde_json::Value::Object(values) =
+            serde_json::to_value(header).map_err(S::Error::custom)?
+        else {
+            return Err(S::Error::custom("The header is not a map"));
+        };
+
+        let mut map = serializer.serialize_map(None)?;
+        for field in PX_HEADER_FIELDS
+            .iter()
+            .filter(|f| header.has_v4_header() || f.offset < PX_HEADER_SIZE)
+        {
+            if let Some(value) = values.get(field.name) {
+                map.serialize_entry(field.name, value)?;
+            }
+        }
+        map.serialize_entry("table_name", &header.table_name)?;
+        map.end()
+    }
+}
+
+#[derive(Serialize)]
+struct FieldOutput<'a> {
+    number: usize,
+    name: &'a str,
+    field_type: u8,
+    type_name: &'static str,
+    size: u8,
+    key: bool,
+}
+
+impl<'a> TableOutput<'a> {
+    fn new(path: &'a Path, table: &'a Table, with_header: bool) -> Self {
+        let header = table.header();
+        let keys = key_fields(header);
+
+        TableOutput {
+            file: path,
+            header: with_header.then(|| HeaderOutput {
+                header: HeaderFields(header),
+                file_version: header.file_version_id.name(),
+                file_type_name: file_type_name(header.file_type),
+                sort_order_name: sort_order_name(header.sort_order),
+                code_page_name: header
+                    .has_v4_header()
+                    .then(|| code_page_name(header.dos_global_code_page)),
+                block_size: header.block_size(),
+            }),
+            fields: table
+                .fields()
+                .iter()
+                .enumerate()
+                .map(|(i, field)| FieldOutput {
+                    number: i + 1,
+                    name: &field.name,
+                    field_type: field.field_type,
+                    type_name: field.type_name(),
+                    size: field.size,
+                    key: i < keys,
+                })
+                .collect(),
+        }
+    }
+}
+
+// Describes every table as a list of documents, tables that cannot be
+// read are reported and left out.
+fn describe(
+    patterns: &[String],
+    out: &mut dyn Write,
+    format: OutputFormat,
+    with_header: bool,
+) -> Result<i32> {
+    let mut tables = vec![];
+    let code = for_each_file(patterns, out, false, |path, _| {
+        tables.push((path.to_path_buf(), open_table(path)?));
+        Ok(true)
+    })?;
+
+    let tables: Vec<_> = tables
+        .iter()
+        .map(|(path, table)| TableOutput::new(path, table, with_header))
+        .collect();
+
+    match format {
+        OutputFormat::Yaml => {
+            serde_yaml::to_writer(&mut *out, &tables).map_err(io::Error::other)?
+        }
+        _ => writeln!(out, "{}", to_json(&tables)?)?,
+    }
+
+    Ok(code)
 }
 
-fn main() -> io::Result<()> {
-    let matches = Cli::parse();
+fn info(path: &Path, out: &mut dyn Write) -> Result<bool> {
+    let table = open_table(path)?;
+    write!(out, "{}", table.header())?;
+    Ok(true)
+}
 
-    let filename = matches.filename;
-    let path = Path::new(&filename);
+fn schema(path: &Path, out: &mut dyn Write) -> Result<bool> {
+    let table = open_table(path)?;
+    let keys = key_fields(table.header());
+    let width = table
+        .fields()
+        .iter()
+        .map(|f| f.name.len())
+        .max()
+        .unwrap_or(0);
 
-    if !path.exists() {
-        eprintln!("File '{}' does not exist", filename);
-        std::process::exit(1);
+    for (i, field) in table.fields().iter().enumerate() {
+        writeln!(
+            out,
+            "{:>3}  {:<width$}  {:<15}{:>4}{}",
+            i + 1,
+            field.name,
+            field.type_name(),
+            field.size,
+            if i < keys { "  key" } else { "" },
+        )?;
     }
 
-    let file = File::open(path)?;
+    Ok(true)
+}
 
-    let mut reader = BufReader::new(file);
+fn dump(path: &Path, out: &mut dyn Write, filter: Option<&str>) -> Result<bool> {
+    let table = open_table(path)?;
+    let width = table
+        .fields()
+        .iter()
+        .map(|f| f.name.len())
+        .max()
+        .unwrap_or(0);
 
-    // Dummy header for demonstration purposes.
-    let header = PxHeader::from_reader(&mut reader).expect("Failed to read header");
+    for (i, record) in matching_records(&table, filter)?.enumerate() {
+        let (position, record) = record?;
+        if i > 0 {
+            writeln!(out)?;
+        }
+        writeln!(out, "[{}]", position)?;
+        for (index, field) in table.fields().iter().enumerate() {
+            writeln!(out, "{:<width$} : {}", field.name, record.fetch(index)?)?;
+        }
+    }
+
+    Ok(true)
+}
 
-    println!("{}", header);
+fn export(
+    path: &Path,
+    out: &mut dyn Write,
+    format: ExportFormat,
+    filter: Option<&str>,
+    out_dir: Option<&Path>,
+) -> Result<bool> {
+    let table = open_table(path)?;
 
+    let mut file;
+    let out = match out_dir {
+        Some(dir) => {
+            let stem = path.file_stem().unwrap_or_default();
+            let target = dir.join(stem).with_extension(format.extension());
+            file =
+                BufWriter::new(File::create(&target).map_err(|e| Error::from(e).in_file(&target))?);
+            &mut file as &mut dyn Write
+        }
+        None => out,
+    };
+
+    write_export(&table, out, format, filter)?;
+    Ok(true)
+}
+
+// Tables of this many blocks and more are exported in parallel, smaller
+// ones are streamed record by record
+#[cfg(feature = "rayon")]
+const PARALLEL_EXPORT_MIN_BLOCKS: u16 = 1024;
+
+// Blocks decoded together by a parallel export, bounding the rows held in
+// memory
+#[cfg(feature = "rayon")]
+const PARALLEL_EXPORT_BLOCKS: usize = 64;
+
+// The values of the records matching the filter, in table order
+fn export_rows<'a>(
+    table: &'a Table,
+    filter: Option<&str>,
+) -> Result<Box<dyn Iterator<Item = Result<Vec<Value>>> + 'a>> {
+    #[cfg(feature = "rayon")]
+    if table.header().file_blocks >= PARALLEL_EXPORT_MIN_BLOCKS {
+        return parallel_rows(table, filter);
+    }
+
+    Ok(Box::new(
+        matching_records(table, filter)?.map(|record| record?.1.fetch_values()),
+    ))
+}
+
+// `export_rows` with the blocks decoded on the rayon thread pool, a chunk
+// of the block chain at a time
+#[cfg(feature = "rayon")]
+fn parallel_rows<'a>(
+    table: &'a Table,
+    filter: Option<&str>,
+) -> Result<Box<dyn Iterator<Item = Result<Vec<Value>>> + 'a>> {
+    use rayon::prelude::*;
+
+    let filter = filter
+        .map(|source| expr::Filter::new(source, table.fields()))
+        .transpose()?;
+    let collation = table.collation();
+
+    let mut blocks = table.blocks();
+    let chunks = std::iter::from_fn(move || {
+        let chunk = blocks
+            .by_ref()
+            .take(PARALLEL_EXPORT_BLOCKS)
+            .map(|block| block.map(|block| block.number))
+            .collect::<Result<Vec<_>>>();
+        match chunk {
+            Ok(numbers) if numbers.is_empty() => None,
+            chunk => Some(chunk),
+        }
+    });
+
+    Ok(Box::new(chunks.flat_map(move |chunk| {
+        let numbers = match chunk {
+            Ok(numbers) => numbers,
+            Err(e) => return vec![Err(e)],
+        };
+        table
+            .par_records_in(numbers)
+            .filter_map(|values| {
+                let matches = match (&values, &filter) {
+                    (Ok(values), Some(filter)) => filter.matches_values(collation, values),
+                    _ => Ok(true),
+                };
+                match matches {
+                    Ok(true) => Some(values),
+                    Ok(false) => None,
+                    Err(e) => Some(Err(e)),
+                }
+            })
+            .collect::<Vec<_>>()
+    })))
+}
+
+fn write_export(
+    table: &Table,
+    out: &mut dyn Write,
+    format: ExportFormat,
+    filter: Option<&str>,
+) -> Result<()> {
+    let rows = export_rows(table, filter)?;
+    let names = table.fields().iter().map(|f| f.name.as_str());
+
+    match format {
+        ExportFormat::Csv | ExportFormat::Tsv => {
+            let delimiter = match format {
+                ExportFormat::Tsv => b'\t',
+                _ => b',',
+            };
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(delimiter)
+                .from_writer(&mut *out);
+
+            writer.write_record(names).map_err(io::Error::from)?;
+            for values in rows {
+                let values = values?.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+                writer.write_record(values).map_err(io::Error::from)?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Json => {
+            write!(out, "[")?;
+            for (i, values) in rows.enumerate() {
+                let row = diff::Row(names.clone().map(str::to_string).zip(values?).collect());
+                write!(out, "{}\n  ", if i > 0 { "," } else { "" })?;
+                serde_json::to_writer(&mut *out, &row).map_err(io::Error::from)?;
+            }
+            writeln!(out, "\n]")?;
+        }
+    }
+
+    out.flush()?;
     Ok(())
 }
+
+// Keeps file names made of field names portable
+fn file_name_part(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn blobs(path: &Path, out: &mut dyn Write, extract: Option<&Path>) -> Result<bool> {
+    let table = open_table(path)?;
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let fields: Vec<_> = (0..table.fields().len())
+        .filter(|&i| table.fields()[i].is_blob())
+        .collect();
+
+    for (n, record) in table.records().enumerate() {
+        let record = record?;
+        for &i in &fields {
+            let name = &table.fields()[i].name;
+            let location = match record.get(i)? {
+                Value::Null => continue,
+                Value::BlobRef(pointer) => format!(
+                    "MB offset 0x{:x}, index {}, {} bytes",
+                    pointer.offset, pointer.index, pointer.length
+                ),
+                Value::Memo(s) => format!("inline, {} bytes", s.len()),
+                Value::Blob(data) => format!("inline, {} bytes", data.len()),
+                value => format!("{:?}", value),
+            };
+            writeln!(out, "{:>6}  {:<20}{}", n + 1, name, location)?;
+
+            if let Some(dir) = extract {
+                let data = match record.fetch(i)? {
+                    Value::Memo(s) => s.chars().map(|c| c as u8).collect(),
+                    Value::Blob(data) => data,
+                    _ => continue,
+                };
+                let target = dir.join(format!("{}-{}-{}", stem, n + 1, file_name_part(name)));
+                fs::write(&target, data).map_err(|e| Error::from(e).in_file(&target))?;
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+fn check(path: &Path, out: &mut dyn Write) -> Result<bool> {
+    let table = open_table(path)?;
+    let problems = check::check_table(&table)?;
+
+    if problems.is_empty() {
+        writeln!(out, "ok")?;
+    }
+    for problem in &problems {
+        writeln!(out, "{}", problem)?;
+    }
+
+    Ok(problems.is_empty())
+}
+
+fn locks(path: &Path, out: &mut dyn Write) -> Result<bool> {
+    let locks = Locks::for_table(path)?;
+    if locks.is_empty() {
+        writeln!(out, "No lock files")?;
+    }
+
+    for file in &locks.files {
+        let mut line = format!("{}: {}", file.path.display(), file.kind.description());
+        if !file.users.is_empty() {
+            line += &format!(" ({})", file.users.join(", "));
+        }
+        if let Some(net_file) = &file.net_file {
+            line += &format!(", network control file {}", net_file);
+        }
+        writeln!(out, "{}", line)?;
+        for lock in &file.locks {
+            writeln!(out, "  {}", lock)?;
+        }
+    }
+
+    Ok(locks.check_writable().is_ok())
+}
+
+// Prints the spans 16 bytes to a line, the label and value next to the
+// first line of each. Repeated lines are shown as a single `*`, as hexdump
+// does.
+fn print_spans(out: &mut dyn Write, spans: &[Span]) -> Result<()> {
+    for span in spans {
+        if span.bytes.is_empty() {
+            writeln!(out, "{:08x}  -- {} --", span.offset, span.label)?;
+            continue;
+        }
+
+        let mut previous: Option<&[u8]> = None;
+        let mut repeated = false;
+        for (n, line) in span.bytes.chunks(16).enumerate() {
+            if n > 0 && previous == Some(line) && line.len() == 16 {
+                if !repeated {
+                    writeln!(out, "*")?;
+                    repeated = true;
+                }
+                continue;
+            }
+            previous = Some(line);
+            repeated = false;
+
+            let hex: Vec<_> = line.iter().map(|b| format!("{:02x}", b)).collect();
+            let annotation = match (n, span.value.is_empty()) {
+                (0, true) => span.label.clone(),
+                (0, false) => format!("{} = {}", span.label, span.value),
+                _ => String::new(),
+            };
+            let line = format!(
+                "{:08x}  {:<47}  {}",
+                span.offset + 16 * n,
+                hex.join(" "),
+                annotation
+            );
+            writeln!(out, "{}", line.trim_end())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn inspect(out: &mut dyn Write, file: &str, header: bool, blocks: &[u16]) -> Result<i32> {
+    if header || blocks.is_empty() {
+        let data = fs::read(file).map_err(|e| Error::from(e).in_file(Path::new(file)))?;
+        writeln!(out, "Header")?;
+        print_spans(out, &inspect::header_spans(&data))?;
+    }
+
+    if !blocks.is_empty() {
+        let table = open_table(file)?;
+        for &number in blocks {
+            writeln!(out)?;
+            writeln!(out, "Block {}", number)?;
+            print_spans(out, &inspect::block_spans(&table, number)?)?;
+        }
+    }
+
+    Ok(0)
+}
+
+fn parse_number(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    let number = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse().ok()?,
+    };
+    Some(if negative { -number } else { number })
+}
+
+fn set_header(
+    out: &mut dyn Write,
+    file: &str,
+    assignments: &[String],
+    dry_run: bool,
+) -> Result<i32> {
+    let path = Path::new(file);
+    let locate = |e: Error| e.in_file(path);
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(!dry_run)
+        .open(path)
+        .map_err(|e| locate(e.into()))?;
+    let _lock = match dry_run {
+        true => None,
+        false => Some(lock::lock_exclusive(&file, path)?),
+    };
+    let old = PxHeader::from_reader(&mut file).map_err(locate)?;
+
+    let mut new = old.clone();
+    for assignment in assignments {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| Error::invalid(format!("Expected FIELD=VALUE, got '{}'", assignment)))?;
+        let value = parse_number(value)
+            .ok_or_else(|| Error::invalid(format!("Invalid number '{}'", value)))?;
+        new.set_field(name.trim(), value)?;
+    }
+
+    let mut before = vec![];
+    old.write_to(&mut before)?;
+    let mut after = vec![];
+    new.write_to(&mut after)?;
+
+    let mut changed = false;
+    for field in PX_HEADER_FIELDS {
+        if let (Some(old), Some(new)) = (field.read(&before), field.read(&after)) {
+            if old != new {
+                writeln!(
+                    out,
+                    "0x{:02x} {}: {} -> {}",
+                    field.offset, field.name, old, new
+                )?;
+                changed = true;
+            }
+        }
+    }
+
+    if !changed {
+        writeln!(out, "Nothing to change")?;
+    } else if dry_run {
+        writeln!(out, "Dry run, {} was not modified", path.display())?;
+    } else {
+        file.seek(SeekFrom::Start(0))
+            .map_err(|e| locate(e.into()))?;
+        file.write_all(&after).map_err(|e| locate(e.into()))?;
+    }
+
+    Ok(0)
+}
+
+fn convert(out: &mut dyn Write, file: &str, target: &Path, version: Version) -> Result<i32> {
+    let table = open_table(file)?;
+    if fs::canonicalize(file).ok() == fs::canonicalize(target).ok() {
+        return Err(Error::invalid("The table cannot be converted in place"));
+    }
+
+    let conversion = table
+        .convert_version(target, version)
+        .map_err(|e| e.in_file(target))?;
+    for warning in &conversion.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    let header = conversion.header;
+    writeln!(
+        out,
+        "Wrote {} ({} records, version {})",
+        target.display(),
+        header.num_records,
+        header.file_version_id.name()
+    )?;
+
+    Ok(0)
+}
+
+fn schema_diff(out: &mut dyn Write, old: &str, new: &str, json: bool) -> Result<i32> {
+    let old = open_table(old)?;
+    let new = open_table(new)?;
+
+    let diff =
+        schema_diff::diff_schemas((old.header(), old.fields()), (new.header(), new.fields()));
+
+    if json {
+        writeln!(out, "{}", to_json(&diff)?)?;
+    } else {
+        write!(out, "{}", diff)?;
+    }
+
+    Ok(if diff.is_empty() { 0 } else { EXIT_DIFFERENT })
+}
+
+fn data_diff(out: &mut dyn Write, old: &str, new: &str, json: bool) -> Result<i32> {
+    let old = open_table(old)?;
+    let new = open_table(new)?;
+
+    let diff = diff::diff_tables(&old, &new)?;
+    for warning in &diff.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    if json {
+        writeln!(out, "{}", to_json(&diff)?)?;
+    } else {
+        write!(out, "{}", diff)?;
+    }
+
+    Ok(if diff.is_empty() { 0 } else { EXIT_DIFFERENT })
+}
+
+// Fingerprints of the tables of a directory by file name
+type State = BTreeMap<String, Fingerprint>;
+
+// A state file of a previous run, the default when there is none yet
+fn read_state<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    match fs::read(path) {
+        Ok(data) => serde_json::from_slice(&data)
+            .map_err(|e| Error::invalid(format!("Invalid state file: {}", e)).in_file(path)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(Error::from(e).in_file(path)),
+    }
+}
+
+// Replaces a state file through a temporary file next to it, so a run that
+// stops halfway leaves the old state readable
+fn write_state(path: &Path, json: String) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".{}.tmp", std::process::id()));
+    let temp = PathBuf::from(temp);
+
+    let written = fs::write(&temp, json + "\n").and_then(|()| fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written.map_err(|e| Error::from(e).in_file(path))
+}
+
+fn changed(out: &mut dyn Write, dir: &Path, since: &Path, dry_run: bool) -> Result<i32> {
+    let old: State = read_state(since)?;
+
+    let mut tables = vec![];
+    for entry in fs::read_dir(dir).map_err(|e| Error::from(e).in_file(dir))? {
+        let path = entry.map_err(|e| Error::from(e).in_file(dir))?.path();
+        if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("db"))
+        {
+            tables.push(path);
+        }
+    }
+    tables.sort();
+
+    let mut code = 0;
+    let mut changes = 0;
+    let mut new = State::new();
+    for path in tables {
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        // Unreadable tables keep their old fingerprint
+        let fingerprint = match open_table(&path) {
+            Ok(table) => table.fingerprint(),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                code = EXIT_ERROR;
+                if let Some(fingerprint) = old.get(&name) {
+                    new.insert(name, fingerprint.clone());
+                }
+                continue;
+            }
+        };
+
+        let status = match old.get(&name) {
+            None => Some("new"),
+            Some(old) if *old != fingerprint => Some("changed"),
+            Some(_) => None,
+        };
+        if let Some(status) = status {
+            writeln!(out, "{} {}", status, name)?;
+            changes += 1;
+        }
+        new.insert(name, fingerprint);
+    }
+
+    for name in old.keys().filter(|name| !new.contains_key(*name)) {
+        writeln!(out, "removed {}", name)?;
+        changes += 1;
+    }
+    if changes == 0 {
+        writeln!(out, "No tables changed")?;
+    }
+
+    if !dry_run {
+        write_state(since, to_json(&new)?)?;
+    }
+
+    Ok(code.max(if changes > 0 { EXIT_DIFFERENT } else { 0 }))
+}
+
+fn cdc(out: &mut dyn Write, file: &str, state: &Path, dry_run: bool) -> Result<i32> {
+    let table = open_table(file)?;
+    let previous: cdc::CdcState = read_state(state)?;
+
+    let next = cdc::capture(&table, &previous, |event| {
+        let line = serde_json::to_string(&event).map_err(io::Error::from)?;
+        writeln!(out, "{}", line)?;
+        Ok(())
+    })?;
+
+    // Only a complete run moves the state on
+    out.flush()?;
+    if !dry_run {
+        let json = serde_json::to_string(&next).map_err(io::Error::from)?;
+        write_state(state, json)?;
+    }
+
+    Ok(0)
+}
+
+fn sql(out: &mut dyn Write, query: &str, dir: &str, explain: bool) -> Result<i32> {
+    let query = sql::Query::parse(query)?;
+    let result = query.run(Path::new(dir))?;
+    for warning in &result.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    if explain {
+        for step in &result.plan {
+            eprintln!("{}", step);
+        }
+    }
+    write!(out, "{}", result)?;
+
+    Ok(0)
+}
+
+fn run(matches: Cli, out: &mut dyn Write) -> Result<i32> {
+    lock::set_lock_records(matches.lock_records);
+    #[cfg(feature = "mmap")]
+    MMAP.store(matches.mmap, Ordering::Relaxed);
+    let code = match matches.command {
+        Some(Command::Info { files, output }) => match output {
+            OutputFormat::Text => for_each_file(&files, out, true, info)?,
+            format => describe(&files, out, format, true)?,
+        },
+        Some(Command::Schema { files, output }) => match output {
+            OutputFormat::Text => for_each_file(&files, out, true, schema)?,
+            format => describe(&files, out, format, false)?,
+        },
+        Some(Command::Dump { files, filter }) => for_each_file(&files, out, true, |path, out| {
+            dump(path, out, filter.as_deref())
+        })?,
+        Some(Command::Export {
+            files,
+            format,
+            filter,
+            out_dir,
+        }) => for_each_file(&files, out, out_dir.is_none(), |path, out| {
+            export(path, out, format, filter.as_deref(), out_dir.as_deref())
+        })?,
+        Some(Command::Blobs { files, extract }) => {
+            for_each_file(&files, out, true, |path, out| {
+                blobs(path, out, extract.as_deref())
+            })?
+        }
+        Some(Command::Check { files }) => for_each_file(&files, out, true, check)?,
+        Some(Command::Locks { files }) => for_each_file(&files, out, true, locks)?,
+        Some(Command::Inspect {
+            file,
+            header,
+            blocks,
+        }) => inspect(out, &file, header, &blocks)?,
+        Some(Command::SetHeader {
+            file,
+            assignments,
+            dry_run,
+        }) => set_header(out, &file, &assignments, dry_run)?,
+        Some(Command::Convert {
+            file,
+            out: target,
+            to_version,
+        }) => convert(out, &file, &target, to_version.version())?,
+        Some(Command::SchemaDiff { old, new, json }) => schema_diff(out, &old, &new, json)?,
+        Some(Command::Diff { old, new, json }) => data_diff(out, &old, &new, json)?,
+        Some(Command::Changed {
+            dir,
+            since,
+            dry_run,
+        }) => changed(out, &dir, &since, dry_run)?,
+        Some(Command::Cdc {
+            file,
+            state,
+            dry_run,
+        }) => cdc(out, &file, &state, dry_run)?,
+        Some(Command::Sql {
+            query,
+            dir,
+            explain,
+        }) => sql(out, &query, &dir, explain)?,
+        None => {
+            show_info(out, &matches.filename.unwrap_or_default())?;
+            0
+        }
+    };
+
+    out.flush()?;
+    Ok(code)
+}
+
+fn main() {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+
+    let code = match run(Cli::parse(), &mut out) {
+        Ok(code) => code,
+        Err(e) if is_broken_pipe(&e) => 0,
+        Err(e) => {
+            // Whatever was written before the error still goes out
+            let _ = out.flush();
+            eprintln!("Error: {}", e);
+            EXIT_ERROR
+        }
+    };
+
+    std::process::exit(code);
+}
+
+#[cfg(test)]
+#[path = "testing.rs"]
+mod testing;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_without_the_v4_extension_leave_its_fields_out() {
+        let json = |header: &PxHeader| serde_json::to_string(&HeaderFields(header)).unwrap();
+
+        let mut header = PxHeader::new_table("T");
+        let v7 = json(&header);
+        assert!(v7.contains("\"change_count4\":0"));
+
+        header.file_version_id = version::PX_VERSION_3_5;
+        let v3 = json(&header);
+        assert!(!v3.contains("change_count4") && !v3.contains("file_update_time"));
+        // The fields keep the order of the file
+        assert!(v3.starts_with("{\"record_size\":"));
+        assert!(v3.ends_with(",\"dummy_d\":0,\"table_name\":\"T\"}"));
+        assert!(v7.contains(",\"dummy_d\":0,\"file_version_id2\":"));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_export_keeps_the_sequential_rows() {
+        use crate::testing::{field, write_table, TempDir};
+        use pxrs::types::{PX_FIELD_TYPE_ALPHA, PX_FIELD_TYPE_LONG_INT};
+
+        // Wide records, a few to a block, giving more blocks than a parallel
+        // export decodes at once
+        let dir = TempDir::new("main-export");
+        let fields = vec![
+            field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+            field("Name", PX_FIELD_TYPE_ALPHA, 250),
+        ];
+        let rows = (1..=300)
+            .map(|id| vec![Value::Long(id), Value::Alpha(format!("Name {}", id % 7))])
+            .collect::<Vec<_>>();
+        let table = write_table(
+            &dir.join("SAMPLE.DB"),
+            &PxHeader::new_table("SAMPLE"),
+            fields,
+            0,
+            &rows,
+        );
+        let blocks = table.header().file_blocks;
+        assert!(blocks as usize > PARALLEL_EXPORT_BLOCKS && blocks < PARALLEL_EXPORT_MIN_BLOCKS);
+
+        for filter in [None, Some("Name = 'Name 3' OR Id > 290")] {
+            let sequential = export_rows(&table, filter)
+                .unwrap()
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+            let parallel = parallel_rows(&table, filter)
+                .unwrap()
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(sequential, parallel);
+        }
+        assert_eq!(export_rows(&table, None).unwrap().count(), 300);
+    }
+}
@@ -1,198 +1,167 @@
-use std::fs::File;
-use std::io::Read;
-use std::io::Result;
-use std::mem::size_of;
-use std::ptr;
-
-use crate::types::PxBlocks;
-use crate::types::{PxFieldInfo, PxHeader};
-
-// Assume PxHeader, PxFieldInfo, px_records, PxBlocks, etc. are defined similarly to the previous structs in Rust.
-
-// Helper function to read little-endian values from a byte slice.
-fn copy_from_le<T: Default + Copy>(dst: &mut T, src: &[u8], size: usize) {
-    let mut buf: [u8; 8] = [0; 8]; // Adjust for the largest expected type (like u64)
-    buf[..size].copy_from_slice(&src[..size]);
-    *dst = unsafe { ptr::read(buf.as_ptr() as *const T) };
-}
-
-// Parses the header from unp_head into the PxHeader struct.
-fn parse_header(unp_head: &[u8], header: &mut PxHeader) {
-    let mut i = 0;
-
-    macro_rules! head_copy {
-        ($x:ident) => {
-            copy_from_le(&mut header.$x, &unp_head[i..], size_of::<PxHeader>());
-            i += size_of::<PxHeader>();
-        };
-    }
-
-    head_copy!(record_size);
-    head_copy!(header_size);
-    head_copy!(file_type);
-    head_copy!(max_table_size);
-    head_copy!(num_records);
-    head_copy!(used_blocks);
-    head_copy!(file_blocks);
-    head_copy!(first_block);
-    head_copy!(last_block);
-    head_copy!(dummy_1);
-    head_copy!(modified_flags1);
-    head_copy!(index_field_number);
-    head_copy!(primary_index_workspace);
-    head_copy!(dummy_2);
-    head_copy!(index_root_block);
-    head_copy!(index_levels);
-    head_copy!(num_fields);
-    head_copy!(primary_key_fields);
-    head_copy!(encryption1);
-    head_copy!(sort_order);
-    head_copy!(modified_flags2);
-    head_copy!(dummy_5);
-    head_copy!(change_count1);
-    head_copy!(change_count2);
-    head_copy!(dummy_6);
-    head_copy!(table_name_ptr);
-    head_copy!(field_info);
-    head_copy!(write_protected);
-    head_copy!(file_version_id);
-    head_copy!(max_blocks);
-    head_copy!(dummy_7);
-    head_copy!(aux_passwords);
-    head_copy!(dummy_8);
-    head_copy!(crypt_info_start);
-    head_copy!(crypt_info_end);
-    head_copy!(dummy_9);
-    head_copy!(auto_inc);
-    head_copy!(dummy_a);
-    head_copy!(index_update_required);
-    head_copy!(dummy_b);
-    head_copy!(dummy_c);
-    head_copy!(ref_integrity);
-    head_copy!(dummy_d);
-}
+use byte::{BytesExt, LE};
 
-fn parse_header_v4(unp_head: &[u8], header: &mut PxHeader) {
-    let mut i = 0;
+use crate::error::{Error, Result};
+use crate::types::{PxBlocks, PxFieldInfo, PxHeader, PX_HEADER_SIZE, PX_HEADER_SIZE_V4};
 
-    macro_rules! head_copy {
-        ($x:ident) => {
-            copy_from_le(&mut header.$x, &unp_head[i..], size_of::<PxHeader>());
-            i += size_of::<PxHeader>();
-        };
-    }
+// Size of the block header: nextBlock, prevBlock and addDataSize.
+pub const PX_BLOCK_HEADER_SIZE: usize = 6;
 
-    head_copy!(file_version_id2);
-    head_copy!(file_version_id3);
-    head_copy!(encryption2);
-    head_copy!(file_update_time);
-    head_copy!(hi_field_id);
-    head_copy!(hi_field_id_info);
-    head_copy!(sometimes_num_fields);
-    head_copy!(dos_global_code_page);
-    head_copy!(dummy_e);
-    head_copy!(change_count4);
-    head_copy!(dummy_f);
-    head_copy!(dummy_10);
+// Reads a NUL terminated string, mapping every byte to the matching latin-1 char.
+pub fn read_cstr(data: &[u8]) -> String {
+    data.iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as char)
+        .collect()
 }
 
 // Check if the header is supported based on fileVersionID and fileType
-fn is_header_supported(header: &PxHeader) -> bool {
+fn check_header_supported(header: &PxHeader) -> Result<()> {
     if !header.file_version_id.is_supported() {
-        eprintln!("Unknown Fileversion ID");
-        return false;
+        return Err(Error::unsupported(format!(
+            "Unknown file version ID {:02x}",
+            header.file_version_id.0
+        ))
+        .at_offset(0x39));
     }
 
-    match header.file_type {
-        0x00..=0x08 => (),
-        _ => {
-            eprintln!("Unknown FileType ID");
-            return false;
-        }
+    if !(0x00..=0x08).contains(&header.file_type) {
+        return Err(
+            Error::unsupported(format!("Unknown file type {:02x}", header.file_type))
+                .at_offset(0x04),
+        );
     }
 
-    if header.num_records > 0 && header.first_block != 1 {
-        eprintln!(
-            "Warning: numRecords > 0 ({}) && firstBlock != 1 ({})",
-            header.num_records, header.first_block
-        );
+    if !(1..=4).contains(&header.max_table_size) {
+        return Err(Error::unsupported(format!(
+            "Unknown maximum table size {}",
+            header.max_table_size
+        ))
+        .at_offset(0x05));
     }
 
-    true
+    Ok(())
 }
 
-// Parses the entire header from the file.
-pub fn parse_complete_header(fd: &mut File, header: &mut PxHeader) -> Result<Vec<PxFieldInfo>> {
-    let mut unp_head = [0u8; 0x58];
-    fd.read_exact(&mut unp_head)?;
-    parse_header(&unp_head, header);
-
-    if !is_header_supported(header) {
-        eprintln!("Unsupported header");
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "Unsupported header",
-        ));
+// Parses the entire header from the start of the file: the fixed part, the
+// field descriptions, the table name and the field names.
+pub fn parse_complete_header(data: &[u8]) -> Result<(PxHeader, Vec<PxFieldInfo>)> {
+    let mut reader = data;
+    let mut header = PxHeader::from_reader(&mut reader)?;
+
+    check_header_supported(&header)?;
+
+    if header.is_encrypted() {
+        return Err(Error::unsupported("Encrypted tables are not supported"));
     }
 
-    if header.file_version_id.0 >= 0x05
-        && header.file_type != 0x01
-        && header.file_type != 0x04
-        && header.file_type != 0x07
-    {
-        let mut unp_head4 = [0u8; 0x20];
-        fd.read_exact(&mut unp_head4)?;
-        parse_header_v4(&unp_head4, header);
+    let head = data
+        .get(..header.header_size as usize)
+        .ok_or_else(|| Error::corrupt("Truncated header").at_offset(data.len() as u64))?;
+    let num_fields = header.num_fields.max(0) as usize;
+
+    let mut i = if header.has_v4_header() {
+        PX_HEADER_SIZE_V4
+    } else {
+        PX_HEADER_SIZE
+    };
+
+    let field_info = head.get(i..i + 2 * num_fields).ok_or_else(|| {
+        Error::corrupt("Field information exceeds the header").at_offset(i as u64)
+    })?;
+    i += 2 * num_fields;
+
+    // tableNamePtr and, except for index files, the fieldNamePtrArray
+    i += 4;
+    if !header.is_index() {
+        i += 4 * num_fields;
     }
 
-    let mut fields = vec![];
+    let table_name_len = if header.file_version_id.0 >= 0x0c {
+        261
+    } else {
+        79
+    };
+    let table_name = head
+        .get(i..i + table_name_len)
+        .ok_or_else(|| Error::corrupt("Table name exceeds the header").at_offset(i as u64))?;
+    header.table_name = read_cstr(table_name);
+    i += table_name_len;
+
+    let mut fields = Vec::with_capacity(num_fields);
+
+    for (n, d) in field_info.chunks_exact(2).enumerate() {
+        let name = if header.is_index() {
+            format!("Key {}", n + 1)
+        } else {
+            let name = read_cstr(head.get(i..).unwrap_or_default());
+            i += name.len() + 1;
+            name
+        };
 
-    for _ in 0..header.num_fields {
-        let mut d = [0u8; 2];
-        fd.read_exact(&mut d)?;
+        fields.push(PxFieldInfo {
+            name,
+            field_type: d[0],
+            size: d[1],
+        });
+    }
 
-        let field_info: PxFieldInfo = PxFieldInfo {
-            name: "asdf".to_string().into_bytes().try_into().unwrap(),
-            field_type: d[0] as i32,
-            size: d[1] as i32,
-        };
-        fields.push(field_info);
+    // Index records end with the block number, the record count and an
+    // unknown short, none of which are listed in the field information.
+    if header.is_index() {
+        for name in ["Block", "Count", "Unknown"] {
+            fields.push(PxFieldInfo {
+                name: name.to_string(),
+                field_type: crate::types::PX_FIELD_TYPE_SHORT_INT,
+                size: 2,
+            });
+        }
     }
 
-    // Read the table name
-    let mut table_name = [0u8; 79];
-    fd.read_exact(&mut table_name)?;
-    // header.table_name.copy_from_slice(&table_name);
+    let record_size: usize = fields.iter().map(PxFieldInfo::data_size).sum();
+    if header.record_size <= 0 || record_size != header.record_size as usize {
+        return Err(Error::corrupt(format!(
+            "Field sizes add up to {} bytes, the record size is {}",
+            record_size, header.record_size
+        )));
+    }
 
-    Ok(fields)
+    Ok((header, fields))
 }
 
-// Assuming PxBlocks and PxFieldInfo structs exist
-pub fn parse_blocks(fd: &mut File, header: &PxHeader) -> Result<Vec<PxBlocks>> {
-    let mut blocks = Vec::with_capacity(header.file_blocks as usize);
-    let mut block_buf = vec![0u8; 0x400 * header.max_table_size as usize];
-
-    while fd.read(&mut block_buf)? > 0 {
-        let mut block_index = 0;
-
-        let mut next_block: u32 = 0;
-        let mut prev_block: u32 = 0;
-        let num_recs_in_block: u32 = 0;
-
-        copy_from_le(&mut next_block, &block_buf[block_index..], size_of::<u16>());
-        block_index += size_of::<u16>();
-        copy_from_le(&mut prev_block, &block_buf[block_index..], size_of::<u16>());
-
-        let block = PxBlocks {
-            prev_block: prev_block as i32,
-            next_block: next_block as i32,
-            num_recs_in_block: num_recs_in_block as i32,
-            records: ptr::null_mut(),
-        };
-
-        // Continue parsing the block...
-        blocks.push(block);
-    }
+// Parses the block header of a data block and borrows its records.
+pub fn parse_block<'a>(data: &'a [u8], number: u16, header: &PxHeader) -> Result<PxBlocks<'a>> {
+    let mut offset = 0;
+
+    let next_block: u16 = data
+        .read_with(&mut offset, LE)
+        .map_err(|_| truncated_block())?;
+    let prev_block: u16 = data
+        .read_with(&mut offset, LE)
+        .map_err(|_| truncated_block())?;
+    let add_data_size: i16 = data
+        .read_with(&mut offset, LE)
+        .map_err(|_| truncated_block())?;
+
+    let record_size = header.record_size as usize;
+    let num_recs_in_block = if add_data_size < 0 {
+        0
+    } else {
+        add_data_size as usize / record_size + 1
+    };
+
+    let records = data
+        .get(PX_BLOCK_HEADER_SIZE..PX_BLOCK_HEADER_SIZE + num_recs_in_block * record_size)
+        .ok_or_else(|| Error::corrupt("Block holds more records than fit in it"))?;
+
+    Ok(PxBlocks {
+        number,
+        prev_block,
+        next_block,
+        num_recs_in_block,
+        records,
+    })
+}
 
-    Ok(blocks)
+fn truncated_block() -> Error {
+    Error::corrupt("Truncated block header")
 }
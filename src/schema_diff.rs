@@ -0,0 +1,446 @@
+use std::fmt::{self, Display};
+
+use serde::Serialize;
+
+use crate::types::{code_page_name, sort_order_name, PxFieldInfo, PxHeader};
+
+// A table property that differs between the two tables
+#[derive(Debug, Serialize)]
+pub struct PropertyChange {
+    pub property: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+// Positions are 1 based, as Paradox numbers fields
+#[derive(Debug, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum FieldChange {
+    Added {
+        name: String,
+        position: usize,
+        field_type: &'static str,
+        size: u8,
+    },
+    Removed {
+        name: String,
+        position: usize,
+        field_type: &'static str,
+        size: u8,
+    },
+    Retyped {
+        name: String,
+        old_type: &'static str,
+        old_size: u8,
+        new_type: &'static str,
+        new_size: u8,
+    },
+    Reordered {
+        name: String,
+        old_position: usize,
+        new_position: usize,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaDiff {
+    pub properties: Vec<PropertyChange>,
+    pub fields: Vec<FieldChange>,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.properties.is_empty() && self.fields.is_empty()
+    }
+}
+
+fn key_fields(header: &PxHeader, fields: &[PxFieldInfo]) -> String {
+    let keys = fields
+        .iter()
+        .take(header.primary_key_fields.max(0) as usize)
+        .map(|f| f.name.as_str())
+        .collect::<Vec<_>>();
+
+    if keys.is_empty() {
+        "(none)".to_string()
+    } else {
+        keys.join(", ")
+    }
+}
+
+fn code_page(header: &PxHeader) -> String {
+    if header.has_v4_header() {
+        format!(
+            "{} ({:04x})",
+            code_page_name(header.dos_global_code_page),
+            header.dos_global_code_page
+        )
+    } else {
+        "(none)".to_string()
+    }
+}
+
+// Compares the structure of table `a` with table `b`, reporting what it
+// takes to get from the first to the second.
+pub fn diff_schemas(
+    (a, a_fields): (&PxHeader, &[PxFieldInfo]),
+    (b, b_fields): (&PxHeader, &[PxFieldInfo]),
+) -> SchemaDiff {
+    let mut properties = vec![];
+    let mut compare = |property, old: String, new: String| {
+        if old != new {
+            properties.push(PropertyChange { property, old, new });
+        }
+    };
+
+    compare(
+        "version",
+        a.file_version_id.to_string(),
+        b.file_version_id.to_string(),
+    );
+    compare(
+        "key_fields",
+        key_fields(a, a_fields),
+        key_fields(b, b_fields),
+    );
+    compare(
+        "sort_order",
+        format!("{} ({:02x})", sort_order_name(a.sort_order), a.sort_order),
+        format!("{} ({:02x})", sort_order_name(b.sort_order), b.sort_order),
+    );
+    compare("code_page", code_page(a), code_page(b));
+
+    let position = |fields: &[PxFieldInfo], name: &str| {
+        fields
+            .iter()
+            .position(|f| f.name.eq_ignore_ascii_case(name))
+    };
+    let mut fields = vec![];
+
+    for (i, field) in a_fields.iter().enumerate() {
+        if position(b_fields, &field.name).is_none() {
+            fields.push(FieldChange::Removed {
+                name: field.name.clone(),
+                position: i + 1,
+                field_type: field.type_name(),
+                size: field.size,
+            });
+        }
+    }
+
+    for (i, field) in b_fields.iter().enumerate() {
+        match position(a_fields, &field.name) {
+            None => fields.push(FieldChange::Added {
+                name: field.name.clone(),
+                position: i + 1,
+                field_type: field.type_name(),
+                size: field.size,
+            }),
+            Some(j) => {
+                let old = &a_fields[j];
+                if old.field_type != field.field_type || old.size != field.size {
+                    fields.push(FieldChange::Retyped {
+                        name: field.name.clone(),
+                        old_type: old.type_name(),
+                        old_size: old.size,
+                        new_type: field.type_name(),
+                        new_size: field.size,
+                    });
+                }
+            }
+        }
+    }
+
+    // Only the order of the columns both tables have counts, adding or
+    // removing a column does not move the others.
+    let common = |from: &[PxFieldInfo], other: &[PxFieldInfo]| {
+        from.iter()
+            .filter(|f| position(other, &f.name).is_some())
+            .map(|f| f.name.clone())
+            .collect::<Vec<_>>()
+    };
+    let a_common = common(a_fields, b_fields);
+    let b_common = common(b_fields, a_fields);
+
+    // The columns that kept their relative order form the longest common
+    // subsequence of both lists, only the rest have moved.
+    for (name, moved) in b_common.iter().zip(moved(&a_common, &b_common)) {
+        if moved {
+            fields.push(FieldChange::Reordered {
+                name: name.clone(),
+                old_position: position(a_fields, name).unwrap_or_default() + 1,
+                new_position: position(b_fields, name).unwrap_or_default() + 1,
+            });
+        }
+    }
+
+    SchemaDiff { properties, fields }
+}
+
+// Marks the names of `b` that are not part of a longest common subsequence
+// of `a` and `b`.
+fn moved(a: &[String], b: &[String]) -> Vec<bool> {
+    let same = |i: usize, j: usize| a[i].eq_ignore_ascii_case(&b[j]);
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if same(i, j) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut moved = vec![true; b.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if same(i, j) {
+            moved[j] = false;
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    moved
+}
+
+impl Display for PropertyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let property = match self.property {
+            "version" => "File-Version",
+            "key_fields" => "Key fields",
+            "sort_order" => "Sort-Order",
+            "code_page" => "Codepage",
+            other => other,
+        };
+
+        write!(f, "{}: {} -> {}", property, self.old, self.new)
+    }
+}
+
+impl Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldChange::Added {
+                name,
+                position,
+                field_type,
+                size,
+            } => write!(
+                f,
+                "+ {} ({} {}) at position {}",
+                name, field_type, size, position
+            ),
+            FieldChange::Removed {
+                name,
+                position,
+                field_type,
+                size,
+            } => write!(
+                f,
+                "- {} ({} {}) at position {}",
+                name, field_type, size, position
+            ),
+            FieldChange::Retyped {
+                name,
+                old_type,
+                old_size,
+                new_type,
+                new_size,
+            } => write!(
+                f,
+                "~ {}: {} {} -> {} {}",
+                name, old_type, old_size, new_type, new_size
+            ),
+            FieldChange::Reordered {
+                name,
+                old_position,
+                new_position,
+            } => write!(
+                f,
+                "> {}: position {} -> {}",
+                name, old_position, new_position
+            ),
+        }
+    }
+}
+
+impl Display for SchemaDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "Schemas are identical");
+        }
+
+        for change in &self.properties {
+            writeln!(f, "{}", change)?;
+        }
+        for change in &self.fields {
+            writeln!(f, "{}", change)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::field;
+    use crate::types::{
+        Version, PX_FIELD_TYPE_ALPHA, PX_FIELD_TYPE_DATE, PX_FIELD_TYPE_LONG_INT,
+        PX_FIELD_TYPE_NUMBER,
+    };
+
+    fn keyed(keys: i16) -> PxHeader {
+        let mut header = PxHeader::new_table("T");
+        header.primary_key_fields = keys;
+        header
+    }
+
+    fn lines(diff: &SchemaDiff) -> Vec<String> {
+        diff.to_string().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn reports_added_removed_and_retyped_fields() {
+        let old = [
+            field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+            field("Name", PX_FIELD_TYPE_ALPHA, 20),
+            field("Born", PX_FIELD_TYPE_DATE, 4),
+        ];
+        let new = [
+            field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+            field("Name", PX_FIELD_TYPE_ALPHA, 40),
+            field("Score", PX_FIELD_TYPE_NUMBER, 8),
+        ];
+        let header = keyed(1);
+        let diff = diff_schemas((&header, &old), (&header, &new));
+        assert!(diff.properties.is_empty());
+        assert_eq!(
+            lines(&diff),
+            [
+                "- Born (Date 4) at position 3",
+                "~ Name: Alpha 20 -> Alpha 40",
+                "+ Score (Number 8) at position 3",
+            ]
+        );
+
+        let same = diff_schemas((&header, &old), (&header, &old));
+        assert!(same.is_empty());
+        assert_eq!(same.to_string(), "Schemas are identical\n");
+    }
+
+    #[test]
+    fn reports_table_properties() {
+        let fields = [
+            field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+            field("Name", PX_FIELD_TYPE_ALPHA, 20),
+        ];
+        let old = keyed(1);
+        let mut new = keyed(2);
+        new.file_version_id = Version(0x0b);
+        new.sort_order = 0xb7;
+        new.dos_global_code_page = 0x0352;
+
+        let diff = diff_schemas((&old, &fields), (&new, &fields));
+        let properties = diff
+            .properties
+            .iter()
+            .map(|p| p.property)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            properties,
+            ["version", "key_fields", "sort_order", "code_page"]
+        );
+        assert_eq!(diff.properties[1].to_string(), "Key fields: Id -> Id, Name");
+        assert!(diff.fields.is_empty());
+    }
+
+    #[test]
+    fn serializes_changes_by_kind() {
+        let old = [
+            field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+            field("Gone", PX_FIELD_TYPE_DATE, 4),
+        ];
+        let new = [
+            field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+            field("New", PX_FIELD_TYPE_ALPHA, 10),
+        ];
+        let diff = diff_schemas((&keyed(1), &old), (&keyed(0), &new));
+        assert_eq!(
+            serde_json::to_value(&diff).unwrap(),
+            serde_json::json!({
+                "properties": [
+                    {"property": "key_fields", "old": "Id", "new": "(none)"},
+                ],
+                "fields": [
+                    {
+                        "change": "removed",
+                        "name": "Gone",
+                        "position": 2,
+                        "field_type": "Date",
+                        "size": 4,
+                    },
+                    {
+                        "change": "added",
+                        "name": "New",
+                        "position": 2,
+                        "field_type": "Alpha",
+                        "size": 10,
+                    },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn reports_only_the_fields_that_moved() {
+        let names = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| field(name, PX_FIELD_TYPE_LONG_INT, 4))
+                .collect::<Vec<_>>()
+        };
+        let header = keyed(0);
+        let diff = diff_schemas(
+            (&header, &names(&["A", "B", "C", "D"])),
+            (&header, &names(&["B", "C", "D", "A"])),
+        );
+        assert_eq!(lines(&diff), ["> A: position 1 -> 4"]);
+
+        // Added and removed columns do not move the others.
+        let diff = diff_schemas(
+            (&header, &names(&["A", "X", "B", "C"])),
+            (&header, &names(&["A", "B", "Y", "C"])),
+        );
+        assert_eq!(
+            lines(&diff),
+            [
+                "- X (Long Integer 4) at position 2",
+                "+ Y (Long Integer 4) at position 3"
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_field_names_ignoring_case() {
+        let old = [
+            field("id", PX_FIELD_TYPE_LONG_INT, 4),
+            field("name", PX_FIELD_TYPE_ALPHA, 20),
+        ];
+        let new = [
+            field("NAME", PX_FIELD_TYPE_ALPHA, 30),
+            field("ID", PX_FIELD_TYPE_LONG_INT, 4),
+        ];
+        let header = keyed(0);
+        let diff = diff_schemas((&header, &old), (&header, &new));
+        assert_eq!(
+            lines(&diff),
+            ["~ NAME: Alpha 20 -> Alpha 30", "> ID: position 1 -> 2"]
+        );
+    }
+}
@@ -0,0 +1,1180 @@
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+use crate::collation::Collation;
+use crate::currency::PxCurrency;
+use crate::expr::{as_number, field_position, unknown_field, CompareOp, Expr, Parser, Token};
+use crate::table::{Record, Table};
+use crate::types::{Value, PX_FILETYPE_DB_INDEXED};
+
+// SELECT queries over the tables of a directory:
+//
+//   SELECT [DISTINCT] items FROM table [[AS] alias]
+//     [[LEFT | INNER] JOIN table [[AS] alias] ON condition]...
+//     [WHERE condition] [GROUP BY exprs]
+//     [ORDER BY items [ASC | DESC]] [LIMIT n]
+//
+// Items are expressions, `*`, `table.*`, or the aggregates COUNT(*),
+// COUNT, SUM, AVG, MIN and MAX of an expression. Conditions use the
+// `--where` syntax, fields can be qualified by their table. Alpha values
+// compare and sort in the order of the first table's keys.
+
+fn query_error(msg: &str) -> Error {
+    Error::invalid(msg)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Projection {
+    Expr(Expr),
+    // The argument is None for COUNT(*)
+    Aggregate(Aggregate, Option<Expr>),
+}
+
+#[derive(Debug, Clone)]
+pub enum SelectItem {
+    // `*`, or `table.*`
+    Wildcard(Option<String>),
+    Projection(Projection, Option<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct TableRef {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Join {
+    pub table: TableRef,
+    pub on: Expr,
+    pub left: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub distinct: bool,
+    pub items: Vec<SelectItem>,
+    pub from: TableRef,
+    pub joins: Vec<Join>,
+    pub filter: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    // The bool is set for descending order
+    pub order_by: Vec<(Projection, bool)>,
+    pub limit: Option<usize>,
+}
+
+// Words that end a table reference without alias
+const CLAUSES: [&str; 12] = [
+    "JOIN", "LEFT", "INNER", "OUTER", "ON", "WHERE", "GROUP", "ORDER", "BY", "LIMIT", "AS", "FROM",
+];
+
+fn parse_name(parser: &mut Parser) -> Result<String> {
+    match parser.peek() {
+        Some(Token::Word(name) | Token::Quoted(name)) => {
+            let name = name.clone();
+            parser.next_token();
+            Ok(name)
+        }
+        _ => Err(parser.error("Expected a name")),
+    }
+}
+
+fn parse_table(parser: &mut Parser) -> Result<TableRef> {
+    let mut name = parse_name(parser)?;
+    // File names like CUSTOMER.DB
+    if parser.eat_symbol(".") {
+        name = format!("{}.{}", name, parse_name(parser)?);
+    }
+
+    let has_alias = match parser.peek() {
+        Some(Token::Quoted(_)) => true,
+        Some(Token::Word(w)) => !CLAUSES.iter().any(|c| w.eq_ignore_ascii_case(c)),
+        _ => false,
+    };
+    let alias = if parser.eat_keyword("AS") || has_alias {
+        Some(parse_name(parser)?)
+    } else {
+        None
+    };
+
+    Ok(TableRef { name, alias })
+}
+
+fn parse_projection(parser: &mut Parser) -> Result<Projection> {
+    let aggregate = match (parser.peek(), parser.peek_at(1)) {
+        (Some(Token::Word(w)), Some(Token::Symbol("("))) => match w.to_ascii_uppercase().as_str() {
+            "COUNT" => Some(Aggregate::Count),
+            "SUM" => Some(Aggregate::Sum),
+            "AVG" => Some(Aggregate::Avg),
+            "MIN" => Some(Aggregate::Min),
+            "MAX" => Some(Aggregate::Max),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let Some(aggregate) = aggregate else {
+        return Ok(Projection::Expr(parser.parse_expr()?));
+    };
+
+    parser.next_token();
+    parser.next_token();
+    let argument = if aggregate == Aggregate::Count && parser.eat_symbol("*") {
+        None
+    } else {
+        Some(parser.parse_expr()?)
+    };
+    parser.expect_symbol(")")?;
+
+    Ok(Projection::Aggregate(aggregate, argument))
+}
+
+fn parse_item(parser: &mut Parser) -> Result<SelectItem> {
+    if parser.eat_symbol("*") {
+        return Ok(SelectItem::Wildcard(None));
+    }
+    if let (
+        Some(Token::Word(table) | Token::Quoted(table)),
+        Some(Token::Symbol(".")),
+        Some(Token::Symbol("*")),
+    ) = (parser.peek(), parser.peek_at(1), parser.peek_at(2))
+    {
+        let table = table.clone();
+        for _ in 0..3 {
+            parser.next_token();
+        }
+        return Ok(SelectItem::Wildcard(Some(table)));
+    }
+
+    let projection = parse_projection(parser)?;
+    let alias = if parser.eat_keyword("AS") {
+        Some(parse_name(parser)?)
+    } else {
+        None
+    };
+
+    Ok(SelectItem::Projection(projection, alias))
+}
+
+// Parses a comma separated list
+fn parse_list<T>(parser: &mut Parser, item: impl Fn(&mut Parser) -> Result<T>) -> Result<Vec<T>> {
+    let mut items = vec![item(parser)?];
+    while parser.eat_symbol(",") {
+        items.push(item(parser)?);
+    }
+    Ok(items)
+}
+
+impl Query {
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut parser = Parser::new(source)?;
+        let parser = &mut parser;
+
+        parser.expect_keyword("SELECT")?;
+        let distinct = parser.eat_keyword("DISTINCT");
+        let items = parse_list(parser, parse_item)?;
+
+        parser.expect_keyword("FROM")?;
+        let from = parse_table(parser)?;
+
+        let mut joins = vec![];
+        while ["JOIN", "LEFT", "INNER"]
+            .iter()
+            .any(|k| parser.is_keyword(k))
+        {
+            let left = parser.eat_keyword("LEFT");
+            if left {
+                parser.eat_keyword("OUTER");
+            } else {
+                parser.eat_keyword("INNER");
+            }
+            parser.expect_keyword("JOIN")?;
+            let table = parse_table(parser)?;
+            parser.expect_keyword("ON")?;
+            let on = parser.parse_expr()?;
+            joins.push(Join { table, on, left });
+        }
+
+        let filter = if parser.eat_keyword("WHERE") {
+            Some(parser.parse_expr()?)
+        } else {
+            None
+        };
+
+        let mut group_by = vec![];
+        if parser.eat_keyword("GROUP") {
+            parser.expect_keyword("BY")?;
+            group_by = parse_list(parser, Parser::parse_expr)?;
+        }
+
+        let mut order_by = vec![];
+        if parser.eat_keyword("ORDER") {
+            parser.expect_keyword("BY")?;
+            order_by = parse_list(parser, |parser| {
+                let projection = parse_projection(parser)?;
+                let descending = parser.eat_keyword("DESC");
+                if !descending {
+                    parser.eat_keyword("ASC");
+                }
+                Ok((projection, descending))
+            })?;
+        }
+
+        let mut limit = None;
+        if parser.eat_keyword("LIMIT") {
+            match parser.peek() {
+                Some(&Token::Number(n)) if n >= 0.0 && n.fract() == 0.0 => {
+                    limit = Some(n as usize);
+                    parser.next_token();
+                }
+                _ => return Err(parser.error("Expected a number of rows")),
+            }
+        }
+
+        if !parser.at_end() {
+            return Err(parser.error("Unexpected input"));
+        }
+
+        Ok(Query {
+            distinct,
+            items,
+            from,
+            joins,
+            filter,
+            group_by,
+            order_by,
+            limit,
+        })
+    }
+}
+
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    // How every table was read
+    pub plan: Vec<String>,
+    // Problems with the tables, by table name
+    pub warnings: Vec<String>,
+}
+
+impl Display for QueryResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.columns.join("\t"))?;
+        for row in &self.rows {
+            let values = row.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+            writeln!(f, "{}", values.join("\t"))?;
+        }
+        Ok(())
+    }
+}
+
+// A table of the query, its fields starting at `offset` in joined rows
+struct Source {
+    name: String,
+    table: Table,
+    offset: usize,
+}
+
+impl Source {
+    fn columns(&self) -> std::ops::Range<usize> {
+        self.offset..self.offset + self.table.fields().len()
+    }
+}
+
+// Finds the table file for a name, "customers" matching CUSTOMERS.DB
+fn find_table(dir: &Path, name: &str) -> Result<PathBuf> {
+    let path = dir.join(name);
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let matches = |part: Option<&std::ffi::OsStr>, text: &str| {
+            part.and_then(|p| p.to_str())
+                .is_some_and(|p| p.eq_ignore_ascii_case(text))
+        };
+        if matches(path.file_stem(), name) && matches(path.extension(), "db") {
+            return Ok(path);
+        }
+    }
+
+    Err(Error::invalid(format!(
+        "Table '{}' not found in {}",
+        name,
+        dir.display()
+    )))
+}
+
+fn resolve(sources: &[Source], table: Option<&str>, name: &str) -> Result<usize> {
+    let mut found = sources
+        .iter()
+        .filter(|s| table.is_none_or(|t| s.name.eq_ignore_ascii_case(t)))
+        .filter_map(|s| field_position(s.table.fields(), name).map(|i| s.offset + i));
+
+    let qualified = match table {
+        Some(table) => format!("{}.{}", table, name),
+        None => name.to_string(),
+    };
+    let column = found.next().ok_or_else(|| unknown_field(&qualified))?;
+    if found.next().is_some() {
+        return Err(query_error(&format!(
+            "Field '{}' is ambiguous, qualify it with its table",
+            qualified
+        )));
+    }
+
+    Ok(column)
+}
+
+// The conditions that must all hold for `expr` to hold
+fn conjuncts(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::And(a, b) => {
+            let mut all = conjuncts(a);
+            all.extend(conjuncts(b));
+            all
+        }
+        expr => vec![expr],
+    }
+}
+
+// Key ranges of the first key field, from comparisons with literals.
+// Conditions compare Alpha values in the order of the query, only ranges
+// of a table keyed in that same order can skip blocks.
+fn key_bounds(
+    conditions: &[&Expr],
+    column: usize,
+    collation: Collation,
+    query_collation: Collation,
+) -> Result<(Bound<Value>, Bound<Value>)> {
+    let mut lo = Bound::Unbounded;
+    let mut hi = Bound::Unbounded;
+
+    for condition in conditions {
+        let Expr::Compare(a, op, b) = condition else {
+            continue;
+        };
+        let (op, value) = match (a.as_ref(), b.as_ref()) {
+            (Expr::Column(c), Expr::Literal(v)) if *c == column => (*op, v),
+            (Expr::Literal(v), Expr::Column(c)) if *c == column => match op {
+                CompareOp::Lt => (CompareOp::Gt, v),
+                CompareOp::Le => (CompareOp::Ge, v),
+                CompareOp::Gt => (CompareOp::Lt, v),
+                CompareOp::Ge => (CompareOp::Le, v),
+                op => (*op, v),
+            },
+            _ => continue,
+        };
+        if *value == Value::Null {
+            continue;
+        }
+        if matches!(value, Value::Alpha(_))
+            && collation != query_collation
+            && !matches!(op, CompareOp::Eq)
+        {
+            continue;
+        }
+
+        let (new_lo, new_hi) = match op {
+            CompareOp::Eq => (Some(Bound::Included(value)), Some(Bound::Included(value))),
+            CompareOp::Gt => (Some(Bound::Excluded(value)), None),
+            CompareOp::Ge => (Some(Bound::Included(value)), None),
+            CompareOp::Lt => (None, Some(Bound::Excluded(value))),
+            CompareOp::Le => (None, Some(Bound::Included(value))),
+            CompareOp::Ne => (None, None),
+        };
+
+        // Keep the tighter bound
+        if let Some(new) = new_lo {
+            lo = match (&lo, new) {
+                (
+                    Bound::Included(v) | Bound::Excluded(v),
+                    Bound::Included(n) | Bound::Excluded(n),
+                ) if collation.key_order(v, n)?.is_gt() => lo,
+                _ => new.cloned(),
+            };
+        }
+        if let Some(new) = new_hi {
+            hi = match (&hi, new) {
+                (
+                    Bound::Included(v) | Bound::Excluded(v),
+                    Bound::Included(n) | Bound::Excluded(n),
+                ) if collation.key_order(v, n)?.is_lt() => hi,
+                _ => new.cloned(),
+            };
+        }
+    }
+
+    Ok((lo, hi))
+}
+
+// Rows produced one at a time, so that a LIMIT can stop reading early
+type Rows<'a> = Box<dyn Iterator<Item = Result<Vec<Value>>> + 'a>;
+
+// Reads the rows of a source keeping those `conditions` allow, using the
+// primary index when they restrict the first key field.
+fn scan<'a>(
+    source: &'a Source,
+    conditions: Vec<&'a Expr>,
+    collation: Collation,
+    plan: &mut Vec<String>,
+) -> Result<Rows<'a>> {
+    let table = &source.table;
+    let keyed =
+        table.header().file_type == PX_FILETYPE_DB_INDEXED && table.header().primary_key_fields > 0;
+
+    let (lo, hi) = match keyed {
+        true => key_bounds(&conditions, source.offset, table.collation(), collation)?,
+        false => (Bound::Unbounded, Bound::Unbounded),
+    };
+
+    let records: Box<dyn Iterator<Item = Result<Record<'a>>> + 'a> =
+        if lo != Bound::Unbounded || hi != Bound::Unbounded {
+            plan.push(format!(
+                "{}: key range scan on {}",
+                source.name,
+                table.fields()[0].name
+            ));
+            Box::new(table.range((lo, hi))?)
+        } else {
+            plan.push(format!("{}: full scan", source.name));
+            Box::new(table.records())
+        };
+
+    let offset = source.offset;
+    let keep = move |row: &[Value]| -> Result<bool> {
+        for condition in &conditions {
+            if !condition.matches_in(collation, &mut |i| Ok(row[i - offset].clone()))? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    };
+
+    Ok(Box::new(records.filter_map(move |record| {
+        let row = record.and_then(|record| record.fetch_values());
+        match row.as_deref().map(&keep) {
+            Ok(Ok(false)) => None,
+            Ok(Err(e)) => Some(Err(e)),
+            _ => Some(row),
+        }
+    })))
+}
+
+// Values equal under `compare_values` get the same key, numbers of
+// different types included. Nulls never join.
+fn join_key(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Alpha(s) | Value::Memo(s) => Some(format!("s{}", s)),
+        value => match as_number(value) {
+            Some(n) => Some(format!("n{}", n)),
+            None => Some(format!("{:?}", value)),
+        },
+    }
+}
+
+fn row_key(values: &[Value]) -> String {
+    values.iter().map(|v| format!("{:?}\u{0}", v)).collect()
+}
+
+fn eval_on(expr: &Expr, row: &[Value], collation: Collation) -> Result<Value> {
+    expr.eval_in(collation, &mut |i| Ok(row[i].clone()))
+}
+
+// The rows of a table joined to the rows before it, held in memory and
+// looked up for every row coming in
+struct JoinedTable {
+    rows: Vec<Vec<Value>>,
+    width: usize,
+    on: Expr,
+    left_join: bool,
+    // The side of an equality in `on` reading the previous tables, whose
+    // value is looked up in `buckets`
+    lookup: Option<Expr>,
+    buckets: HashMap<String, Vec<usize>>,
+    collation: Collation,
+}
+
+impl JoinedTable {
+    fn new(
+        rows: Vec<Vec<Value>>,
+        on: Expr,
+        left_join: bool,
+        columns: std::ops::Range<usize>,
+        collation: Collation,
+    ) -> Result<Self> {
+        let start = columns.start;
+        let on_right = |e: &Expr| {
+            let used = e.columns();
+            !used.is_empty() && used.iter().all(|c| columns.contains(c))
+        };
+        let on_left = |e: &Expr| {
+            let used = e.columns();
+            !used.is_empty() && used.iter().all(|&c| c < start)
+        };
+
+        // An equality between the new table and the previous ones allows a
+        // hash join, otherwise every pair is tried
+        let equality = conjuncts(&on).into_iter().find_map(|c| match c {
+            Expr::Compare(a, CompareOp::Eq, b) if on_left(a) && on_right(b) => Some((a, b)),
+            Expr::Compare(a, CompareOp::Eq, b) if on_right(a) && on_left(b) => Some((b, a)),
+            _ => None,
+        });
+
+        let mut buckets: HashMap<String, Vec<usize>> = HashMap::new();
+        if let Some((_, right_expr)) = equality {
+            for (i, row) in rows.iter().enumerate() {
+                let value = right_expr.eval_in(collation, &mut |c| Ok(row[c - start].clone()))?;
+                if let Some(key) = join_key(&value) {
+                    buckets.entry(key).or_default().push(i);
+                }
+            }
+        }
+        let lookup = equality.map(|(left_expr, _)| left_expr.as_ref().clone());
+
+        Ok(JoinedTable {
+            rows,
+            width: columns.len(),
+            on,
+            left_join,
+            lookup,
+            buckets,
+            collation,
+        })
+    }
+
+    // The combined rows for a row of the previous tables
+    fn join(&self, row: Vec<Value>) -> Result<Vec<Vec<Value>>> {
+        let candidates: Box<dyn Iterator<Item = usize> + '_> = match &self.lookup {
+            Some(left_expr) => match join_key(&eval_on(left_expr, &row, self.collation)?) {
+                Some(key) => Box::new(self.buckets.get(&key).into_iter().flatten().copied()),
+                None => Box::new(std::iter::empty()),
+            },
+            None => Box::new(0..self.rows.len()),
+        };
+
+        let mut joined = vec![];
+        for i in candidates {
+            let mut combined = row.clone();
+            combined.extend(self.rows[i].iter().cloned());
+            if self
+                .on
+                .matches_in(self.collation, &mut |c| Ok(combined[c].clone()))?
+            {
+                joined.push(combined);
+            }
+        }
+
+        if self.left_join && joined.is_empty() {
+            let mut combined = row;
+            combined.resize(combined.len() + self.width, Value::Null);
+            joined.push(combined);
+        }
+
+        Ok(joined)
+    }
+}
+
+// Currency values are summed exactly as long as every value is one
+fn add(sum: Option<Value>, value: &Value, number: impl Fn() -> Result<f64>) -> Result<Value> {
+    let currency = |value: &Value| match value {
+        Value::Currency(v) => Some(*v),
+        _ => None,
+    };
+    let total = match &sum {
+        Some(sum) => currency(sum),
+        None => Some(PxCurrency::ZERO),
+    };
+    let exact = total
+        .zip(currency(value))
+        .and_then(|(total, value)| total.checked_add(value));
+    Ok(match exact {
+        Some(total) => total.into(),
+        None => Value::Number(sum.as_ref().and_then(as_number).unwrap_or(0.0) + number()?),
+    })
+}
+
+enum Accumulator {
+    Count(u64),
+    Sum(Option<Value>),
+    Avg(f64, u64),
+    Min(Value),
+    Max(Value),
+}
+
+impl Accumulator {
+    fn new(aggregate: Aggregate) -> Self {
+        match aggregate {
+            Aggregate::Count => Accumulator::Count(0),
+            Aggregate::Sum => Accumulator::Sum(None),
+            Aggregate::Avg => Accumulator::Avg(0.0, 0),
+            Aggregate::Min => Accumulator::Min(Value::Null),
+            Aggregate::Max => Accumulator::Max(Value::Null),
+        }
+    }
+
+    // `value` is None for COUNT(*), nulls are skipped otherwise. MIN and
+    // MAX order Alpha values by `collation`.
+    fn add(&mut self, value: Option<Value>, collation: Collation) -> Result<()> {
+        let value = match value {
+            None => Value::Logical(true),
+            Some(Value::Null) => return Ok(()),
+            Some(value) => value,
+        };
+        let number =
+            || as_number(&value).ok_or_else(|| query_error("SUM and AVG need numeric values"));
+
+        match self {
+            Accumulator::Count(n) => *n += 1,
+            Accumulator::Sum(sum) => *sum = Some(add(sum.take(), &value, number)?),
+            Accumulator::Avg(sum, n) => {
+                *sum += number()?;
+                *n += 1;
+            }
+            Accumulator::Min(min) => {
+                if *min == Value::Null || collation.key_order(&value, min)?.is_lt() {
+                    *min = value;
+                }
+            }
+            Accumulator::Max(max) => {
+                if *max == Value::Null || collation.key_order(&value, max)?.is_gt() {
+                    *max = value;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Value {
+        match self {
+            Accumulator::Count(n) => Value::Number(n as f64),
+            Accumulator::Sum(sum) => sum.unwrap_or(Value::Null),
+            Accumulator::Avg(_, 0) => Value::Null,
+            Accumulator::Avg(sum, n) => Value::Number(sum / n as f64),
+            Accumulator::Min(value) | Accumulator::Max(value) => value,
+        }
+    }
+}
+
+fn column_name(projection: &Projection) -> String {
+    let expr_name = |e: &Expr| match e {
+        Expr::Field(_, name) => name.clone(),
+        e => e.to_string(),
+    };
+
+    match projection {
+        Projection::Expr(e) => expr_name(e),
+        Projection::Aggregate(aggregate, argument) => format!(
+            "{}({})",
+            format!("{:?}", aggregate).to_ascii_uppercase(),
+            argument.as_ref().map_or("*".to_string(), expr_name)
+        ),
+    }
+}
+
+fn bind_projection(projection: &mut Projection, sources: &[Source]) -> Result<()> {
+    let resolve = |table: Option<&str>, name: &str| resolve(sources, table, name);
+    match projection {
+        Projection::Expr(e) | Projection::Aggregate(_, Some(e)) => e.bind(&resolve),
+        Projection::Aggregate(_, None) => Ok(()),
+    }
+}
+
+impl Query {
+    // Runs the query over the tables found in `dir`.
+    pub fn run(&self, dir: &Path) -> Result<QueryResult> {
+        let mut sources: Vec<Source> = vec![];
+        for table in std::iter::once(&self.from).chain(self.joins.iter().map(|j| &j.table)) {
+            let name = table.alias.clone().unwrap_or_else(|| table.name.clone());
+            if sources.iter().any(|s| s.name.eq_ignore_ascii_case(&name)) {
+                return Err(query_error(&format!(
+                    "Table '{}' is used twice, give it an alias",
+                    name
+                )));
+            }
+            let offset = sources.last().map_or(0, |s| s.columns().end);
+            sources.push(Source {
+                name,
+                table: Table::open(find_table(dir, &table.name)?)?,
+                offset,
+            });
+        }
+        let resolve_all = |table: Option<&str>, name: &str| resolve(&sources, table, name);
+        // Alpha values are ordered like the keys of the first table
+        let collation = sources[0].table.collation();
+
+        // Select list, with wildcards expanded
+        let mut columns = vec![];
+        let mut projections = vec![];
+        for item in &self.items {
+            match item {
+                SelectItem::Wildcard(table) => {
+                    let mut found = false;
+                    for source in &sources {
+                        if table
+                            .as_ref()
+                            .is_some_and(|t| !source.name.eq_ignore_ascii_case(t))
+                        {
+                            continue;
+                        }
+                        found = true;
+                        for (i, field) in source.table.fields().iter().enumerate() {
+                            columns.push(field.name.clone());
+                            projections.push(Projection::Expr(Expr::Column(source.offset + i)));
+                        }
+                    }
+                    if !found {
+                        return Err(query_error(&format!(
+                            "Unknown table '{}'",
+                            table.as_deref().unwrap_or_default()
+                        )));
+                    }
+                }
+                SelectItem::Projection(projection, alias) => {
+                    columns.push(alias.clone().unwrap_or_else(|| column_name(projection)));
+                    let mut projection = projection.clone();
+                    bind_projection(&mut projection, &sources)?;
+                    projections.push(projection);
+                }
+            }
+        }
+        let visible = projections.len();
+
+        // ORDER BY items name an output column, give its position, or are
+        // computed as hidden columns
+        let mut order = vec![];
+        for (projection, descending) in &self.order_by {
+            let position = match projection {
+                Projection::Expr(Expr::Field(None, name)) => {
+                    columns.iter().position(|c| c.eq_ignore_ascii_case(name))
+                }
+                Projection::Expr(Expr::Literal(Value::Number(n))) => {
+                    let n = *n as usize;
+                    if n < 1 || n > visible {
+                        return Err(query_error(&format!(
+                            "ORDER BY position {} is out of range",
+                            n
+                        )));
+                    }
+                    Some(n - 1)
+                }
+                _ => None,
+            };
+
+            let position = match position {
+                Some(position) => position,
+                None => {
+                    let mut projection = projection.clone();
+                    bind_projection(&mut projection, &sources)?;
+                    match projections.iter().position(|p| *p == projection) {
+                        Some(position) => position,
+                        None => {
+                            projections.push(projection);
+                            projections.len() - 1
+                        }
+                    }
+                }
+            };
+            order.push((position, *descending));
+        }
+
+        let mut filter = self.filter.clone();
+        if let Some(filter) = &mut filter {
+            filter.bind(&resolve_all)?;
+        }
+        let mut group_by = self.group_by.clone();
+        for expr in &mut group_by {
+            expr.bind(&resolve_all)?;
+        }
+
+        // Conditions on a single table are checked while reading it, except
+        // for the outer side of LEFT JOINs where a missing row can still
+        // satisfy them. Comparisons on the key can skip blocks there too.
+        let conditions = filter.as_ref().map(conjuncts).unwrap_or_default();
+        let mut plan = vec![];
+        let mut rows: Rows = Box::new(std::iter::empty());
+
+        for (i, source) in sources.iter().enumerate() {
+            let outer = i > 0 && self.joins[i - 1].left;
+            let local = conditions
+                .iter()
+                .copied()
+                .filter(|c| {
+                    let columns = c.columns();
+                    columns.iter().all(|c| source.columns().contains(c))
+                        && (!outer || matches!(c, Expr::Compare(..)) && !columns.is_empty())
+                })
+                .collect::<Vec<_>>();
+
+            let table_rows = scan(source, local, collation, &mut plan)?;
+
+            if i == 0 {
+                rows = table_rows;
+                continue;
+            }
+
+            let join_spec = &self.joins[i - 1];
+            let mut on = join_spec.on.clone();
+            on.bind(&|table, name| resolve(&sources[..=i], table, name))?;
+            let joined = JoinedTable::new(
+                table_rows.collect::<Result<_>>()?,
+                on,
+                join_spec.left,
+                source.columns(),
+                collation,
+            )?;
+            rows = Box::new(
+                rows.flat_map(move |row| match row.and_then(|row| joined.join(row)) {
+                    Ok(joined) => joined.into_iter().map(Ok).collect(),
+                    Err(e) => vec![Err(e)],
+                }),
+            );
+        }
+
+        if let Some(filter) = &filter {
+            rows = Box::new(rows.filter_map(move |row| {
+                let keep = row
+                    .as_deref()
+                    .map(|row| filter.matches_in(collation, &mut |i| Ok(row[i].clone())));
+                match keep {
+                    Ok(Ok(false)) => None,
+                    Ok(Err(e)) => Some(Err(e)),
+                    _ => Some(row),
+                }
+            }));
+        }
+
+        let aggregated = !group_by.is_empty()
+            || projections
+                .iter()
+                .any(|p| matches!(p, Projection::Aggregate(..)));
+
+        // Rows come out in the order they are read unless they are grouped,
+        // made distinct or sorted, reading can stop at the limit then
+        if let Some(limit) = self.limit {
+            if !aggregated && !self.distinct && order.is_empty() {
+                plan.push(format!("stop after {} rows", limit));
+                rows = Box::new(rows.take(limit));
+            }
+        }
+
+        let mut output = vec![];
+        if aggregated {
+            for (projection, column) in projections
+                .iter()
+                .zip(columns.iter().chain(std::iter::repeat(&String::new())))
+            {
+                if let Projection::Expr(e) = projection {
+                    if !e.columns().is_empty() && !group_by.contains(e) {
+                        let name = if column.is_empty() {
+                            e.to_string()
+                        } else {
+                            column.clone()
+                        };
+                        return Err(query_error(&format!(
+                            "'{}' must be in GROUP BY or inside an aggregate",
+                            name
+                        )));
+                    }
+                }
+            }
+
+            let width = sources.last().map_or(0, |s| s.columns().end);
+            let new_accumulators = || {
+                projections
+                    .iter()
+                    .map(|p| match p {
+                        Projection::Aggregate(aggregate, _) => Some(Accumulator::new(*aggregate)),
+                        Projection::Expr(_) => None,
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            // Groups in the order they are first seen, a query without
+            // GROUP BY having a single one even without rows
+            let mut groups: Vec<(Vec<Value>, Vec<Option<Accumulator>>)> = vec![];
+            let mut positions: HashMap<String, usize> = HashMap::new();
+            if group_by.is_empty() {
+                groups.push((vec![Value::Null; width], new_accumulators()));
+                positions.insert(row_key(&[]), 0);
+            }
+
+            for row in rows {
+                let row = row?;
+                let key = group_by
+                    .iter()
+                    .map(|e| eval_on(e, &row, collation))
+                    .collect::<Result<Vec<_>>>()?;
+                let position = *positions.entry(row_key(&key)).or_insert_with(|| {
+                    groups.push((row.clone(), new_accumulators()));
+                    groups.len() - 1
+                });
+
+                let accumulators = &mut groups[position].1;
+                for (projection, accumulator) in projections.iter().zip(accumulators.iter_mut()) {
+                    if let (Projection::Aggregate(_, argument), Some(accumulator)) =
+                        (projection, accumulator)
+                    {
+                        let value = argument
+                            .as_ref()
+                            .map(|e| eval_on(e, &row, collation))
+                            .transpose()?;
+                        accumulator.add(value, collation)?;
+                    }
+                }
+            }
+
+            for (first, accumulators) in groups {
+                let mut values = vec![];
+                for (projection, accumulator) in projections.iter().zip(accumulators) {
+                    values.push(match (projection, accumulator) {
+                        (_, Some(accumulator)) => accumulator.finish(),
+                        (Projection::Expr(e), None) => eval_on(e, &first, collation)?,
+                        (Projection::Aggregate(..), None) => Value::Null,
+                    });
+                }
+                output.push(values);
+            }
+        } else {
+            for row in rows {
+                let row = row?;
+                let values = projections
+                    .iter()
+                    .map(|p| match p {
+                        Projection::Expr(e) => eval_on(e, &row, collation),
+                        Projection::Aggregate(..) => Ok(Value::Null),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                output.push(values);
+            }
+        }
+
+        if self.distinct {
+            if projections.len() > visible {
+                return Err(query_error(
+                    "With DISTINCT, ORDER BY items must be in the select list",
+                ));
+            }
+            let mut seen = std::collections::HashSet::new();
+            output.retain(|row| seen.insert(row_key(row)));
+        }
+
+        if !order.is_empty() {
+            let mut error = None;
+            output.sort_by(|a, b| {
+                for &(i, descending) in &order {
+                    let ordering = match collation.key_order(&a[i], &b[i]) {
+                        Ok(ordering) => ordering,
+                        Err(e) => {
+                            error.get_or_insert(e);
+                            return std::cmp::Ordering::Equal;
+                        }
+                    };
+                    if ordering.is_ne() {
+                        return if descending {
+                            ordering.reverse()
+                        } else {
+                            ordering
+                        };
+                    }
+                }
+                std::cmp::Ordering::Equal
+            });
+            if let Some(e) = error {
+                return Err(e);
+            }
+        }
+
+        if let Some(limit) = self.limit {
+            output.truncate(limit);
+        }
+        for row in &mut output {
+            row.truncate(visible);
+        }
+
+        let warnings = sources
+            .iter()
+            .flat_map(|s| {
+                let name = &s.name;
+                s.table
+                    .warnings()
+                    .into_iter()
+                    .map(move |w| format!("{}: {}", name, w))
+            })
+            .collect();
+
+        Ok(QueryResult {
+            columns,
+            rows: output,
+            plan,
+            warnings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::PxCurrency;
+    use crate::testing::{field, write_table, TempDir};
+    use crate::types::{
+        PxHeader, PX_FIELD_TYPE_ALPHA, PX_FIELD_TYPE_CURRENCY, PX_FIELD_TYPE_LONG_INT,
+    };
+
+    fn run(dir: &TempDir, query: &str) -> QueryResult {
+        Query::parse(query).unwrap().run(dir.path()).unwrap()
+    }
+
+    // Customers 1 to 3 in two cities, orders for the first two
+    fn shop(dir: &Path) {
+        let alpha = |s: &str| Value::Alpha(s.to_string());
+        let money = |units: i64| Value::from(PxCurrency::from_units(units));
+        write_table(
+            &dir.join("CUST.DB"),
+            &PxHeader::new_table("CUST"),
+            vec![
+                field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+                field("City", PX_FIELD_TYPE_ALPHA, 10),
+            ],
+            1,
+            &[
+                vec![Value::Long(1), alpha("Oslo")],
+                vec![Value::Long(2), alpha("Bergen")],
+                vec![Value::Long(3), alpha("Oslo")],
+            ],
+        );
+        write_table(
+            &dir.join("ORD.DB"),
+            &PxHeader::new_table("ORD"),
+            vec![
+                field("No", PX_FIELD_TYPE_LONG_INT, 4),
+                field("CustId", PX_FIELD_TYPE_LONG_INT, 4),
+                field("Total", PX_FIELD_TYPE_CURRENCY, 8),
+            ],
+            1,
+            &[
+                vec![Value::Long(10), Value::Long(1), money(1_000)],
+                vec![Value::Long(11), Value::Long(2), money(2_500)],
+                vec![Value::Long(12), Value::Long(1), money(1)],
+            ],
+        );
+    }
+
+    #[test]
+    fn joins_and_aggregates() {
+        let dir = TempDir::new("sql-shop");
+        shop(dir.path());
+
+        let result = run(
+            &dir,
+            "SELECT c.City, COUNT(*), SUM(o.Total) FROM ord o JOIN cust c ON o.CustId = c.Id \
+             GROUP BY c.City ORDER BY c.City",
+        );
+        assert_eq!(
+            result.rows,
+            [
+                vec![
+                    Value::Alpha("Bergen".to_string()),
+                    Value::Number(1.0),
+                    PxCurrency::from_units(2_500).into()
+                ],
+                vec![
+                    Value::Alpha("Oslo".to_string()),
+                    Value::Number(2.0),
+                    PxCurrency::from_units(1_001).into()
+                ],
+            ]
+        );
+
+        let result = run(
+            &dir,
+            "SELECT c.Id, o.No FROM cust c LEFT JOIN ord o ON o.CustId = c.Id ORDER BY c.Id, o.No",
+        );
+        let pairs = result
+            .rows
+            .iter()
+            .map(|row| (row[0].clone(), row[1].clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pairs,
+            [
+                (Value::Long(1), Value::Long(10)),
+                (Value::Long(1), Value::Long(12)),
+                (Value::Long(2), Value::Long(11)),
+                (Value::Long(3), Value::Null),
+            ]
+        );
+    }
+
+    #[test]
+    fn limit_stops_reading() {
+        let dir = TempDir::new("sql-limit");
+        shop(dir.path());
+
+        let result = run(
+            &dir,
+            "SELECT No FROM ord o JOIN cust c ON o.CustId = c.Id LIMIT 2",
+        );
+        assert_eq!(result.rows, [vec![Value::Long(10)], vec![Value::Long(11)]]);
+        assert!(result.plan.contains(&"stop after 2 rows".to_string()));
+
+        let result = run(&dir, "SELECT COUNT(*) FROM ord LIMIT 1");
+        assert_eq!(result.rows, [vec![Value::Number(3.0)]]);
+        assert!(!result.plan.iter().any(|step| step.starts_with("stop")));
+    }
+
+    #[test]
+    fn key_ranges_in_international_order() {
+        let dir = TempDir::new("sql-collation");
+        let collation = Collation::International;
+        let mut header = PxHeader::new_table("NAMES");
+        header.sort_order = collation.sort_order();
+
+        // Lower and upper case, and Ä (0x8e in code page 437)
+        let mut names = (0..600)
+            .map(|n| format!("{}{:03}", ['a', 'B', 'b', 'A', '\u{8e}', 'c'][n % 6], n))
+            .collect::<Vec<_>>();
+        names.sort_by(|a, b| collation.compare(a, b));
+        let rows = names
+            .iter()
+            .map(|name| vec![Value::Alpha(name.clone())])
+            .collect::<Vec<_>>();
+        write_table(
+            &dir.join("NAMES.DB"),
+            &header,
+            vec![field("Name", PX_FIELD_TYPE_ALPHA, 10)],
+            1,
+            &rows,
+        );
+
+        let result = run(
+            &dir,
+            "SELECT Name FROM names WHERE Name >= 'a500' AND Name < 'b100'",
+        );
+        let expected = names
+            .iter()
+            .filter(|n| {
+                collation.compare(n, "a500").is_ge() && collation.compare(n, "b100").is_lt()
+            })
+            .map(|n| vec![Value::Alpha(n.clone())])
+            .collect::<Vec<_>>();
+        assert!(expected.len() > 50);
+        assert_eq!(result.rows, expected);
+        assert_eq!(result.plan, ["names: key range scan on Name"]);
+    }
+}
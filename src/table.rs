@@ -0,0 +1,486 @@
+use std::fs::File;
+use std::io::Read;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::collation::Collation;
+use crate::convert::{decode_field, px_blob_data};
+use crate::error::{Error, Result};
+use crate::index::CachedIndex;
+use crate::parse::{parse_block, parse_complete_header, PX_BLOCK_HEADER_SIZE};
+use crate::types::{
+    BlobPointer, PxBlocks, PxFieldInfo, PxHeader, Value, PX_FIELD_TYPE_DUNNO,
+    PX_FIELD_TYPE_MEMO_BLOB,
+};
+
+// Backing storage of a table: either the whole file read into memory or,
+// with the `mmap` feature, the file mapped into the address space.
+enum Storage {
+    Buffer(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Storage {
+    fn read(path: &Path) -> Result<Self> {
+        let mut data = vec![];
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .map_err(|e| Error::from(e).in_file(path))?;
+        Ok(Storage::Buffer(data))
+    }
+
+    #[cfg(feature = "mmap")]
+    fn map(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|e| Error::from(e).in_file(path))?;
+        // SAFETY: the mapping is read only. Another process truncating the
+        // file while it is mapped is the usual caveat of memory mapped IO.
+        let map = unsafe { memmap2::Mmap::map(&file) }.map_err(|e| Error::from(e).in_file(path))?;
+        Ok(Storage::Mapped(map))
+    }
+}
+
+impl Deref for Storage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Storage::Buffer(data) => data,
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(map) => map,
+        }
+    }
+}
+
+// Order in which blocks are visited: as they are laid out in the file, or
+// following the linked list, which for keyed tables is key order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockOrder {
+    Physical,
+    Linked,
+}
+
+pub struct Table {
+    header: PxHeader,
+    fields: Vec<PxFieldInfo>,
+    offsets: Vec<usize>,
+    data: Storage,
+    blobs: Option<Storage>,
+    path: Option<PathBuf>,
+    // Read by `primary_index` on first use
+    pub(crate) index: OnceLock<CachedIndex>,
+}
+
+impl Table {
+    // Reads the whole table file into memory.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let blobs = blob_path(path).map(|p| Storage::read(&p)).transpose()?;
+        let mut table =
+            Self::from_storage(Storage::read(path)?, blobs).map_err(|e| e.in_file(path))?;
+        table.path = Some(path.to_path_buf());
+        Ok(table)
+    }
+
+    // Maps the table file into memory, records are then borrowed straight
+    // from the mapping. Another program truncating the file while it is
+    // mapped makes reading it crash with SIGBUS.
+    #[cfg(feature = "mmap")]
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let blobs = blob_path(path).map(|p| Storage::map(&p)).transpose()?;
+        let mut table =
+            Self::from_storage(Storage::map(path)?, blobs).map_err(|e| e.in_file(path))?;
+        table.path = Some(path.to_path_buf());
+        Ok(table)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        Self::from_storage(Storage::Buffer(data), None)
+    }
+
+    fn from_storage(data: Storage, blobs: Option<Storage>) -> Result<Self> {
+        let (header, fields) = parse_complete_header(&data)?;
+
+        let offsets = fields
+            .iter()
+            .scan(0, |offset, field| {
+                let start = *offset;
+                *offset += field.data_size();
+                Some(start)
+            })
+            .collect();
+
+        Ok(Table {
+            header,
+            fields,
+            offsets,
+            data,
+            blobs,
+            path: None,
+            index: OnceLock::new(),
+        })
+    }
+
+    // File the table was opened from, None for tables read from bytes
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn header(&self) -> &PxHeader {
+        &self.header
+    }
+
+    // Problems that do not keep the table from being read, for the caller
+    // to report. Looks for the primary index if it was not used yet.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        let header = &self.header;
+        if header.num_records > 0 && header.first_block != 1 {
+            warnings.push(format!(
+                "numRecords > 0 ({}) && firstBlock != 1 ({})",
+                header.num_records, header.first_block
+            ));
+        }
+
+        match self.primary_index() {
+            Err(e) => warnings.push(format!("the primary index cannot be read: {}", e)),
+            Ok(None) if self.primary_index_out_of_date() => {
+                warnings.push("the primary index is out of date, ignoring it".to_string())
+            }
+            Ok(_) => {}
+        }
+
+        warnings
+    }
+
+    // The order of Alpha keys, from the sort order of the header. Unknown
+    // sort orders fall back to byte order.
+    pub fn collation(&self) -> Collation {
+        Collation::from_sort_order(self.header.sort_order).unwrap_or_default()
+    }
+
+    pub fn fields(&self) -> &[PxFieldInfo] {
+        &self.fields
+    }
+
+    // The bytes of the whole table file
+    pub fn raw(&self) -> &[u8] {
+        &self.data
+    }
+
+    // The bytes of the .MB file, if the table has one
+    pub fn raw_blobs(&self) -> Option<&[u8]> {
+        self.blobs.as_deref()
+    }
+
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f.name == name)
+    }
+
+    // Blocks are numbered from 1 and all have the same size, so their
+    // position in the file follows from the number.
+    pub(crate) fn block_offset(&self, number: u16) -> usize {
+        self.header.header_size as usize
+            + (number as usize).wrapping_sub(1) * self.header.block_size()
+    }
+
+    pub fn block(&self, number: u16) -> Result<PxBlocks<'_>> {
+        let start = self.block_offset(number);
+        let locate = |e: Error| self.locate(e).at_block(number).at_offset(start as u64);
+
+        let data = (number > 0)
+            .then(|| self.data.get(start..start + self.header.block_size()))
+            .flatten()
+            .ok_or_else(|| locate(Error::corrupt("Block is outside of the file")))?;
+
+        parse_block(data, number, &self.header).map_err(locate)
+    }
+
+    // Adds the table file to an error
+    fn locate(&self, error: Error) -> Error {
+        match &self.path {
+            Some(path) => error.in_file(path),
+            None => error,
+        }
+    }
+
+    // Iterates over the data blocks following the linked list.
+    pub fn blocks(&self) -> Blocks<'_> {
+        Blocks {
+            table: self,
+            next: self.header.first_block,
+            remaining: self.header.file_blocks,
+        }
+    }
+
+    // Iterates over all records in linked list order.
+    pub fn records(&self) -> Records<'_> {
+        self.records_from(self.header.first_block)
+    }
+
+    // Iterates over the records of the linked list from block `first` on.
+    pub fn records_from(&self, first: u16) -> Records<'_> {
+        Records {
+            table: self,
+            blocks: Blocks {
+                table: self,
+                next: first,
+                remaining: self.header.file_blocks,
+            },
+            current: None,
+            slot: 0,
+        }
+    }
+
+    // Iterates over the records of the linked list from the record at
+    // `slot` of `block` on.
+    pub fn records_at<'a>(&'a self, block: PxBlocks<'a>, slot: usize) -> Records<'a> {
+        let mut records = self.records_from(block.next_block);
+        records.current = Some(block);
+        records.slot = slot;
+        records
+    }
+
+    // Numbers of the blocks holding records. Free blocks are not on the
+    // linked list and are skipped in both orders.
+    pub fn block_numbers(&self, order: BlockOrder) -> Result<Vec<u16>> {
+        let mut numbers = self
+            .blocks()
+            .map(|block| block.map(|block| block.number))
+            .collect::<Result<Vec<_>>>()?;
+
+        if order == BlockOrder::Physical {
+            numbers.sort_unstable();
+        }
+
+        Ok(numbers)
+    }
+
+    // Decodes the records of every block on the rayon thread pool. Blocks
+    // are independent of each other, and `collect` keeps the given order.
+    #[cfg(feature = "rayon")]
+    pub fn par_records(
+        &self,
+        order: BlockOrder,
+    ) -> Result<impl ParallelIterator<Item = Result<Vec<Value>>> + '_> {
+        let numbers = self.block_numbers(order)?;
+        Ok(self.par_records_in(numbers))
+    }
+
+    // Decodes the records of the given blocks, in their order, with memos
+    // and blobs read from the .MB file.
+    #[cfg(feature = "rayon")]
+    pub fn par_records_in(
+        &self,
+        numbers: Vec<u16>,
+    ) -> impl ParallelIterator<Item = Result<Vec<Value>>> + '_ {
+        numbers.into_par_iter().flat_map_iter(move |number| {
+            let values = match self.block(number) {
+                Ok(block) => (0..block.num_recs_in_block)
+                    .filter_map(|slot| self.record(&block, slot))
+                    .map(|record| record.fetch_values())
+                    .collect(),
+                Err(e) => vec![Err(e)],
+            };
+
+            values.into_iter()
+        })
+    }
+
+    pub fn record<'a>(&'a self, block: &PxBlocks<'a>, slot: usize) -> Option<Record<'a>> {
+        let size = self.header.record_size as usize;
+        block
+            .records
+            .get(slot * size..(slot + 1) * size)
+            .map(|data| Record {
+                table: self,
+                block: block.number,
+                slot,
+                data,
+            })
+    }
+
+    // Retrieves the contents of a blob stored in the .MB file.
+    pub fn blob(&self, pointer: &BlobPointer) -> Result<Vec<u8>> {
+        let mb = self
+            .blobs
+            .as_ref()
+            .ok_or_else(|| self.locate(Error::invalid("Table has no .MB file")))?;
+
+        px_blob_data(mb, pointer).map_err(|e| match self.path.as_deref().and_then(blob_path) {
+            Some(path) => e.in_file(&path),
+            None => e,
+        })
+    }
+}
+
+// The .MB file sits next to the table with the same stem.
+fn blob_path(path: &Path) -> Option<PathBuf> {
+    companion_path(path, "MB")
+}
+
+// Files belonging to a table share its stem, in either case.
+pub fn companion_path(path: &Path, extension: &str) -> Option<PathBuf> {
+    [
+        extension.to_ascii_uppercase(),
+        extension.to_ascii_lowercase(),
+    ]
+    .iter()
+    .map(|ext| path.with_extension(ext))
+    .find(|p| p.exists())
+}
+
+pub struct Blocks<'a> {
+    table: &'a Table,
+    next: u16,
+    remaining: u16,
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = Result<PxBlocks<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == 0 {
+            return None;
+        }
+
+        // A damaged chain could loop forever
+        if self.remaining == 0 {
+            self.next = 0;
+            return Some(Err(self.table.locate(Error::corrupt(
+                "Block chain is longer than the number of blocks",
+            ))));
+        }
+        self.remaining -= 1;
+
+        let block = self.table.block(self.next);
+        self.next = match &block {
+            Ok(block) => block.next_block,
+            Err(_) => 0,
+        };
+
+        Some(block)
+    }
+}
+
+pub struct Records<'a> {
+    table: &'a Table,
+    blocks: Blocks<'a>,
+    current: Option<PxBlocks<'a>>,
+    slot: usize,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<Record<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(block) = &self.current {
+                if let Some(record) = self.table.record(block, self.slot) {
+                    self.slot += 1;
+                    return Some(Ok(record));
+                }
+            }
+
+            match self.blocks.next()? {
+                Ok(block) => {
+                    self.current = Some(block);
+                    self.slot = 0;
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+// A record borrowed from the table data, fields are decoded on access.
+#[derive(Clone, Copy)]
+pub struct Record<'a> {
+    table: &'a Table,
+    block: u16,
+    slot: usize,
+    data: &'a [u8],
+}
+
+impl<'a> Record<'a> {
+    pub fn table(&self) -> &'a Table {
+        self.table
+    }
+
+    // Number of the block holding the record
+    pub fn block(&self) -> u16 {
+        self.block
+    }
+
+    // Position of the record in its block, from 0
+    pub fn slot(&self) -> usize {
+        self.slot
+    }
+
+    // Adds the position of the record, and of field `index`, to an error
+    fn locate(&self, error: Error, index: usize) -> Error {
+        let offset = self.table.block_offset(self.block)
+            + PX_BLOCK_HEADER_SIZE
+            + self.slot * self.data.len()
+            + self.table.offsets.get(index).copied().unwrap_or_default();
+
+        self.table
+            .locate(error)
+            .at_block(self.block)
+            .at_record(self.slot)
+            .at_offset(offset as u64)
+    }
+
+    pub fn raw(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn field_data(&self, index: usize) -> Option<&'a [u8]> {
+        let field = self.table.fields.get(index)?;
+        let offset = self.table.offsets[index];
+        self.data.get(offset..offset + field.data_size())
+    }
+
+    pub fn get(&self, index: usize) -> Result<Value> {
+        let data = self
+            .field_data(index)
+            .ok_or_else(|| Error::invalid(format!("No field with index {}", index)))?;
+
+        decode_field(data, &self.table.fields[index]).map_err(|e| self.locate(e, index))
+    }
+
+    // Like `get`, but blobs stored in the .MB file are read as well.
+    pub fn fetch(&self, index: usize) -> Result<Value> {
+        match self.get(index)? {
+            Value::BlobRef(pointer) => {
+                let data = self
+                    .table
+                    .blob(&pointer)
+                    .map_err(|e| self.locate(e.in_field(&self.table.fields[index].name), index))?;
+                Ok(match self.table.fields[index].field_type {
+                    PX_FIELD_TYPE_MEMO_BLOB | PX_FIELD_TYPE_DUNNO => {
+                        Value::Memo(data.iter().map(|&c| c as char).collect())
+                    }
+                    _ => Value::Blob(data),
+                })
+            }
+            value => Ok(value),
+        }
+    }
+
+    pub fn values(&self) -> Result<Vec<Value>> {
+        (0..self.table.fields.len()).map(|i| self.get(i)).collect()
+    }
+
+    // All values with memos and blobs read, as `fetch` returns them
+    pub fn fetch_values(&self) -> Result<Vec<Value>> {
+        (0..self.table.fields.len())
+            .map(|i| self.fetch(i))
+            .collect()
+    }
+}
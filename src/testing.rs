@@ -0,0 +1,74 @@
+// Helpers shared by the tests of the library, of the CLI and by the
+// integration tests, which include this file as a module of their own. It
+// only names the crate as `pxrs` so that it builds in all three.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use pxrs::types::{PxFieldInfo, PxHeader, Value};
+use pxrs::{Table, TableWriter};
+
+// A directory of its own for a test, removed with everything in it when
+// dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("pxrs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn field(name: &str, field_type: u8, size: u8) -> PxFieldInfo {
+    PxFieldInfo {
+        name: name.to_string(),
+        field_type,
+        size,
+    }
+}
+
+// Writes a table of `rows`, keyed on its first `keys` fields unless that is
+// 0, and opens it
+pub fn write_table(
+    path: &Path,
+    template: &PxHeader,
+    fields: Vec<PxFieldInfo>,
+    keys: usize,
+    rows: &[Vec<Value>],
+) -> Table {
+    let mut writer = match keys {
+        0 => TableWriter::create(path, template, fields),
+        keys => TableWriter::create_keyed(path, template, fields, keys),
+    }
+    .unwrap();
+    for row in rows {
+        writer.write_values(row).unwrap();
+    }
+    writer.finish().unwrap();
+    Table::open(path).unwrap()
+}
+
+// Every value of every record, in file order
+pub fn read_rows(table: &Table) -> Vec<Vec<Value>> {
+    table
+        .records()
+        .map(|record| record.unwrap().fetch_values().unwrap())
+        .collect()
+}
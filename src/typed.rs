@@ -0,0 +1,689 @@
+use crate::error::Error;
+use std::marker::PhantomData;
+use std::path::Path;
+
+use crate::currency::PxCurrency;
+use crate::datetime::{PxDate, PxTime, PxTimestamp};
+use crate::table::{Record, Table};
+use crate::types::*;
+use crate::writer::TableWriter;
+
+// Used by the derived code
+pub use crate::error::Result;
+pub use crate::types::Value;
+
+#[cfg(feature = "derive")]
+pub use pxrs_derive::PxRecord;
+
+// A Rust type that a Paradox field can be decoded into and encoded from.
+// FIELD_TYPE and SIZE describe the column created for it in a new table.
+pub trait PxValue: Sized {
+    const FIELD_TYPE: u8;
+    const SIZE: u8;
+
+    // Field types values are read from
+    fn accepts(field_type: u8) -> bool;
+
+    // Field types `to_value` results can be stored in
+    fn writes(field_type: u8) -> bool {
+        Self::accepts(field_type)
+    }
+
+    fn from_value(value: Value) -> Result<Self>;
+    fn to_value(&self) -> Result<Value>;
+}
+
+fn unexpected(value: &Value) -> Error {
+    match value {
+        Value::Null => Error::value("Value is null"),
+        value => Error::value(format!("Unexpected value {:?}", value)),
+    }
+}
+
+impl PxValue for i16 {
+    const FIELD_TYPE: u8 = PX_FIELD_TYPE_SHORT_INT;
+    const SIZE: u8 = 2;
+
+    fn accepts(field_type: u8) -> bool {
+        field_type == PX_FIELD_TYPE_SHORT_INT
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Short(v) => Ok(v),
+            value => Err(unexpected(&value)),
+        }
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        Ok(Value::Short(*self))
+    }
+}
+
+impl PxValue for i32 {
+    const FIELD_TYPE: u8 = PX_FIELD_TYPE_LONG_INT;
+    const SIZE: u8 = 4;
+
+    fn accepts(field_type: u8) -> bool {
+        matches!(
+            field_type,
+            PX_FIELD_TYPE_SHORT_INT | PX_FIELD_TYPE_LONG_INT | PX_FIELD_TYPE_INCREMENTAL
+        )
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Short(v) => Ok(v as i32),
+            Value::Long(v) | Value::AutoInc(v) => Ok(v),
+            value => Err(unexpected(&value)),
+        }
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        Ok(Value::Long(*self))
+    }
+}
+
+impl PxValue for f64 {
+    const FIELD_TYPE: u8 = PX_FIELD_TYPE_NUMBER;
+    const SIZE: u8 = 8;
+
+    fn accepts(field_type: u8) -> bool {
+        matches!(
+            field_type,
+            PX_FIELD_TYPE_NUMBER
+                | PX_FIELD_TYPE_CURRENCY
+                | PX_FIELD_TYPE_SHORT_INT
+                | PX_FIELD_TYPE_LONG_INT
+                | PX_FIELD_TYPE_INCREMENTAL
+                | PX_FIELD_TYPE_BCD
+        )
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Number(v) => Ok(v),
+            Value::Currency(v) => Ok(v.to_f64()),
+            Value::Short(v) => Ok(v as f64),
+            Value::Long(v) | Value::AutoInc(v) => Ok(v as f64),
+            Value::Bcd(s) => s
+                .parse()
+                .map_err(|_| Error::value(format!("Invalid BCD value '{}'", s))),
+            value => Err(unexpected(&value)),
+        }
+    }
+
+    // Doubles are not converted to integers
+    fn writes(field_type: u8) -> bool {
+        matches!(
+            field_type,
+            PX_FIELD_TYPE_NUMBER | PX_FIELD_TYPE_CURRENCY | PX_FIELD_TYPE_BCD
+        )
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        Ok(Value::Number(*self))
+    }
+}
+
+impl PxValue for bool {
+    const FIELD_TYPE: u8 = PX_FIELD_TYPE_LOGICAL;
+    const SIZE: u8 = 1;
+
+    fn accepts(field_type: u8) -> bool {
+        field_type == PX_FIELD_TYPE_LOGICAL
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Logical(v) => Ok(v),
+            value => Err(unexpected(&value)),
+        }
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        Ok(Value::Logical(*self))
+    }
+}
+
+impl PxValue for String {
+    const FIELD_TYPE: u8 = PX_FIELD_TYPE_ALPHA;
+    const SIZE: u8 = 255;
+
+    fn accepts(field_type: u8) -> bool {
+        matches!(
+            field_type,
+            PX_FIELD_TYPE_ALPHA | PX_FIELD_TYPE_MEMO_BLOB | PX_FIELD_TYPE_DUNNO
+        )
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Alpha(s) | Value::Memo(s) => Ok(s),
+            value => Err(unexpected(&value)),
+        }
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        Ok(Value::Alpha(self.clone()))
+    }
+}
+
+impl PxValue for Vec<u8> {
+    const FIELD_TYPE: u8 = PX_FIELD_TYPE_BYTES;
+    const SIZE: u8 = 255;
+
+    fn accepts(field_type: u8) -> bool {
+        matches!(
+            field_type,
+            PX_FIELD_TYPE_BYTES
+                | PX_FIELD_TYPE_BIN_BLOB
+                | PX_FIELD_TYPE_OLE
+                | PX_FIELD_TYPE_GRAPHIC
+        )
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Bytes(v) | Value::Blob(v) => Ok(v),
+            value => Err(unexpected(&value)),
+        }
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        Ok(Value::Bytes(self.clone()))
+    }
+}
+
+impl<T: PxValue> PxValue for Option<T> {
+    const FIELD_TYPE: u8 = T::FIELD_TYPE;
+    const SIZE: u8 = T::SIZE;
+
+    fn accepts(field_type: u8) -> bool {
+        T::accepts(field_type)
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+
+    fn writes(field_type: u8) -> bool {
+        T::writes(field_type)
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        self.as_ref().map_or(Ok(Value::Null), T::to_value)
+    }
+}
+
+impl PxValue for PxDate {
+    const FIELD_TYPE: u8 = PX_FIELD_TYPE_DATE;
+    const SIZE: u8 = 4;
+
+    fn accepts(field_type: u8) -> bool {
+        field_type == PX_FIELD_TYPE_DATE
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        PxDate::try_from(&value).map_err(|_| unexpected(&value))
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        Ok((*self).into())
+    }
+}
+
+impl PxValue for PxTime {
+    const FIELD_TYPE: u8 = PX_FIELD_TYPE_TIME;
+    const SIZE: u8 = 4;
+
+    fn accepts(field_type: u8) -> bool {
+        field_type == PX_FIELD_TYPE_TIME
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        PxTime::try_from(&value).map_err(|_| unexpected(&value))
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        Ok((*self).into())
+    }
+}
+
+impl PxValue for PxTimestamp {
+    const FIELD_TYPE: u8 = PX_FIELD_TYPE_TIMESTAMP;
+    const SIZE: u8 = 8;
+
+    fn accepts(field_type: u8) -> bool {
+        field_type == PX_FIELD_TYPE_TIMESTAMP
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        PxTimestamp::try_from(&value).map_err(|_| unexpected(&value))
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        Ok((*self).into())
+    }
+}
+
+impl PxValue for PxCurrency {
+    const FIELD_TYPE: u8 = PX_FIELD_TYPE_CURRENCY;
+    const SIZE: u8 = 8;
+
+    fn accepts(field_type: u8) -> bool {
+        matches!(
+            field_type,
+            PX_FIELD_TYPE_CURRENCY
+                | PX_FIELD_TYPE_SHORT_INT
+                | PX_FIELD_TYPE_LONG_INT
+                | PX_FIELD_TYPE_INCREMENTAL
+                | PX_FIELD_TYPE_BCD
+        )
+    }
+
+    fn writes(field_type: u8) -> bool {
+        matches!(
+            field_type,
+            PX_FIELD_TYPE_CURRENCY | PX_FIELD_TYPE_NUMBER | PX_FIELD_TYPE_BCD
+        )
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        PxCurrency::try_from(&value).map_err(|_| unexpected(&value))
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        Ok((*self).into())
+    }
+}
+
+// The chrono types are read and written as the Paradox types they convert
+// from
+#[cfg(feature = "chrono")]
+mod chrono_values {
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+    use super::PxValue;
+    use crate::datetime::{PxDate, PxTime, PxTimestamp};
+    use crate::error::Result;
+    use crate::types::Value;
+
+    impl PxValue for NaiveDate {
+        const FIELD_TYPE: u8 = PxDate::FIELD_TYPE;
+        const SIZE: u8 = PxDate::SIZE;
+
+        fn accepts(field_type: u8) -> bool {
+            PxDate::accepts(field_type)
+        }
+
+        fn from_value(value: Value) -> Result<Self> {
+            PxDate::from_value(value)?.try_into()
+        }
+
+        fn to_value(&self) -> Result<Value> {
+            PxDate::try_from(*self)?.to_value()
+        }
+    }
+
+    impl PxValue for NaiveTime {
+        const FIELD_TYPE: u8 = PxTime::FIELD_TYPE;
+        const SIZE: u8 = PxTime::SIZE;
+
+        fn accepts(field_type: u8) -> bool {
+            PxTime::accepts(field_type)
+        }
+
+        fn from_value(value: Value) -> Result<Self> {
+            PxTime::from_value(value)?.try_into()
+        }
+
+        fn to_value(&self) -> Result<Value> {
+            PxTime::from(*self).to_value()
+        }
+    }
+
+    impl PxValue for NaiveDateTime {
+        const FIELD_TYPE: u8 = PxTimestamp::FIELD_TYPE;
+        const SIZE: u8 = PxTimestamp::SIZE;
+
+        fn accepts(field_type: u8) -> bool {
+            PxTimestamp::accepts(field_type)
+        }
+
+        fn from_value(value: Value) -> Result<Self> {
+            PxTimestamp::from_value(value)?.try_into()
+        }
+
+        fn to_value(&self) -> Result<Value> {
+            PxTimestamp::try_from(*self)?.to_value()
+        }
+    }
+}
+
+// Decimals are stored in Currency fields, and read from BCD fields too
+#[cfg(feature = "rust_decimal")]
+mod decimal_values {
+    use rust_decimal::Decimal;
+
+    use super::{unexpected, PxValue};
+    use crate::currency::PxCurrency;
+    use crate::error::{Error, Result};
+    use crate::types::*;
+
+    impl PxValue for Decimal {
+        const FIELD_TYPE: u8 = PX_FIELD_TYPE_CURRENCY;
+        const SIZE: u8 = 8;
+
+        fn accepts(field_type: u8) -> bool {
+            PxCurrency::accepts(field_type)
+        }
+
+        fn writes(field_type: u8) -> bool {
+            PxCurrency::writes(field_type)
+        }
+
+        fn from_value(value: Value) -> Result<Self> {
+            match &value {
+                Value::Bcd(s) => s
+                    .parse()
+                    .map_err(|_| Error::value(format!("Invalid BCD value '{}'", s))),
+                value => PxCurrency::try_from(value)
+                    .map(Decimal::from)
+                    .map_err(|_| unexpected(value)),
+            }
+        }
+
+        // Rounded to a Currency value, decimals beyond its range fail
+        fn to_value(&self) -> Result<Value> {
+            PxCurrency::try_from(*self).map(Value::from)
+        }
+    }
+}
+
+// A struct field mapped onto a table column
+pub struct Column {
+    pub name: &'static str,
+    pub field_type: u8,
+    pub size: u8,
+    pub accepts: fn(u8) -> bool,
+    pub writes: fn(u8) -> bool,
+}
+
+// A struct holding one table row, usually implemented with
+// `#[derive(PxRecord)]`. Values are passed in `COLUMNS` order.
+pub trait PxRecord: Sized {
+    const COLUMNS: &'static [Column];
+
+    fn decode(values: Vec<Value>) -> Result<Self>;
+    fn encode(&self) -> Result<Vec<Value>>;
+
+    // Fields of a new table holding these rows
+    fn fields() -> Vec<PxFieldInfo> {
+        Self::COLUMNS
+            .iter()
+            .map(|column| PxFieldInfo {
+                name: column.name.to_string(),
+                field_type: column.field_type,
+                size: column.size,
+            })
+            .collect()
+    }
+}
+
+// Used by the derived `decode` to take the next value of a column.
+pub fn decode_column<T: PxValue>(
+    values: &mut impl Iterator<Item = Value>,
+    name: &str,
+) -> Result<T> {
+    let value = values.next().unwrap_or(Value::Null);
+    T::from_value(value).map_err(|e| e.in_field(name))
+}
+
+// Used by the derived `encode` for the value of a column.
+pub fn encode_column<T: PxValue>(value: &T, name: &str) -> Result<Value> {
+    value.to_value().map_err(|e| e.in_field(name))
+}
+
+// Maps every column of `T` to the index of the table field with the same
+// name, checking that the field type can be converted.
+pub fn column_mapping<T: PxRecord>(fields: &[PxFieldInfo]) -> Result<Vec<usize>> {
+    mapping::<T>(fields, |column| column.accepts)
+}
+
+// Like `column_mapping`, checking that the values of `T` can be stored in
+// the fields instead.
+pub fn write_mapping<T: PxRecord>(fields: &[PxFieldInfo]) -> Result<Vec<usize>> {
+    mapping::<T>(fields, |column| column.writes)
+}
+
+fn mapping<T: PxRecord>(
+    fields: &[PxFieldInfo],
+    check: fn(&Column) -> fn(u8) -> bool,
+) -> Result<Vec<usize>> {
+    T::COLUMNS
+        .iter()
+        .map(|column| {
+            let index = fields
+                .iter()
+                .position(|f| f.name == column.name)
+                .ok_or_else(|| {
+                    Error::invalid(format!("Column '{}' not found in the table", column.name))
+                })?;
+
+            let field = &fields[index];
+            if !check(column)(field.field_type) {
+                return Err(Error::invalid(format!(
+                    "Column '{}' is of type {}, which does not match the struct",
+                    column.name,
+                    field.type_name()
+                )));
+            }
+
+            Ok(index)
+        })
+        .collect()
+}
+
+impl Table {
+    // Decodes every record into `T` after validating the table's fields
+    // against the struct.
+    pub fn rows<T: PxRecord>(&self) -> Result<impl Iterator<Item = Result<T>> + '_> {
+        let mapping = column_mapping::<T>(self.fields())?;
+
+        Ok(self
+            .records()
+            .map(move |record| decode_record(&record?, &mapping)))
+    }
+}
+
+fn decode_record<T: PxRecord>(record: &Record, mapping: &[usize]) -> Result<T> {
+    let values = mapping
+        .iter()
+        .map(|&i| record.fetch(i))
+        .collect::<Result<Vec<_>>>()?;
+
+    T::decode(values).map_err(|e| e.at_block(record.block()).at_record(record.slot()))
+}
+
+impl Record<'_> {
+    // Decodes a single record into `T`, validating the fields each time.
+    // Prefer `Table::rows` for whole tables.
+    pub fn decode<T: PxRecord>(&self) -> Result<T> {
+        decode_record(self, &column_mapping::<T>(self.table().fields())?)
+    }
+}
+
+// A writer that only accepts rows of type `T`.
+pub struct TypedWriter<T: PxRecord> {
+    writer: TableWriter,
+    mapping: Vec<usize>,
+    row: PhantomData<T>,
+}
+
+impl<T: PxRecord> TypedWriter<T> {
+    // Creates a table with the columns of `T`.
+    pub fn create<P: AsRef<Path>>(path: P, template: &PxHeader) -> Result<Self> {
+        Self::new(TableWriter::create(path, template, T::fields())?)
+    }
+
+    // Adds rows to the end of an existing table.
+    pub fn append<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(TableWriter::append(path)?)
+    }
+
+    // Validates that the writer's fields can store the values of `T`.
+    pub fn new(writer: TableWriter) -> Result<Self> {
+        let mapping = write_mapping::<T>(writer.fields())?;
+
+        Ok(TypedWriter {
+            writer,
+            mapping,
+            row: PhantomData,
+        })
+    }
+
+    pub fn write(&mut self, row: &T) -> Result<()> {
+        let mut values = vec![Value::Null; self.writer.fields().len()];
+        for (value, &i) in row.encode()?.into_iter().zip(&self.mapping) {
+            values[i] = value;
+        }
+
+        self.writer.write_values(&values)
+    }
+
+    pub fn finish(self) -> Result<PxHeader> {
+        self.writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{field, TempDir};
+
+    struct Row {
+        id: i32,
+        amount: f64,
+    }
+
+    impl PxRecord for Row {
+        const COLUMNS: &'static [Column] = &[
+            Column {
+                name: "Id",
+                field_type: PX_FIELD_TYPE_LONG_INT,
+                size: 4,
+                accepts: i32::accepts,
+                writes: i32::writes,
+            },
+            Column {
+                name: "Amount",
+                field_type: PX_FIELD_TYPE_NUMBER,
+                size: 8,
+                accepts: f64::accepts,
+                writes: f64::writes,
+            },
+        ];
+
+        fn decode(values: Vec<Value>) -> Result<Self> {
+            let mut values = values.into_iter();
+            Ok(Row {
+                id: decode_column(&mut values, "Id")?,
+                amount: decode_column(&mut values, "Amount")?,
+            })
+        }
+
+        fn encode(&self) -> Result<Vec<Value>> {
+            Ok(vec![
+                encode_column(&self.id, "Id")?,
+                encode_column(&self.amount, "Amount")?,
+            ])
+        }
+    }
+
+    #[test]
+    fn writer_checks_the_fields_can_store_the_values() {
+        // Doubles are read from Long fields but not written to them
+        let fields = vec![
+            field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+            field("Amount", PX_FIELD_TYPE_LONG_INT, 4),
+        ];
+        assert!(column_mapping::<Row>(&fields).is_ok());
+
+        let dir = TempDir::new("typed-writes");
+        let writer =
+            TableWriter::create(dir.join("T.DB"), &PxHeader::new_table("T"), fields).unwrap();
+        let error = TypedWriter::<Row>::new(writer).err().unwrap();
+        assert!(matches!(error, Error::Invalid { .. }));
+    }
+
+    #[test]
+    fn typed_rows_round_trip() {
+        let dir = TempDir::new("typed-rows");
+        let path = dir.join("T.DB");
+        let mut writer = TypedWriter::<Row>::create(&path, &PxHeader::new_table("T")).unwrap();
+        for id in 0..100 {
+            writer
+                .write(&Row {
+                    id,
+                    amount: id as f64 / 4.0,
+                })
+                .unwrap();
+        }
+        writer.finish().unwrap();
+
+        let table = Table::open(&path).unwrap();
+        let rows = table
+            .rows::<Row>()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(rows.len(), 100);
+        assert!(rows.iter().all(|r| r.amount == r.id as f64 / 4.0));
+    }
+
+    #[test]
+    fn decode_errors_name_the_column() {
+        let error = Row::decode(vec![Value::Long(1), Value::Alpha("x".to_string())])
+            .err()
+            .unwrap();
+        assert_eq!(error.location().field.as_deref(), Some("Amount"));
+
+        let error = Row::decode(vec![Value::Null, Value::Number(1.0)])
+            .err()
+            .unwrap();
+        assert_eq!(error.location().field.as_deref(), Some("Id"));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derived_records_name_the_crate() {
+        #[derive(PxRecord)]
+        struct Derived {
+            #[px(name = "Id")]
+            id: i32,
+            name: Option<String>,
+        }
+
+        let columns = Derived::COLUMNS;
+        assert_eq!((columns[0].name, columns[1].name), ("Id", "name"));
+        let values = Derived { id: 3, name: None }.encode().unwrap();
+        assert_eq!(values, [Value::Long(3), Value::Null]);
+        assert_eq!(Derived::decode(values).unwrap().id, 3);
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn decimals_beyond_currency_fail() {
+        use rust_decimal::Decimal;
+
+        assert!(Decimal::MAX.to_value().is_err());
+        assert_eq!(
+            Decimal::new(12345, 2).to_value().unwrap(),
+            Value::from(PxCurrency::from_units(1_234_500))
+        );
+    }
+}
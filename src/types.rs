@@ -1,19 +1,35 @@
-use byte::ctx::{Str, NULL};
 use byte::*;
 use std::fmt::Display;
 use std::io::Read;
-use std::mem::MaybeUninit;
-use std::ptr::addr_of_mut;
 
 use self::ctx::Endian;
+use crate::currency::PxCurrency;
 
-#[derive(Debug, Clone, Default, Copy, PartialEq, Eq, PartialOrd, Ord)]
+// Size of the header part common to all files, and of the header including
+// the Paradox 4+ extension found in .DB and .Xnn files.
+pub const PX_HEADER_SIZE: usize = 0x58;
+pub const PX_HEADER_SIZE_V4: usize = 0x78;
+
+#[derive(Debug, Clone, Default, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(transparent)]
 pub struct Version(pub u8);
 
 impl Version {
     pub fn is_supported(&self) -> bool {
         matches!(self.0, 0x03..=0x0c)
     }
+
+    // The Paradox release that writes this version
+    pub fn name(&self) -> &'static str {
+        match self.0 {
+            0x03 => "3.0",
+            0x04 => "3.5",
+            0x05..=0x09 => "4.x",
+            0x0a | 0x0b => "5.x",
+            0x0c => "7.x",
+            _ => "Unknown",
+        }
+    }
 }
 
 impl<'a> TryRead<'a, Endian> for Version {
@@ -23,22 +39,20 @@ impl<'a> TryRead<'a, Endian> for Version {
     }
 }
 
+impl TryWrite<Endian> for Version {
+    fn try_write(self, bytes: &mut [u8], ctx: Endian) -> Result<usize> {
+        self.0.try_write(bytes, ctx)
+    }
+}
+
 impl Display for Version {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let version = match self.0 {
-            0x03 => "3.0",
-            0x04 => "3.5",
-            0x05..=0x09 => "4.x",
-            0x0a | 0x0b => "5.x",
-            0x0c => "7.x",
-            _ => "Unknown",
-        };
-
-        write!(f, "{} | {:02x}", version, self.0)
+        write!(f, "{} | {:02x}", self.name(), self.0)
     }
 }
 
-#[derive(Debug, Clone)]
+// Serializes with the raw values of every field, offsets as in the file
+#[derive(Debug, Clone, Default, serde::Serialize)]
 pub struct PxHeader {
     pub record_size: i16,             // 0x00: signed short
     pub header_size: i16,             // 0x02: signed short
@@ -98,31 +112,34 @@ pub struct PxHeader {
     pub table_name: String,           // ----: char[79]
 }
 
-impl Default for PxHeader {
-    fn default() -> Self {
-        unsafe {
-            let mut header = MaybeUninit::<PxHeader>::uninit();
-            let ptr: *mut PxHeader = header.as_mut_ptr();
-
-            addr_of_mut!((*ptr).table_name).write(String::new());
-
-            header.assume_init()
-        }
+fn truncated_header(error: std::io::Error) -> crate::error::Error {
+    match error.kind() {
+        std::io::ErrorKind::UnexpectedEof => crate::error::Error::corrupt("Truncated header"),
+        _ => error.into(),
     }
 }
 
 impl PxHeader {
-    pub fn from_reader(reader: &mut dyn Read) -> std::io::Result<Self> {
+    // Reads the fixed part of the header. The Paradox 4+ extension is only
+    // read for the files that carry it, the table name is filled in by
+    // `parse_complete_header`.
+    pub fn from_reader(reader: &mut dyn Read) -> crate::error::Result<Self> {
         let mut offset = 0;
 
-        let mut buffer = [0u8; size_of::<Self>()];
-        reader.read_exact(&mut buffer)?;
+        let mut buffer = [0u8; PX_HEADER_SIZE_V4];
+        reader
+            .read_exact(&mut buffer[..PX_HEADER_SIZE])
+            .map_err(truncated_header)?;
 
         let mut header = PxHeader::default();
 
         macro_rules! read_field {
             ($field:ident) => {
-                header.$field = buffer.read_with(&mut offset, BE).unwrap();
+                let start = offset;
+                header.$field = buffer.read_with(&mut offset, LE).map_err(|_| {
+                    crate::error::Error::corrupt(concat!("Invalid ", stringify!($field)))
+                        .at_offset(start as u64)
+                })?;
             };
         }
 
@@ -169,6 +186,15 @@ impl PxHeader {
         read_field!(dummy_c);
         read_field!(ref_integrity);
         read_field!(dummy_d);
+
+        if !header.has_v4_header() {
+            return Ok(header);
+        }
+
+        reader
+            .read_exact(&mut buffer[PX_HEADER_SIZE..])
+            .map_err(truncated_header)?;
+
         read_field!(file_version_id2);
         read_field!(file_version_id3);
         read_field!(encryption2);
@@ -182,48 +208,181 @@ impl PxHeader {
         read_field!(dummy_f);
         read_field!(dummy_10);
 
-        header.table_name = buffer
-            .read_with::<&str>(&mut offset, Str::Delimiter(NULL))
-            .unwrap_or_default()
-            .to_string();
-
         Ok(header)
     }
+
+    // Writes the fixed part of the header exactly as `from_reader` reads
+    // it, unknown fields included, so reading the bytes back gives the same
+    // header. The Paradox 4+ extension is written for the files carrying it.
+    pub fn write_to(&self, writer: &mut dyn std::io::Write) -> crate::error::Result<()> {
+        let mut offset = 0;
+        let mut buffer = [0u8; PX_HEADER_SIZE_V4];
+
+        macro_rules! write_field {
+            ($field:ident) => {
+                let start = offset;
+                buffer
+                    .write_with(&mut offset, self.$field, LE)
+                    .map_err(|_| {
+                        crate::error::Error::invalid(concat!("Invalid ", stringify!($field)))
+                            .at_offset(start as u64)
+                    })?;
+            };
+        }
+
+        write_field!(record_size);
+        write_field!(header_size);
+        write_field!(file_type);
+        write_field!(max_table_size);
+        write_field!(num_records);
+        write_field!(used_blocks);
+        write_field!(file_blocks);
+        write_field!(first_block);
+        write_field!(last_block);
+        write_field!(dummy_1);
+        write_field!(modified_flags1);
+        write_field!(index_field_number);
+        write_field!(primary_index_workspace);
+        write_field!(dummy_2);
+        write_field!(index_root_block);
+        write_field!(index_levels);
+        write_field!(num_fields);
+        write_field!(primary_key_fields);
+        write_field!(encryption1);
+        write_field!(sort_order);
+        write_field!(modified_flags2);
+        write_field!(dummy_5);
+        write_field!(change_count1);
+        write_field!(change_count2);
+        write_field!(dummy_6);
+        write_field!(table_name_ptr);
+        write_field!(field_info);
+        write_field!(write_protected);
+        write_field!(file_version_id);
+        write_field!(max_blocks);
+        write_field!(dummy_7);
+        write_field!(aux_passwords);
+        write_field!(dummy_8);
+        write_field!(crypt_info_start);
+        write_field!(crypt_info_end);
+        write_field!(dummy_9);
+        write_field!(auto_inc);
+        write_field!(dummy_a);
+        write_field!(index_update_required);
+        write_field!(dummy_b);
+        write_field!(dummy_c);
+        write_field!(ref_integrity);
+        write_field!(dummy_d);
+
+        if self.has_v4_header() {
+            write_field!(file_version_id2);
+            write_field!(file_version_id3);
+            write_field!(encryption2);
+            write_field!(file_update_time);
+            write_field!(hi_field_id);
+            write_field!(hi_field_id_info);
+            write_field!(sometimes_num_fields);
+            write_field!(dos_global_code_page);
+            write_field!(dummy_e);
+            write_field!(change_count4);
+            write_field!(dummy_f);
+            write_field!(dummy_10);
+        }
+
+        writer.write_all(&buffer[..offset])?;
+        Ok(())
+    }
+
+    // Size of the fixed part written by `write_to`
+    pub fn fixed_size(&self) -> usize {
+        if self.has_v4_header() {
+            PX_HEADER_SIZE_V4
+        } else {
+            PX_HEADER_SIZE
+        }
+    }
+
+    // Sets a field of the fixed header by its name in `PX_HEADER_FIELDS`,
+    // checking that the value fits. Nothing else is adjusted, e.g. setting
+    // `num_records` leaves the blocks as they are.
+    pub fn set_field(&mut self, name: &str, value: i64) -> crate::error::Result<()> {
+        let field = PX_HEADER_FIELDS
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| {
+                crate::error::Error::invalid(format!("Unknown header field '{}'", name))
+            })?;
+        if field.offset >= self.fixed_size() {
+            return Err(crate::error::Error::invalid(format!(
+                "The header of this file has no field '{}'",
+                name
+            )));
+        }
+
+        let bits = 8 * field.size as u32;
+        let range = match field.signed {
+            true => -(1i64 << (bits - 1))..=(1i64 << (bits - 1)) - 1,
+            false => 0..=(1i64 << bits) - 1,
+        };
+        if !range.contains(&value) {
+            return Err(crate::error::Error::value(format!(
+                "{} does not fit into {}, expected {} to {}",
+                value,
+                name,
+                range.start(),
+                range.end()
+            )));
+        }
+
+        // Patch the serialized header and read it back
+        let mut data = vec![];
+        self.write_to(&mut data)?;
+        data[field.offset..field.offset + field.size]
+            .copy_from_slice(&value.to_le_bytes()[..field.size]);
+
+        let table_name = std::mem::take(&mut self.table_name);
+        *self = PxHeader::from_reader(&mut &data[..])?;
+        self.table_name = table_name;
+        Ok(())
+    }
+
+    // Only .DB and .Xnn files of version 4.0 and above have the header
+    // extension at 0x58..0x78.
+    pub fn has_v4_header(&self) -> bool {
+        self.file_version_id.0 >= 0x05
+            && self.file_type != PX_FILETYPE_PX
+            && self.file_type != PX_FILETYPE_YNN
+            && self.file_type != PX_FILETYPE_YGN
+    }
+
+    // .PX and .Ynn files store the key fields followed by three shorts and
+    // carry no field names.
+    pub fn is_index(&self) -> bool {
+        matches!(
+            self.file_type,
+            PX_FILETYPE_PX | PX_FILETYPE_YNN | PX_FILETYPE_YGN
+        )
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.max_table_size as usize * 0x400
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        if self.has_v4_header() {
+            self.encryption2 != 0
+        } else {
+            self.encryption1 != 0
+        }
+    }
 }
 
 impl Display for PxHeader {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "File-Version: {}", self.file_version_id)?;
-        writeln!(
-            f,
-            "Filetype: {}",
-            match self.file_type {
-                0x00 => "indexed .DB",
-                0x01 => "primary index .PX",
-                0x02 => "non indexed .DB",
-                0x03 => "non-incrementing secondary index .Xnn",
-                0x04 => "secondary index .Ynn (inc/non-inc)",
-                0x05 => "incrementing secondary index .Xnn",
-                0x06 => "non-incrementing secondary index .XGn",
-                0x07 => "secondary index .YGn (inc/non-inc)",
-                0x08 => "incrementing secondary index .XGn",
-                _ => "Unknown",
-            }
-        )?;
+        writeln!(f, "Filetype: {}", file_type_name(self.file_type))?;
         writeln!(f, "Tablename: {}", &self.table_name)?;
-        writeln!(
-            f,
-            "Sort-Order: {}",
-            match self.sort_order {
-                0x00 => "ASCII",
-                0xb7 => "International",
-                0x82 | 0xe6 => "Norwegian/Danish",
-                0x0b => "Swedish/Finnish",
-                0x5d => "Spanish",
-                0x62 => "PDX ANSI intl",
-                _ => "Unknown",
-            }
-        )?;
+        writeln!(f, "Sort-Order: {}", sort_order_name(self.sort_order))?;
         writeln!(
             f,
             "Write-Protection: {}",
@@ -234,20 +393,8 @@ impl Display for PxHeader {
             }
         )?;
 
-        if self.file_version_id.0 >= 0x05
-            && self.file_type != 0x01
-            && self.file_type != 0x04
-            && self.file_type != 0x07
-        {
-            writeln!(
-                f,
-                "Codepage: {}",
-                match self.dos_global_code_page {
-                    0x01b5 => "United States",
-                    0x04e4 => "Spain",
-                    _ => "Unknown",
-                }
-            )?;
+        if self.has_v4_header() {
+            writeln!(f, "Codepage: {}", code_page_name(self.dos_global_code_page))?;
         }
 
         writeln!(f, "Number of Blocks: {}", self.file_blocks)?;
@@ -267,28 +414,216 @@ impl Display for PxHeader {
     }
 }
 
+// Position of a header field in the file
+#[derive(Debug, Clone, Copy)]
+pub struct HeaderField {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+    pub signed: bool,
+}
+
+const fn field(name: &'static str, offset: usize, size: usize, signed: bool) -> HeaderField {
+    HeaderField {
+        name,
+        offset,
+        size,
+        signed,
+    }
+}
+
+// Layout of the fixed header, in file order. The fields from 0x58 on are
+// only present when `has_v4_header` holds.
+pub const PX_HEADER_FIELDS: &[HeaderField] = &[
+    field("record_size", 0x00, 2, true),
+    field("header_size", 0x02, 2, true),
+    field("file_type", 0x04, 1, false),
+    field("max_table_size", 0x05, 1, false),
+    field("num_records", 0x06, 4, false),
+    field("used_blocks", 0x0a, 2, false),
+    field("file_blocks", 0x0c, 2, false),
+    field("first_block", 0x0e, 2, false),
+    field("last_block", 0x10, 2, false),
+    field("dummy_1", 0x12, 2, false),
+    field("modified_flags1", 0x14, 1, false),
+    field("index_field_number", 0x15, 1, false),
+    field("primary_index_workspace", 0x16, 4, false),
+    field("dummy_2", 0x1a, 4, false),
+    field("index_root_block", 0x1e, 2, false),
+    field("index_levels", 0x20, 1, false),
+    field("num_fields", 0x21, 2, true),
+    field("primary_key_fields", 0x23, 2, true),
+    field("encryption1", 0x25, 4, false),
+    field("sort_order", 0x29, 1, false),
+    field("modified_flags2", 0x2a, 1, false),
+    field("dummy_5", 0x2b, 2, false),
+    field("change_count1", 0x2d, 1, false),
+    field("change_count2", 0x2e, 1, false),
+    field("dummy_6", 0x2f, 1, false),
+    field("table_name_ptr", 0x30, 4, false),
+    field("field_info", 0x34, 4, false),
+    field("write_protected", 0x38, 1, false),
+    field("file_version_id", 0x39, 1, false),
+    field("max_blocks", 0x3a, 2, false),
+    field("dummy_7", 0x3c, 1, false),
+    field("aux_passwords", 0x3d, 1, false),
+    field("dummy_8", 0x3e, 2, false),
+    field("crypt_info_start", 0x40, 4, false),
+    field("crypt_info_end", 0x44, 4, false),
+    field("dummy_9", 0x48, 1, false),
+    field("auto_inc", 0x49, 4, false),
+    field("dummy_a", 0x4d, 2, false),
+    field("index_update_required", 0x4f, 1, false),
+    field("dummy_b", 0x50, 4, false),
+    field("dummy_c", 0x54, 1, false),
+    field("ref_integrity", 0x55, 1, false),
+    field("dummy_d", 0x56, 2, false),
+    field("file_version_id2", 0x58, 2, false),
+    field("file_version_id3", 0x5a, 2, false),
+    field("encryption2", 0x5c, 4, false),
+    field("file_update_time", 0x60, 4, false),
+    field("hi_field_id", 0x64, 2, false),
+    field("hi_field_id_info", 0x66, 2, false),
+    field("sometimes_num_fields", 0x68, 2, false),
+    field("dos_global_code_page", 0x6a, 2, false),
+    field("dummy_e", 0x6c, 4, false),
+    field("change_count4", 0x70, 2, false),
+    field("dummy_f", 0x72, 4, false),
+    field("dummy_10", 0x76, 2, false),
+];
+
+impl HeaderField {
+    // Reads the little endian value of the field from the start of a file
+    pub fn read(&self, data: &[u8]) -> Option<i64> {
+        let bytes = data.get(self.offset..self.offset + self.size)?;
+        let mut raw = [0u8; 8];
+        raw[..self.size].copy_from_slice(bytes);
+        let value = u64::from_le_bytes(raw) as i64;
+
+        // Sign extend from the size of the field
+        let unused = 64 - 8 * self.size as u32;
+        Some(match self.signed {
+            true => (value << unused) >> unused,
+            false => value,
+        })
+    }
+}
+
+pub fn file_type_name(file_type: u8) -> &'static str {
+    match file_type {
+        0x00 => "indexed .DB",
+        0x01 => "primary index .PX",
+        0x02 => "non indexed .DB",
+        0x03 => "non-incrementing secondary index .Xnn",
+        0x04 => "secondary index .Ynn (inc/non-inc)",
+        0x05 => "incrementing secondary index .Xnn",
+        0x06 => "non-incrementing secondary index .XGn",
+        0x07 => "secondary index .YGn (inc/non-inc)",
+        0x08 => "incrementing secondary index .XGn",
+        _ => "Unknown",
+    }
+}
+
+pub fn sort_order_name(sort_order: u8) -> &'static str {
+    match sort_order {
+        0x00 => "ASCII",
+        0xb7 => "International",
+        0x82 | 0xe6 => "Norwegian/Danish",
+        0x0b => "Swedish/Finnish",
+        0x5d => "Spanish",
+        0x62 => "PDX ANSI intl",
+        _ => "Unknown",
+    }
+}
+
+pub fn code_page_name(code_page: u16) -> &'static str {
+    match code_page {
+        0x01b5 => "United States",
+        0x04e4 => "Spain",
+        _ => "Unknown",
+    }
+}
+
 // Field information structure
 
-#[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PxFieldInfo {
-    pub name: [u8; 80],  // char[80]
-    pub field_type: i32, // int
-    pub size: i32,       // int
+    pub name: String,
+    pub field_type: u8,
+    pub size: u8,
 }
 
-type PxRecords = *const u8;
+impl PxFieldInfo {
+    // Number of bytes the field occupies in a record. For BCD fields the
+    // size holds the number of decimals instead.
+    pub fn data_size(&self) -> usize {
+        match self.field_type {
+            PX_FIELD_TYPE_BCD => 17,
+            _ => self.size as usize,
+        }
+    }
 
-#[repr(C)]
-pub struct PxBlocks {
-    pub prev_block: i32,         // int
-    pub next_block: i32,         // int
-    pub num_recs_in_block: i32,  // int
-    pub records: *mut PxRecords, // px_records*
+    pub fn type_name(&self) -> &'static str {
+        field_type_name(self.field_type)
+    }
+
+    // Blob fields keep a leader in the record, larger values live in the
+    // .MB file.
+    pub fn is_blob(&self) -> bool {
+        matches!(
+            self.field_type,
+            PX_FIELD_TYPE_MEMO_BLOB
+                | PX_FIELD_TYPE_BIN_BLOB
+                | PX_FIELD_TYPE_DUNNO
+                | PX_FIELD_TYPE_OLE
+                | PX_FIELD_TYPE_GRAPHIC
+        )
+    }
 }
 
-#[repr(C)]
+pub fn field_type_name(field_type: u8) -> &'static str {
+    match field_type {
+        0x01 => "Alpha",
+        0x02 => "Date",
+        0x03 => "Short Integer",
+        0x04 => "Long Integer",
+        0x05 => "Currency",
+        0x06 => "Number",
+        0x0c => "Memo BLOB",
+        0x10 => "Graphic",
+        0x0d => "BLOB",
+        0x0e => "Formatted Memo",
+        0x0f => "OLE",
+        0x09 => "Logical",
+        0x14 => "Time",
+        0x15 => "Timestamp",
+        0x16 => "Incremental",
+        0x17 => "BCD",
+        0x18 => "Bytes",
+        _ => "Unknown",
+    }
+}
+
+// A data block borrowed from the table file
+#[derive(Debug, Clone, Copy)]
+pub struct PxBlocks<'a> {
+    pub number: u16,
+    pub prev_block: u16,
+    pub next_block: u16,
+    pub num_recs_in_block: usize,
+    pub records: &'a [u8],
+}
+
+// Location of a blob stored in the .MB file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobPointer {
+    pub offset: u32,
+    pub index: u8,
+    pub length: u32,
+    pub mod_number: u16,
+}
 
+#[repr(C)]
 pub struct MbType2Pointer {
     pub type_: u8,
     pub size_div_4k: u16,
@@ -296,6 +631,69 @@ pub struct MbType2Pointer {
     pub mod_count: u16,
 }
 
+// A decoded field value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Alpha(String),
+    Date(i32), // days since 1/1/1
+    Short(i16),
+    Long(i32),
+    Currency(PxCurrency),
+    Number(f64),
+    Logical(bool),
+    Memo(String),
+    Blob(Vec<u8>),
+    BlobRef(BlobPointer),
+    Time(i32),      // milliseconds since midnight
+    Timestamp(f64), // milliseconds since 1/1/1
+    AutoInc(i32),
+    Bcd(String),
+    Bytes(Vec<u8>),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Alpha(s) | Value::Memo(s) | Value::Bcd(s) => write!(f, "{}", s),
+            Value::Short(v) => write!(f, "{}", v),
+            Value::Long(v) | Value::AutoInc(v) => write!(f, "{}", v),
+            Value::Currency(v) => write!(f, "{}", v),
+            Value::Number(v) => write!(f, "{}", v),
+            Value::Logical(v) => write!(f, "{}", v),
+            Value::Date(_) | Value::Time(_) | Value::Timestamp(_) => {
+                match crate::convert::px_to_iso_string(self) {
+                    Ok(s) => write!(f, "{}", s),
+                    Err(_) => write!(f, "{:?}", self),
+                }
+            }
+            Value::Blob(data) | Value::Bytes(data) => {
+                data.iter().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+            Value::BlobRef(pointer) => write!(f, "<blob of {} bytes>", pointer.length),
+        }
+    }
+}
+
+// Dates and times serialize as ISO 8601 strings, Currency values as exact
+// decimal strings, and binary data as hex strings, like they are displayed.
+impl serde::Serialize for Value {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_none(),
+            Value::Short(v) => serializer.serialize_i16(*v),
+            Value::Long(v) | Value::AutoInc(v) => serializer.serialize_i32(*v),
+            Value::Number(v) => serializer.serialize_f64(*v),
+            Value::Logical(v) => serializer.serialize_bool(*v),
+            value => serializer.collect_str(value),
+        }
+    }
+}
+
 // Field types constants
 pub const PX_FIELD_TYPE_ALPHA: u8 = 0x01;
 pub const PX_FIELD_TYPE_DATE: u8 = 0x02;
@@ -307,11 +705,13 @@ pub const PX_FIELD_TYPE_LOGICAL: u8 = 0x09;
 pub const PX_FIELD_TYPE_MEMO_BLOB: u8 = 0x0c;
 pub const PX_FIELD_TYPE_BIN_BLOB: u8 = 0x0d;
 pub const PX_FIELD_TYPE_DUNNO: u8 = 0x0e;
+pub const PX_FIELD_TYPE_OLE: u8 = 0x0f;
 pub const PX_FIELD_TYPE_GRAPHIC: u8 = 0x10;
 pub const PX_FIELD_TYPE_TIME: u8 = 0x14;
 pub const PX_FIELD_TYPE_TIMESTAMP: u8 = 0x15;
 pub const PX_FIELD_TYPE_INCREMENTAL: u8 = 0x16;
 pub const PX_FIELD_TYPE_BCD: u8 = 0x17;
+pub const PX_FIELD_TYPE_BYTES: u8 = 0x18;
 
 // File types constants
 pub const PX_FILETYPE_DB_INDEXED: u8 = 0x00;
@@ -0,0 +1,262 @@
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::table::Table;
+use crate::types::*;
+use crate::writer::TableWriter;
+
+// File version IDs written for each Paradox release
+pub const PX_VERSION_3_5: Version = Version(0x04);
+pub const PX_VERSION_4: Version = Version(0x09);
+pub const PX_VERSION_5: Version = Version(0x0b);
+pub const PX_VERSION_7: Version = Version(0x0c);
+
+// Whether tables of `version` can hold fields of `field_type`. Paradox 4
+// added the blobs, Paradox 5 the remaining types.
+pub fn has_field_type(version: Version, field_type: u8) -> bool {
+    match field_type {
+        PX_FIELD_TYPE_ALPHA
+        | PX_FIELD_TYPE_DATE
+        | PX_FIELD_TYPE_SHORT_INT
+        | PX_FIELD_TYPE_CURRENCY
+        | PX_FIELD_TYPE_NUMBER => true,
+        PX_FIELD_TYPE_MEMO_BLOB
+        | PX_FIELD_TYPE_BIN_BLOB
+        | PX_FIELD_TYPE_DUNNO
+        | PX_FIELD_TYPE_OLE
+        | PX_FIELD_TYPE_GRAPHIC => version.0 >= 0x05,
+        PX_FIELD_TYPE_LONG_INT
+        | PX_FIELD_TYPE_TIME
+        | PX_FIELD_TYPE_TIMESTAMP
+        | PX_FIELD_TYPE_LOGICAL
+        | PX_FIELD_TYPE_INCREMENTAL
+        | PX_FIELD_TYPE_BCD
+        | PX_FIELD_TYPE_BYTES => version.0 >= 0x0a,
+        _ => false,
+    }
+}
+
+// How the values of a field are carried over into the converted table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mapping {
+    Keep,
+    LongToNumber,
+    TimeToAlpha,
+    TimestampToDate,
+    LogicalToAlpha,
+    BcdToNumber,
+    BytesToHex,
+    MemoToAlpha,
+}
+
+fn field(name: &str, field_type: u8, size: u8) -> PxFieldInfo {
+    PxFieldInfo {
+        name: name.to_string(),
+        field_type,
+        size,
+    }
+}
+
+// The field replacing `old` in a table of `version`, with a description
+// of what is lost for the warning.
+fn map_field(old: &PxFieldInfo, version: Version) -> Result<(PxFieldInfo, Mapping, &'static str)> {
+    if has_field_type(version, old.field_type) {
+        return Ok((old.clone(), Mapping::Keep, ""));
+    }
+
+    let name = &old.name;
+    Ok(match old.field_type {
+        PX_FIELD_TYPE_LONG_INT => (
+            field(name, PX_FIELD_TYPE_NUMBER, 8),
+            Mapping::LongToNumber,
+            "stored as Number",
+        ),
+        PX_FIELD_TYPE_INCREMENTAL => (
+            field(name, PX_FIELD_TYPE_NUMBER, 8),
+            Mapping::LongToNumber,
+            "stored as Number, new values are no longer numbered automatically",
+        ),
+        PX_FIELD_TYPE_TIME => (
+            field(name, PX_FIELD_TYPE_ALPHA, 12),
+            Mapping::TimeToAlpha,
+            "stored as Alpha(12) text",
+        ),
+        PX_FIELD_TYPE_TIMESTAMP => (
+            field(name, PX_FIELD_TYPE_DATE, 4),
+            Mapping::TimestampToDate,
+            "stored as Date, the time of day is dropped",
+        ),
+        PX_FIELD_TYPE_LOGICAL => (
+            field(name, PX_FIELD_TYPE_ALPHA, 1),
+            Mapping::LogicalToAlpha,
+            "stored as Alpha(1) holding T or F",
+        ),
+        PX_FIELD_TYPE_BCD => (
+            field(name, PX_FIELD_TYPE_NUMBER, 8),
+            Mapping::BcdToNumber,
+            "stored as Number, digits beyond double precision are lost",
+        ),
+        PX_FIELD_TYPE_BYTES => (
+            field(name, PX_FIELD_TYPE_ALPHA, old.size.saturating_mul(2)),
+            Mapping::BytesToHex,
+            "stored as Alpha hex digits, longer data is truncated",
+        ),
+        PX_FIELD_TYPE_MEMO_BLOB | PX_FIELD_TYPE_DUNNO => (
+            field(name, PX_FIELD_TYPE_ALPHA, 255),
+            Mapping::MemoToAlpha,
+            "stored as Alpha(255), longer text is truncated",
+        ),
+        _ => {
+            return Err(Error::unsupported(format!(
+                "{} field '{}' cannot be stored in a version {} table",
+                old.type_name(),
+                name,
+                version.name()
+            )))
+        }
+    })
+}
+
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+fn truncate(s: &str, len: usize) -> String {
+    s.chars().take(len).collect()
+}
+
+fn map_value(value: Value, mapping: Mapping, size: u8) -> Result<Value> {
+    Ok(match (mapping, value) {
+        (_, value @ Value::Null) | (Mapping::Keep, value) => value,
+        (Mapping::LongToNumber, Value::Long(v) | Value::AutoInc(v)) => Value::Number(v as f64),
+        (Mapping::TimeToAlpha, value @ Value::Time(_)) => Value::Alpha(value.to_string()),
+        (Mapping::TimestampToDate, Value::Timestamp(ms)) => {
+            Value::Date((ms / MS_PER_DAY).floor() as i32)
+        }
+        (Mapping::LogicalToAlpha, Value::Logical(v)) => {
+            Value::Alpha(if v { "T" } else { "F" }.to_string())
+        }
+        (Mapping::BcdToNumber, Value::Bcd(s)) => Value::Number(
+            s.parse()
+                .map_err(|_| Error::value(format!("Invalid BCD value '{}'", s)))?,
+        ),
+        (Mapping::BytesToHex, value @ Value::Bytes(_)) => {
+            Value::Alpha(truncate(&value.to_string(), size as usize))
+        }
+        (Mapping::MemoToAlpha, Value::Memo(s) | Value::Alpha(s)) => {
+            Value::Alpha(truncate(&s, size as usize))
+        }
+        (_, value) => return Err(Error::value(format!("Unexpected value {:?}", value))),
+    })
+}
+
+// Secondary index files next to a table: .Xnn, .Ynn, .XGn and .YGn
+pub(crate) fn secondary_indexes(path: &Path) -> Vec<String> {
+    let (Some(dir), Some(stem)) = (path.parent(), path.file_stem()) else {
+        return vec![];
+    };
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut found: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.file_stem().is_some_and(|s| s.eq_ignore_ascii_case(stem)))
+        .filter_map(|p| {
+            let extension = p.extension()?.to_str()?.to_ascii_uppercase();
+            let secondary =
+                extension.len() == 3 && (extension.starts_with('X') || extension.starts_with('Y'));
+            secondary.then(|| p.display().to_string())
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+// The header of a converted table and what was lost converting it
+#[derive(Debug)]
+pub struct Conversion {
+    pub header: PxHeader,
+    pub warnings: Vec<String>,
+}
+
+impl Table {
+    // Writes a copy of the table at `path` in the format of another
+    // Paradox version, the header gaining or losing the Paradox 4+
+    // extension. Field types the version lacks are replaced, see
+    // `map_field`, with a warning for each. The records of keyed tables
+    // are written in key order with a new primary index, secondary indexes
+    // are not converted.
+    pub fn convert_version<P: AsRef<Path>>(&self, path: P, version: Version) -> Result<Conversion> {
+        if !version.is_supported() {
+            return Err(Error::invalid(format!(
+                "Unknown file version {:02x}",
+                version.0
+            )));
+        }
+
+        let header = self.header();
+        let mut fields = vec![];
+        let mut mappings = vec![];
+        let mut warnings = vec![];
+        for old in self.fields() {
+            let (new, mapping, loss) = map_field(old, version)?;
+            if mapping != Mapping::Keep {
+                warnings.push(format!(
+                    "{} field '{}' is {}",
+                    old.type_name(),
+                    old.name,
+                    loss
+                ));
+            }
+            fields.push(new);
+            mappings.push(mapping);
+        }
+
+        if let Some(path) = self.path() {
+            for index in secondary_indexes(path) {
+                warnings.push(format!("secondary index {} is not converted", index));
+            }
+        }
+
+        let mut template = header.clone();
+        template.file_version_id = version;
+        if template.dos_global_code_page == 0 {
+            template.dos_global_code_page = 0x01b5;
+        }
+
+        let mut writer = match header.file_type {
+            PX_FILETYPE_DB_INDEXED => TableWriter::create_keyed(
+                path,
+                &template,
+                fields,
+                header.primary_key_fields.max(0) as usize,
+            )?,
+            _ => TableWriter::create(path, &template, fields)?,
+        };
+
+        for record in self.records() {
+            let record = record?;
+            let values = mappings
+                .iter()
+                .enumerate()
+                .map(|(i, &mapping)| {
+                    let size = writer.fields()[i].size;
+                    map_value(record.fetch(i)?, mapping, size)
+                        .map_err(|e| e.in_field(&self.fields()[i].name))
+                })
+                .collect::<Result<Vec<_>>>()
+                .map_err(|e| e.at_block(record.block()).at_record(record.slot()))?;
+
+            writer.write_values(&values)?;
+        }
+
+        Ok(Conversion {
+            header: writer.finish()?,
+            warnings,
+        })
+    }
+}
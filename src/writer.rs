@@ -0,0 +1,853 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::collation::Collation;
+use crate::convert::encode_field;
+use crate::error::{Error, Result};
+use crate::index::IndexEntry;
+use crate::lock::{create_file, create_locked, open_locked, TableLock};
+use crate::parse::PX_BLOCK_HEADER_SIZE;
+use crate::table::Table;
+use crate::types::*;
+use crate::version::secondary_indexes;
+
+// Name Paradox stores for each sort order at the end of the header
+fn sort_order_id(sort_order: u8) -> &'static str {
+    match sort_order {
+        0xb7 => "intl",
+        0x82 => "nordan",
+        0xe6 => "nordan40",
+        0x0b => "swedfin",
+        0x5d => "spanish",
+        0x62 => "ANSIINTL",
+        _ => "ascii",
+    }
+}
+
+fn table_name_len(header: &PxHeader) -> usize {
+    if header.file_version_id.0 >= 0x0c {
+        261
+    } else {
+        79
+    }
+}
+
+fn latin1(s: &str) -> Result<Vec<u8>> {
+    s.chars()
+        .map(|c| u8::try_from(c).ok())
+        .collect::<Option<_>>()
+        .ok_or_else(|| Error::invalid(format!("'{}' cannot be written in Latin-1", s)))
+}
+
+// Size of the header written for `fields`, rounded up to the usual 2k.
+// Index files carry neither field names nor the sort order.
+fn header_size(header: &PxHeader, fields: &[PxFieldInfo]) -> Result<usize> {
+    let mut size = header.fixed_size() + 2 * fields.len() + 4 + table_name_len(header);
+    if !header.is_index() {
+        let mut names = 0;
+        for field in fields {
+            names += latin1(&field.name)?.len() + 1;
+        }
+        size += 4 * fields.len()
+            + names
+            + 2 * fields.len()
+            + sort_order_id(header.sort_order).len()
+            + 1;
+    }
+
+    Ok(size.div_ceil(0x800) * 0x800)
+}
+
+// Serializes the header of a data or index file the way
+// `parse_complete_header` reads it. Pointers only meaningful in RAM are
+// written as NIL.
+fn header_bytes(header: &PxHeader, fields: &[PxFieldInfo]) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(header.header_size as usize);
+    header.write_to(&mut data)?;
+
+    let mut offset = data.len();
+    data.resize(header.header_size as usize, 0);
+
+    let mut put = |offset: &mut usize, bytes: &[u8]| {
+        data[*offset..*offset + bytes.len()].copy_from_slice(bytes);
+        *offset += bytes.len();
+    };
+
+    for field in fields {
+        put(&mut offset, &[field.field_type, field.size]);
+    }
+
+    // tableNamePtr and fieldNamePtrArray
+    offset += 4;
+    if !header.is_index() {
+        offset += 4 * fields.len();
+    }
+
+    let mut table_name = latin1(&header.table_name)?;
+    table_name.resize(table_name_len(header), 0);
+    put(&mut offset, &table_name);
+
+    if header.is_index() {
+        return Ok(data);
+    }
+
+    for field in fields {
+        put(&mut offset, &latin1(&field.name)?);
+        put(&mut offset, &[0]);
+    }
+
+    for number in 1..=fields.len() as i16 {
+        put(&mut offset, &number.to_le_bytes());
+    }
+
+    put(&mut offset, sort_order_id(header.sort_order).as_bytes());
+
+    Ok(data)
+}
+
+impl PxHeader {
+    // Header template for a new version 7 table with the default settings.
+    pub fn new_table(table_name: &str) -> Self {
+        PxHeader {
+            file_type: PX_FILETYPE_DB_NOT_INDEXED,
+            file_version_id: Version(0x0c),
+            dos_global_code_page: 0x01b5,
+            table_name: table_name.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+fn auto_inc_value(value: &Value) -> Option<i32> {
+    match value {
+        Value::Short(v) => Some(*v as i32),
+        Value::Long(v) | Value::AutoInc(v) => Some(*v),
+        _ => None,
+    }
+}
+
+fn write_at(file: &mut BufWriter<File>, offset: usize, data: &[u8]) -> io::Result<()> {
+    file.seek(SeekFrom::Start(offset as u64))?;
+    file.write_all(data)
+}
+
+// Writes the records of a new table, or adds them to the end of an
+// existing one. Records are written a block at a time: a full block goes to
+// disk, then its link from the block before, then the counts and AutoInc
+// counter of the header. A writer that stops early, or crashes, leaves a
+// readable table with the records of its full blocks. The records of a
+// partly filled last block are only written by `finish`, and a block that
+// was partly filled before appending may hold more records on disk than
+// the header counts. Keyed tables take their records in key order and get
+// their primary index written on `finish`, until then the .PX file is the
+// old one. The change counters of the header are bumped on `finish` too,
+// the update time is left alone. Blank AutoInc fields are numbered from
+// the `auto_inc` counter of the header.
+pub struct TableWriter {
+    file: BufWriter<File>,
+    // Held until the writer is done
+    _lock: TableLock,
+    path: PathBuf,
+    header: PxHeader,
+    fields: Vec<PxFieldInfo>,
+    // Offsets of the fields in a record
+    offsets: Vec<usize>,
+    // The last block and the number of records in it, 0 once it is full
+    block: Vec<u8>,
+    block_number: u16,
+    block_records: usize,
+    records_per_block: usize,
+    keys: usize,
+    last_key: Option<Vec<Value>>,
+    index: Vec<IndexEntry>,
+    // AutoInc fields with the values written to them
+    auto_inc: Vec<(usize, HashSet<i32>)>,
+    // Whether records were written
+    changed: bool,
+}
+
+impl TableWriter {
+    // Creates the table at `path`. The version, sort order, code page and
+    // block size are taken from `template`, a block size of 0 picks the
+    // smallest one that fits a record.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        template: &PxHeader,
+        fields: Vec<PxFieldInfo>,
+    ) -> Result<Self> {
+        let invalid = |msg: &str| Error::invalid(msg);
+
+        let record_size: usize = fields.iter().map(PxFieldInfo::data_size).sum();
+        if fields.is_empty() || record_size > i16::MAX as usize {
+            return Err(invalid("Invalid number of fields or record size"));
+        }
+
+        let mut header = template.clone();
+        header.file_type = PX_FILETYPE_DB_NOT_INDEXED;
+        header.primary_key_fields = 0;
+        header.num_fields = fields.len() as i16;
+        header.record_size = record_size as i16;
+        header.num_records = 0;
+        header.file_blocks = 0;
+        header.first_block = 0;
+        header.last_block = 0;
+        header.used_blocks = 0;
+        header.max_blocks = 0;
+        header.modified_flags1 = 0;
+        header.modified_flags2 = 0;
+        header.index_update_required = 0;
+
+        if header.max_table_size == 0 {
+            header.max_table_size = (1..=4)
+                .find(|n| PX_BLOCK_HEADER_SIZE + record_size <= n * 0x400)
+                .unwrap_or(4) as u8;
+        }
+
+        let records_per_block =
+            header.block_size().saturating_sub(PX_BLOCK_HEADER_SIZE) / record_size;
+        if records_per_block == 0 {
+            return Err(invalid("Record does not fit in a block"));
+        }
+
+        if !header.has_v4_header() {
+            header.encryption1 = 0;
+        } else {
+            header.encryption1 = 0xff00ff00;
+            header.file_version_id2 = 0x0100 | header.file_version_id.0 as u16;
+            header.file_version_id3 = header.file_version_id2;
+            header.hi_field_id = fields.len() as u16 + 1;
+        }
+
+        header.header_size = header_size(&header, &fields)? as i16;
+
+        let path = path.as_ref();
+        let (file, lock) = create_locked(path)?;
+        let mut file = BufWriter::new(file);
+        file.write_all(&header_bytes(&header, &fields)?)?;
+
+        Ok(TableWriter {
+            file,
+            _lock: lock,
+            path: path.to_path_buf(),
+            block: vec![0; header.block_size()],
+            block_number: 0,
+            block_records: 0,
+            records_per_block,
+            offsets: field_offsets(&fields),
+            auto_inc: auto_inc_fields(&fields),
+            header,
+            fields,
+            keys: 0,
+            last_key: None,
+            index: vec![],
+            changed: false,
+        })
+    }
+
+    // Opens an existing table to add records after the last block of its
+    // linked list. The records of keyed tables must follow the last key of
+    // the table, the primary index is rewritten on `finish`. Tables with
+    // secondary indexes are refused, the indexes would miss the new
+    // records. The .MB file is left alone.
+    pub fn append<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(index) = secondary_indexes(path).first() {
+            return Err(Error::invalid(format!(
+                "Records cannot be added to a table with secondary indexes, {} would be out of date",
+                index
+            ))
+            .in_file(path));
+        }
+
+        let (file, lock) = open_locked(path)?;
+        let mut data = vec![];
+        (&file)
+            .read_to_end(&mut data)
+            .map_err(|e| Error::from(e).in_file(path))?;
+        let table = Table::from_bytes(data).map_err(|e| e.in_file(path))?;
+
+        Self::resume(file, lock, path, &table).map_err(|e| e.in_file(path))
+    }
+
+    // A writer positioned after the last record of `table`
+    fn resume(file: File, lock: TableLock, path: &Path, table: &Table) -> Result<Self> {
+        let mut header = table.header().clone();
+        let fields = table.fields().to_vec();
+        let keys = match header.file_type {
+            PX_FILETYPE_DB_INDEXED => header.primary_key_fields.max(0) as usize,
+            PX_FILETYPE_DB_NOT_INDEXED => 0,
+            _ => return Err(Error::invalid("Records can only be added to .DB tables")),
+        };
+
+        let record_size = header.record_size.max(0) as usize;
+        let records_per_block =
+            header.block_size().saturating_sub(PX_BLOCK_HEADER_SIZE) / record_size.max(1);
+        if records_per_block == 0 {
+            return Err(Error::corrupt("Record does not fit in a block"));
+        }
+
+        // The first key of every block for the index, the AutoInc values in
+        // use and the last key
+        let mut index: Vec<IndexEntry> = vec![];
+        let mut auto_inc = auto_inc_fields(&fields);
+        let mut last_key = None;
+        for record in table.records() {
+            let record = record?;
+            if keys > 0 {
+                let key = (0..keys)
+                    .map(|i| record.fetch(i))
+                    .collect::<Result<Vec<_>>>()?;
+                if record.slot() == 0 {
+                    index.push(IndexEntry {
+                        key: key.clone(),
+                        block: record.block(),
+                        count: 0,
+                    });
+                }
+                if let Some(entry) = index.last_mut() {
+                    entry.count += 1;
+                }
+                last_key = Some(key);
+            }
+            for (i, used) in &mut auto_inc {
+                if let Some(value) = auto_inc_value(&record.fetch(*i)?) {
+                    header.auto_inc = header.auto_inc.max(value.max(0) as u32);
+                    used.insert(value);
+                }
+            }
+        }
+
+        // New records fill up the last block first
+        let mut block = vec![0; header.block_size()];
+        let mut block_number = 0;
+        let mut block_records = 0;
+        if let Some(last) = table.blocks().last() {
+            let last = last?;
+            let start = table.block_offset(last.number);
+            block.copy_from_slice(&table.raw()[start..start + header.block_size()]);
+            block_number = last.number;
+            block_records = last.num_recs_in_block % records_per_block;
+        }
+
+        Ok(TableWriter {
+            file: BufWriter::new(file),
+            _lock: lock,
+            path: path.to_path_buf(),
+            block,
+            block_number,
+            block_records,
+            records_per_block,
+            offsets: field_offsets(&fields),
+            auto_inc,
+            header,
+            fields,
+            keys,
+            last_key,
+            index,
+            changed: false,
+        })
+    }
+
+    // Creates a keyed table whose primary key is made of the first
+    // `key_fields` fields. The .PX file is written next to it.
+    pub fn create_keyed<P: AsRef<Path>>(
+        path: P,
+        template: &PxHeader,
+        fields: Vec<PxFieldInfo>,
+        key_fields: usize,
+    ) -> Result<Self> {
+        if key_fields == 0 || key_fields > fields.len() {
+            return Err(Error::invalid("Invalid number of key fields"));
+        }
+        if let Some(field) = fields[..key_fields].iter().find(|f| f.is_blob()) {
+            return Err(Error::invalid(format!(
+                "Blob field '{}' cannot be part of the key",
+                field.name
+            )));
+        }
+
+        let mut writer = Self::create(path, template, fields)?;
+        writer.header.file_type = PX_FILETYPE_DB_INDEXED;
+        writer.header.primary_key_fields = key_fields as i16;
+        writer.keys = key_fields;
+        Ok(writer)
+    }
+
+    pub fn header(&self) -> &PxHeader {
+        &self.header
+    }
+
+    pub fn fields(&self) -> &[PxFieldInfo] {
+        &self.fields
+    }
+
+    // Appends a record, `values` being in field order. Blank AutoInc fields
+    // get the next number of the counter, which `header().auto_inc` holds
+    // afterwards.
+    pub fn write_values(&mut self, values: &[Value]) -> Result<()> {
+        if values.len() != self.fields.len() {
+            return Err(Error::invalid(format!(
+                "Expected {} values, got {}",
+                self.fields.len(),
+                values.len()
+            )));
+        }
+
+        let (values, counter) = self.assign_auto_inc(values)?;
+        let values = &values[..];
+
+        let record_size = self.header.record_size as usize;
+        let mut record = vec![0; record_size];
+        for ((value, field), &offset) in values.iter().zip(&self.fields).zip(&self.offsets) {
+            encode_field(
+                value,
+                field,
+                &mut record[offset..offset + field.data_size()],
+            )?;
+        }
+
+        if self.keys > 0 {
+            self.check_key(&values[..self.keys])?;
+        }
+        if self.block_records == 0 {
+            self.add_block()?;
+        }
+
+        let start = PX_BLOCK_HEADER_SIZE + self.block_records * record_size;
+        self.block[start..start + record_size].copy_from_slice(&record);
+        self.block_records += 1;
+        self.header.num_records += 1;
+        if let Some(entry) = self.index.last_mut() {
+            entry.count = self.block_records as u16;
+        }
+
+        // Only a record that made it into the table uses up its numbers
+        for (i, used) in &mut self.auto_inc {
+            if let Some(value) = auto_inc_value(&values[*i]) {
+                used.insert(value);
+            }
+        }
+        self.header.auto_inc = counter;
+        self.changed = true;
+
+        if self.block_records == self.records_per_block {
+            self.write_block()?;
+            self.block_records = 0;
+        }
+
+        Ok(())
+    }
+
+    // Fills in blank AutoInc fields and checks that given values are
+    // positive and not used yet. Returns the values to write with the
+    // counter after them, which is the highest value used.
+    fn assign_auto_inc<'v>(&self, values: &'v [Value]) -> Result<(Cow<'v, [Value]>, u32)> {
+        let mut values = Cow::Borrowed(values);
+        let mut counter = self.header.auto_inc;
+
+        for (i, used) in &self.auto_inc {
+            let name = &self.fields[*i].name;
+            if values[*i] == Value::Null {
+                counter = counter
+                    .checked_add(1)
+                    .filter(|&n| n <= i32::MAX as u32)
+                    .ok_or_else(|| Error::invalid("AutoInc counter overflow").in_field(name))?;
+                values.to_mut()[*i] = Value::AutoInc(counter as i32);
+                continue;
+            }
+
+            // Values of the wrong type are reported when encoding
+            let Some(value) = auto_inc_value(&values[*i]) else {
+                continue;
+            };
+            if value < 1 {
+                return Err(Error::invalid("AutoInc values must be positive").in_field(name));
+            }
+            if used.contains(&value) {
+                return Err(
+                    Error::invalid(format!("AutoInc value {} is already used", value))
+                        .in_field(name),
+                );
+            }
+            counter = counter.max(value as u32);
+        }
+
+        Ok((values, counter))
+    }
+
+    // Keys must be unique and ascending. The first key of every block goes
+    // into the index.
+    fn check_key(&mut self, key: &[Value]) -> Result<()> {
+        let collation = Collation::from_sort_order(self.header.sort_order).unwrap_or_default();
+        if let Some(last) = &self.last_key {
+            let mut ordering = Ordering::Equal;
+            for (a, b) in last.iter().zip(key) {
+                ordering = collation.key_order(a, b)?;
+                if ordering.is_ne() {
+                    break;
+                }
+            }
+            if ordering.is_ge() {
+                return Err(Error::invalid(match ordering {
+                    Ordering::Equal => "Duplicate key",
+                    _ => "Records of a keyed table must be written in key order",
+                }));
+            }
+        }
+
+        if self.block_records == 0 {
+            // The record starts the block `add_block` adds next
+            self.index.push(IndexEntry {
+                key: key.to_vec(),
+                block: self.header.file_blocks + 1,
+                count: 0,
+            });
+        }
+        self.last_key = Some(key.to_vec());
+        Ok(())
+    }
+
+    fn block_offset(&self, number: u16) -> usize {
+        self.header.header_size as usize + (number as usize - 1) * self.header.block_size()
+    }
+
+    // Starts a new block after the last one, which `write_block` puts at
+    // the end of the file
+    fn add_block(&mut self) -> Result<()> {
+        let number = self
+            .header
+            .file_blocks
+            .checked_add(1)
+            .ok_or_else(|| Error::invalid("The table has no more room for blocks"))?;
+
+        if self.block_number == 0 {
+            self.header.first_block = number;
+        }
+
+        self.block.fill(0);
+        self.block[2..4].copy_from_slice(&self.block_number.to_le_bytes());
+        self.block_number = number;
+
+        self.header.file_blocks = number;
+        self.header.used_blocks += 1;
+        self.header.last_block = number;
+        self.header.max_blocks = self.header.max_blocks.max(number);
+
+        Ok(())
+    }
+
+    // Writes the last block, links it from the one before and writes the
+    // counts of the table header, in this order so the table on disk is
+    // readable at every step
+    fn write_block(&mut self) -> Result<()> {
+        let add_data_size = (self.block_records as i16 - 1) * self.header.record_size;
+        self.block[4..6].copy_from_slice(&add_data_size.to_le_bytes());
+
+        let offset = self.block_offset(self.block_number);
+        write_at(&mut self.file, offset, &self.block)?;
+
+        let previous = u16::from_le_bytes([self.block[2], self.block[3]]);
+        if previous != 0 {
+            let offset = self.block_offset(previous);
+            write_at(&mut self.file, offset, &self.block_number.to_le_bytes())?;
+        }
+
+        self.write_counts()?;
+        self.file.flush()?;
+
+        Ok(())
+    }
+
+    // Writes the fixed part of the header
+    fn write_counts(&mut self) -> Result<()> {
+        let mut header = Vec::with_capacity(self.header.fixed_size());
+        self.header.write_to(&mut header)?;
+        write_at(&mut self.file, 0, &header)?;
+        Ok(())
+    }
+
+    // Writes the final counts and, for keyed tables, the primary index.
+    // Only the fixed part of the header changes, the field numbers, names
+    // and sort order after it keep the bytes they were created or found
+    // with.
+    pub fn finish(mut self) -> Result<PxHeader> {
+        if self.changed {
+            self.header.change_count1 = self.header.change_count1.wrapping_add(1);
+            if self.header.has_v4_header() {
+                self.header.change_count4 = self.header.change_count4.wrapping_add(1);
+            }
+        }
+
+        if self.block_records > 0 {
+            self.write_block()?;
+        } else {
+            self.write_counts()?;
+            self.file.flush()?;
+        }
+
+        if self.keys > 0 {
+            let path = self.path.with_extension(index_extension(&self.path));
+            write_primary_index(&path, &self.header, &self.fields[..self.keys], self.index)?;
+        }
+
+        Ok(self.header)
+    }
+}
+
+// Offsets of the fields in a record
+fn field_offsets(fields: &[PxFieldInfo]) -> Vec<usize> {
+    fields
+        .iter()
+        .scan(0, |offset, field| {
+            let start = *offset;
+            *offset += field.data_size();
+            Some(start)
+        })
+        .collect()
+}
+
+fn auto_inc_fields(fields: &[PxFieldInfo]) -> Vec<(usize, HashSet<i32>)> {
+    fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| field.field_type == PX_FIELD_TYPE_INCREMENTAL)
+        .map(|(i, _)| (i, HashSet::new()))
+        .collect()
+}
+
+// Keeps the case of the table's extension for the .PX file
+fn index_extension(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.chars().any(|c| c.is_ascii_lowercase()) => "px",
+        _ => "PX",
+    }
+}
+
+// Writes the .PX file of a keyed table from the first key, number and
+// record count of each data block. Entries are packed into blocks level by
+// level, each level pointing to the blocks of the one below, up to a
+// single root block.
+fn write_primary_index(
+    path: &Path,
+    table: &PxHeader,
+    key_fields: &[PxFieldInfo],
+    entries: Vec<IndexEntry>,
+) -> Result<()> {
+    let mut fields = key_fields.to_vec();
+    for name in ["Block", "Count", "Unknown"] {
+        fields.push(PxFieldInfo {
+            name: name.to_string(),
+            field_type: PX_FIELD_TYPE_SHORT_INT,
+            size: 2,
+        });
+    }
+    let record_size: usize = fields.iter().map(PxFieldInfo::data_size).sum();
+
+    let mut header = PxHeader {
+        file_type: PX_FILETYPE_PX,
+        max_table_size: table.max_table_size,
+        num_fields: key_fields.len() as i16,
+        primary_key_fields: key_fields.len() as i16,
+        record_size: record_size as i16,
+        sort_order: table.sort_order,
+        file_version_id: table.file_version_id,
+        table_name: table.table_name.clone(),
+        ..Default::default()
+    };
+    header.header_size = header_size(&header, key_fields)? as i16;
+
+    let per_block = (header.block_size() - PX_BLOCK_HEADER_SIZE) / record_size;
+    if per_block < 2 {
+        return Err(Error::invalid("Index entries do not fit in a block"));
+    }
+
+    let mut blocks: Vec<Vec<IndexEntry>> = vec![];
+    let mut level = entries;
+    loop {
+        header.index_levels += 1;
+        let first = blocks.len() as u16 + 1;
+        let chunks: Vec<Vec<IndexEntry>> = match level.is_empty() {
+            true => vec![vec![]],
+            false => level.chunks(per_block).map(<[_]>::to_vec).collect(),
+        };
+
+        level = chunks
+            .iter()
+            .zip(first..)
+            .map(|(chunk, block)| IndexEntry {
+                key: chunk.first().map(|e| e.key.clone()).unwrap_or_default(),
+                block,
+                // Counts are stored as shorts, only the leaves are exact
+                count: chunk
+                    .iter()
+                    .map(|e| e.count as u32)
+                    .sum::<u32>()
+                    .min(i16::MAX as u32) as u16,
+            })
+            .collect();
+        blocks.extend(chunks);
+
+        if level.len() == 1 {
+            header.index_root_block = level[0].block;
+            break;
+        }
+    }
+
+    let count = blocks.len() as u16;
+    header.num_records = blocks.iter().map(|b| b.len() as u32).sum();
+    header.file_blocks = count;
+    header.used_blocks = count;
+    header.first_block = 1;
+    header.last_block = count;
+    header.max_blocks = count;
+
+    let mut file = BufWriter::new(create_file(path)?);
+    file.write_all(&header_bytes(&header, key_fields)?)?;
+
+    let mut block = vec![0u8; header.block_size()];
+    for entries in &blocks {
+        block.fill(0);
+        let add_data_size = (entries.len() as i16 - 1) * header.record_size;
+        block[4..6].copy_from_slice(&add_data_size.to_le_bytes());
+
+        for (i, entry) in entries.iter().enumerate() {
+            let values = entry.key.iter().cloned().chain([
+                Value::Short(entry.block as i16),
+                Value::Short(entry.count as i16),
+                Value::Null,
+            ]);
+            let mut offset = PX_BLOCK_HEADER_SIZE + i * record_size;
+            for (value, field) in values.zip(&fields) {
+                let size = field.data_size();
+                encode_field(&value, field, &mut block[offset..offset + size])?;
+                offset += size;
+            }
+        }
+        file.write_all(&block)?;
+    }
+
+    file.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{field, TempDir};
+
+    #[test]
+    fn appends_records_and_keeps_numbering_auto_inc_fields() {
+        let dir = TempDir::new("writer-append");
+        let path = dir.join("APPEND.DB");
+        let fields = vec![
+            field("Id", PX_FIELD_TYPE_INCREMENTAL, 4),
+            field("Name", PX_FIELD_TYPE_ALPHA, 10),
+        ];
+        let name = |i: usize| Value::Alpha(format!("n{}", i));
+
+        let mut writer =
+            TableWriter::create_keyed(&path, &PxHeader::new_table("APPEND"), fields, 1).unwrap();
+        for i in 0..300 {
+            writer.write_values(&[Value::Null, name(i)]).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut writer = TableWriter::append(&path).unwrap();
+        let per_block = writer.records_per_block;
+        for i in 300..500 {
+            writer.write_values(&[Value::Null, name(i)]).unwrap();
+
+            // The table on disk holds the records of the full blocks
+            let written = ((i + 1) / per_block * per_block).max(300);
+            let table = Table::open(&path).unwrap();
+            assert_eq!(table.header().num_records, written as u32);
+            assert_eq!(table.header().auto_inc, written as u32);
+            assert_eq!(table.records().count(), written);
+        }
+        let duplicate = writer.write_values(&[Value::AutoInc(7), name(0)]);
+        assert!(duplicate.is_err());
+        writer.finish().unwrap();
+
+        let table = Table::open(&path).unwrap();
+        let ids = table
+            .records()
+            .map(|r| r.unwrap().fetch(0).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, (1..=500).map(Value::AutoInc).collect::<Vec<_>>());
+        let index = table.primary_index().unwrap().unwrap();
+        assert_eq!(index.num_records().unwrap(), 500);
+        let found = table
+            .range(Value::AutoInc(420)..)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.fetch(1).unwrap(), name(419));
+        let last = table.record_at(499).unwrap().unwrap();
+        assert_eq!(last.fetch(0).unwrap(), Value::AutoInc(500));
+    }
+
+    #[test]
+    fn appending_keeps_the_header_after_the_fixed_fields() {
+        let dir = TempDir::new("writer-header");
+        let path = dir.join("HEADER.DB");
+        let fields = vec![field("Id", PX_FIELD_TYPE_LONG_INT, 4)];
+        let mut writer =
+            TableWriter::create(&path, &PxHeader::new_table("HEADER"), fields).unwrap();
+        writer.write_values(&[Value::Long(1)]).unwrap();
+        let header = writer.finish().unwrap();
+
+        // A sort order name this crate does not know
+        let mut data = std::fs::read(&path).unwrap();
+        let end = header.header_size as usize;
+        let at = data[..end].windows(5).position(|w| w == b"ascii").unwrap();
+        data[at..at + 5].copy_from_slice(b"xyzzy");
+        std::fs::write(&path, &data).unwrap();
+
+        let mut writer = TableWriter::append(&path).unwrap();
+        writer.write_values(&[Value::Long(2)]).unwrap();
+        writer.finish().unwrap();
+
+        let written = std::fs::read(&path).unwrap();
+        let fixed = header.fixed_size();
+        assert_eq!(written[fixed..end], data[fixed..end]);
+        let table = Table::open(&path).unwrap();
+        assert_eq!(table.header().num_records, 2);
+        assert_eq!(table.header().change_count1, header.change_count1 + 1);
+        assert_eq!(table.header().change_count4, header.change_count4 + 1);
+    }
+
+    #[test]
+    fn names_must_be_latin1() {
+        let dir = TempDir::new("writer-latin1");
+        let header = PxHeader::new_table("NAMES");
+        let fields = vec![field("Café", PX_FIELD_TYPE_LONG_INT, 4)];
+        assert!(TableWriter::create(dir.join("CAFE.DB"), &header, fields).is_ok());
+
+        let fields = vec![field("Price €", PX_FIELD_TYPE_LONG_INT, 4)];
+        let Err(error) = TableWriter::create(dir.join("EURO.DB"), &header, fields) else {
+            panic!("'€' was written");
+        };
+        assert!(error.to_string().contains("Latin-1"), "{}", error);
+    }
+
+    #[test]
+    fn tables_with_secondary_indexes_are_not_appended_to() {
+        let dir = TempDir::new("writer-secondary");
+        let path = dir.join("INDEXED.DB");
+        let fields = vec![field("Id", PX_FIELD_TYPE_LONG_INT, 4)];
+        let writer = TableWriter::create(&path, &PxHeader::new_table("INDEXED"), fields).unwrap();
+        writer.finish().unwrap();
+        assert!(TableWriter::append(&path).is_ok());
+
+        std::fs::write(dir.join("INDEXED.X02"), b"").unwrap();
+        let Err(error) = TableWriter::append(&path) else {
+            panic!("appended to a table with a secondary index");
+        };
+        assert!(error.to_string().contains("INDEXED.X02"), "{}", error);
+    }
+}
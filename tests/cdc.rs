@@ -0,0 +1,113 @@
+// Change data capture over successive versions of a keyed table
+
+#[path = "../src/testing.rs"]
+mod testing;
+
+use pxrs::cdc::{capture, CdcState, Event};
+use pxrs::types::*;
+use pxrs::Table;
+use testing::{field, write_table, TempDir};
+
+fn fields() -> Vec<PxFieldInfo> {
+    vec![
+        field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+        field("Name", PX_FIELD_TYPE_ALPHA, 10),
+        field("Qty", PX_FIELD_TYPE_SHORT_INT, 2),
+    ]
+}
+
+fn write(dir: &TempDir, rows: &[(i32, &str, i16)]) -> Table {
+    let rows = rows
+        .iter()
+        .map(|&(id, name, qty)| {
+            vec![
+                Value::Long(id),
+                Value::Alpha(name.to_string()),
+                Value::Short(qty),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let template = PxHeader::new_table("STOCK");
+    write_table(&dir.join("STOCK.DB"), &template, fields(), 1, &rows)
+}
+
+// The events of a run as JSON, and the state for the next run
+fn events(table: &Table, previous: &CdcState) -> (Vec<String>, CdcState) {
+    let mut events = vec![];
+    let state = capture(table, previous, |event: Event| {
+        events.push(serde_json::to_string(&event).unwrap());
+        Ok(())
+    })
+    .unwrap();
+    (events, state)
+}
+
+#[test]
+fn reports_inserts_updates_and_deletes() {
+    let dir = TempDir::new("cdc-changes");
+    let table = write(&dir, &[(1, "bolt", 10), (2, "nut", 20), (3, "washer", 30)]);
+    let (first, state) = events(&table, &CdcState::default());
+    assert_eq!(
+        first,
+        [
+            r#"{"op":"insert","key":{"Id":1},"record":{"Id":1,"Name":"bolt","Qty":10}}"#,
+            r#"{"op":"insert","key":{"Id":2},"record":{"Id":2,"Name":"nut","Qty":20}}"#,
+            r#"{"op":"insert","key":{"Id":3},"record":{"Id":3,"Name":"washer","Qty":30}}"#,
+        ]
+    );
+    assert_eq!(state.fields, ["Id", "Name", "Qty"]);
+    assert_eq!(state.records.len(), 3);
+
+    // Nothing changed, nothing to report
+    let (unchanged, state) = events(&table, &state);
+    assert!(unchanged.is_empty(), "{:?}", unchanged);
+
+    drop(table);
+    let table = write(
+        &dir,
+        &[(1, "bolt", 10), (3, "washer", 35), (4, "screw", 40)],
+    );
+    let (changes, state) = events(&table, &state);
+    assert_eq!(
+        changes,
+        [
+            r#"{"op":"update","key":{"Id":3},"fields":{"Qty":35}}"#,
+            r#"{"op":"insert","key":{"Id":4},"record":{"Id":4,"Name":"screw","Qty":40}}"#,
+            r#"{"op":"delete","key":{"Id":2}}"#,
+        ]
+    );
+
+    // The state survives being saved between runs
+    let saved = serde_json::to_string(&state).unwrap();
+    let state: CdcState = serde_json::from_str(&saved).unwrap();
+    assert!(events(&table, &state).0.is_empty());
+}
+
+#[test]
+fn fails_on_duplicate_keys_before_reporting_them() {
+    // Written as a heap table, which allows duplicates, then marked as keyed
+    let dir = TempDir::new("cdc-duplicates");
+    let rows = [(1, "first"), (2, "second"), (1, "again")]
+        .map(|(id, name)| vec![Value::Long(id), Value::Alpha(name.to_string()), Value::Null]);
+    let heap = write_table(
+        &dir.join("DUP.DB"),
+        &PxHeader::new_table("DUP"),
+        fields(),
+        0,
+        &rows,
+    );
+    let mut data = heap.raw().to_vec();
+    data[0x04] = PX_FILETYPE_DB_INDEXED;
+    data[0x23..0x25].copy_from_slice(&1i16.to_le_bytes());
+    let table = Table::from_bytes(data).unwrap();
+
+    let mut emitted = 0;
+    let result = capture(&table, &CdcState::default(), |_| {
+        emitted += 1;
+        Ok(())
+    });
+    assert!(result.unwrap_err().to_string().contains("Duplicate key"));
+    assert_eq!(emitted, 2);
+
+    assert!(capture(&heap, &CdcState::default(), |_| Ok(())).is_err());
+}
@@ -0,0 +1,160 @@
+// Sort orders of Alpha values. Values hold the bytes of the file as chars,
+// so accented letters are written as their code page bytes.
+
+#[path = "../src/testing.rs"]
+mod testing;
+
+use std::cmp::Ordering;
+
+use pxrs::expr::Filter;
+use pxrs::types::*;
+use pxrs::{Collation, Table, TableWriter};
+use testing::{field, write_table, TempDir};
+
+fn sorted(collation: Collation, words: &[&str]) -> Vec<String> {
+    let mut words = words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+    words.sort_by(|a, b| collation.compare(a, b));
+    words
+}
+
+#[test]
+fn ascii_compares_bytes() {
+    assert_eq!(
+        sorted(Collation::Ascii, &["b", "B", "a", "A", "\u{82}", "_"]),
+        ["A", "B", "_", "a", "b", "\u{82}"]
+    );
+}
+
+#[test]
+fn international_puts_accents_and_case_after_letters() {
+    // CP437: 0x82 é, 0x90 É, 0x87 ç
+    let e_acute = "\u{82}";
+    let words = ["f", "E", "e", e_acute, "\u{90}", "d", "\u{87}a", "cb", "Ca"];
+    assert_eq!(
+        sorted(Collation::International, &words),
+        ["Ca", "\u{87}a", "cb", "d", "e", "E", e_acute, "\u{90}", "f"]
+    );
+    assert_eq!(
+        Collation::International.compare("abc", "ABD"),
+        Ordering::Less
+    );
+    assert_eq!(
+        Collation::International.compare("r\u{82}sum\u{82}", "resume"),
+        Ordering::Greater
+    );
+    assert_eq!(
+        Collation::International.compare("resume", "resume"),
+        Ordering::Equal
+    );
+    // Letters follow every other character
+    assert_eq!(Collation::International.compare("9", "a"), Ordering::Less);
+    assert_eq!(Collation::International.compare("~", "a"), Ordering::Less);
+}
+
+#[test]
+fn nordic_letters_follow_z() {
+    // CP865: 0x92 Æ, 0x9d Ø, 0x8f Å
+    let words = ["\u{8f}", "\u{9d}", "\u{92}", "Z", "A", "O"];
+    let expected = ["A", "O", "Z", "\u{92}", "\u{9d}", "\u{8f}"];
+    assert_eq!(sorted(Collation::NorwegianDanish, &words), expected);
+    assert_eq!(sorted(Collation::NorwegianDanish40, &words), expected);
+
+    // CP437: 0x8f Å, 0x8e Ä, 0x99 Ö
+    let words = ["\u{99}", "\u{8e}", "\u{8f}", "z", "o", "a"];
+    assert_eq!(
+        sorted(Collation::SwedishFinnish, &words),
+        ["a", "o", "z", "\u{8f}", "\u{8e}", "\u{99}"]
+    );
+}
+
+#[test]
+fn spanish_puts_enie_after_n() {
+    // CP437: 0xa5 Ñ, 0xa4 ñ
+    let words = ["o", "\u{a4}a", "nz", "\u{a5}", "n"];
+    assert_eq!(
+        sorted(Collation::Spanish, &words),
+        ["n", "nz", "\u{a5}", "\u{a4}a", "o"]
+    );
+    // The international order takes Ñ for an N
+    assert_eq!(
+        Collation::International.compare("\u{a4}a", "nz"),
+        Ordering::Less
+    );
+}
+
+#[test]
+fn ansi_international_uses_windows_bytes() {
+    let words = ["f", "\u{e9}", "E", "e", "\u{c9}"];
+    assert_eq!(
+        sorted(Collation::AnsiInternational, &words),
+        ["e", "E", "\u{e9}", "\u{c9}", "f"]
+    );
+}
+
+#[test]
+fn keyed_tables_follow_their_sort_order() {
+    let dir = TempDir::new("collation-keyed");
+    let path = dir.join("NAMES.DB");
+    let mut template = PxHeader::new_table("NAMES");
+    template.sort_order = Collation::International.sort_order();
+    let fields = vec![
+        field("Name", PX_FIELD_TYPE_ALPHA, 10),
+        field("Rank", PX_FIELD_TYPE_SHORT_INT, 2),
+    ];
+
+    let names = sorted(
+        Collation::International,
+        &["b", "A", "a", "\u{82}t\u{82}", "ete", "Ete", "z", "Z"],
+    );
+    let rows = names
+        .iter()
+        .enumerate()
+        .map(|(rank, name)| vec![Value::Alpha(name.clone()), Value::Short(rank as i16)])
+        .collect::<Vec<_>>();
+    write_table(&path, &template, fields, 1, &rows);
+
+    // "y" follows "Z" in byte order but comes before it in the key order
+    let mut writer = TableWriter::append(&path).unwrap();
+    assert!(writer
+        .write_values(&[Value::Alpha("y".into()), Value::Null])
+        .is_err());
+    drop(writer);
+
+    let table = Table::open(&path).unwrap();
+    assert_eq!(table.collation(), Collation::International);
+    let found = table
+        .range(Value::Alpha("e".into())..Value::Alpha("z".into()))
+        .unwrap()
+        .map(|r| r.unwrap().fetch(0).unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(found, ["ete", "Ete", "\u{82}t\u{82}"]);
+}
+
+#[test]
+fn filters_compare_in_the_order_of_the_table() {
+    let dir = TempDir::new("collation-filter");
+    let mut template = PxHeader::new_table("WORDS");
+    template.sort_order = Collation::International.sort_order();
+    let fields = vec![field("Word", PX_FIELD_TYPE_ALPHA, 10)];
+    let rows = ["e", "\u{82}", "f", "E"]
+        .iter()
+        .map(|word| vec![Value::Alpha(word.to_string())])
+        .collect::<Vec<_>>();
+    let table = write_table(&dir.join("WORDS.DB"), &template, fields, 0, &rows);
+
+    let filter = Filter::new("Word < 'f'", table.fields()).unwrap();
+    let matching = |collation: Collation| {
+        table
+            .records()
+            .map(|r| r.unwrap())
+            .filter(|record| filter.matches(collation, record).unwrap())
+            .map(|record| record.fetch(0).unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    // é (0x82) follows "f" in byte order
+    assert_eq!(matching(table.collation()), ["e", "\u{82}", "E"]);
+    assert_eq!(matching(Collation::Ascii), ["e", "E"]);
+
+    let values = [Value::Alpha("\u{82}".into())];
+    assert!(filter.matches_values(table.collation(), &values).unwrap());
+}
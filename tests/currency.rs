@@ -0,0 +1,78 @@
+// Currency values written to Currency and BCD fields and read back, and
+// their text forms
+
+#[path = "../src/testing.rs"]
+mod testing;
+
+use pxrs::types::*;
+use pxrs::PxCurrency;
+use testing::{field, read_rows, write_table, TempDir};
+
+#[test]
+fn currency_and_bcd_values_round_trip() {
+    let dir = TempDir::new("values-currency");
+    let fields = vec![
+        field("Balance", PX_FIELD_TYPE_CURRENCY, 8),
+        field("Amount", PX_FIELD_TYPE_BCD, 2),
+        field("Exact", PX_FIELD_TYPE_BCD, 4),
+    ];
+    let units = [
+        0,
+        1,
+        -1,
+        5_000,
+        12_345_678,
+        -987_654_321,
+        99_999_999_999_999,
+    ];
+    let rows = units
+        .iter()
+        .map(|&units| {
+            let currency = PxCurrency::from_units(units);
+            vec![currency.into(), currency.into(), currency.into()]
+        })
+        .collect::<Vec<_>>();
+
+    let table = write_table(
+        &dir.join("MONEY.DB"),
+        &PxHeader::new_table("MONEY"),
+        fields,
+        0,
+        &rows,
+    );
+    let read = read_rows(&table);
+    for (row, &units) in read.iter().zip(&units) {
+        let currency = PxCurrency::from_units(units);
+        assert_eq!(row[0], Value::Currency(currency));
+        // Two decimals round half away from zero, four keep every digit
+        assert_eq!(row[1], Value::Bcd(format!("{:.2}", currency)));
+        assert_eq!(row[2], Value::Bcd(format!("{:.4}", currency)));
+        assert_eq!(PxCurrency::try_from(&row[2]).unwrap(), currency);
+    }
+    assert_eq!(read[3][1], Value::Bcd("0.50".to_string()));
+    assert_eq!(read[4][1], Value::Bcd("1234.57".to_string()));
+}
+
+#[test]
+fn currency_text_round_trips() {
+    for (text, units, display, alternate) in [
+        ("0", 0, "0.00", "0.00"),
+        ("1234.5", 12_345_000, "1234.50", "1,234.50"),
+        ("-0.0001", -1, "-0.0001", "(0.0001)"),
+        (
+            "(1,234,567.891)",
+            -12_345_678_910,
+            "-1234567.891",
+            "(1,234,567.891)",
+        ),
+        ("2.00005", 20_001, "2.0001", "2.0001"),
+    ] {
+        let currency: PxCurrency = text.parse().unwrap();
+        assert_eq!(currency, PxCurrency::from_units(units), "{}", text);
+        assert_eq!(currency.to_string(), display);
+        assert_eq!(format!("{:#}", currency), alternate);
+        assert_eq!(display.parse::<PxCurrency>().unwrap(), currency);
+    }
+    assert!("1.2.3".parse::<PxCurrency>().is_err());
+    assert!("99999999999999999999".parse::<PxCurrency>().is_err());
+}
@@ -0,0 +1,128 @@
+// Moving a cursor over the records of a table
+
+#[path = "../src/testing.rs"]
+mod testing;
+
+use pxrs::types::*;
+use pxrs::{Record, Table};
+use testing::{field, write_table, TempDir};
+
+const RECORDS: i32 = 300;
+
+// Even ids from 0, a few records per block
+fn keyed(dir: &TempDir) -> Table {
+    let fields = vec![
+        field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+        field("Name", PX_FIELD_TYPE_ALPHA, 200),
+    ];
+    let rows = (0..RECORDS)
+        .map(|n| vec![Value::Long(n * 2), Value::Alpha(format!("n{}", n))])
+        .collect::<Vec<_>>();
+    let path = dir.join("CURSOR.DB");
+    write_table(&path, &PxHeader::new_table("CURSOR"), fields, 1, &rows)
+}
+
+fn id(record: Option<Record>) -> Option<i32> {
+    match record?.get(0).unwrap() {
+        Value::Long(id) => Some(id),
+        value => panic!("unexpected {:?}", value),
+    }
+}
+
+#[test]
+fn moves_both_ways() {
+    let dir = TempDir::new("cursor-moves");
+    let table = keyed(&dir);
+    assert!(table.header().file_blocks > 10);
+    let mut cursor = table.cursor();
+
+    assert_eq!(id(cursor.current()), None);
+    assert_eq!(id(cursor.prev().unwrap()), None);
+    assert_eq!(id(cursor.next().unwrap()), Some(0));
+    assert_eq!(id(cursor.prev().unwrap()), None);
+    assert_eq!(id(cursor.next().unwrap()), Some(0));
+
+    let mut ids = vec![0];
+    while let Some(record) = cursor.next().unwrap() {
+        ids.push(id(Some(record)).unwrap());
+    }
+    assert_eq!(ids, (0..RECORDS).map(|n| n * 2).collect::<Vec<_>>());
+    assert_eq!(id(cursor.next().unwrap()), None);
+
+    let mut ids = vec![];
+    while let Some(record) = cursor.prev().unwrap() {
+        ids.push(id(Some(record)).unwrap());
+    }
+    assert_eq!(ids.len(), RECORDS as usize);
+    assert_eq!(ids.first(), Some(&((RECORDS - 1) * 2)));
+    assert_eq!(ids.last(), Some(&0));
+
+    assert_eq!(id(cursor.last().unwrap()), Some((RECORDS - 1) * 2));
+    assert_eq!(id(cursor.first().unwrap()), Some(0));
+}
+
+#[test]
+fn returns_to_bookmarks() {
+    let dir = TempDir::new("cursor-bookmarks");
+    let table = keyed(&dir);
+    let mut cursor = table.cursor();
+    assert_eq!(cursor.bookmark(), None);
+
+    for _ in 0..=100 {
+        cursor.next().unwrap();
+    }
+    let bookmark = cursor.bookmark().unwrap();
+    assert_eq!(id(cursor.current()), Some(200));
+
+    cursor.last().unwrap();
+    assert_eq!(id(Some(cursor.goto(bookmark).unwrap())), Some(200));
+    assert_eq!(id(cursor.next().unwrap()), Some(202));
+    assert_eq!(id(cursor.prev().unwrap()), Some(200));
+    assert_eq!(id(cursor.prev().unwrap()), Some(198));
+
+    let slots = table.block(bookmark.block).unwrap().num_recs_in_block;
+    let past = pxrs::Bookmark {
+        block: bookmark.block,
+        slot: slots,
+    };
+    assert!(cursor.goto(past).is_err());
+}
+
+#[test]
+fn seeks_keys_at_the_ends_and_between() {
+    let dir = TempDir::new("cursor-seek");
+    let table = keyed(&dir);
+    let mut cursor = table.cursor();
+    let mut seek = |key: i32| id(cursor.seek_key(&[Value::Long(key)]).unwrap());
+
+    assert_eq!(seek(-5), Some(0));
+    assert_eq!(seek(0), Some(0));
+    assert_eq!(seek(1), Some(2));
+    assert_eq!(seek(301), Some(302));
+    assert_eq!(seek(302), Some(302));
+    assert_eq!(seek((RECORDS - 1) * 2), Some((RECORDS - 1) * 2));
+    assert_eq!(seek(RECORDS * 2), None);
+    assert_eq!(id(cursor.prev().unwrap()), Some((RECORDS - 1) * 2));
+
+    assert!(cursor.seek_key(&[]).is_err());
+    assert!(cursor
+        .seek_key(&[Value::Long(1), Value::Alpha("x".into())])
+        .is_err());
+}
+
+#[test]
+fn seeking_does_not_trust_the_record_count() {
+    let dir = TempDir::new("cursor-count");
+    let path = keyed(&dir).path().unwrap().to_path_buf();
+    // Without the index the chain is walked from the first block
+    std::fs::remove_file(path.with_extension("PX")).unwrap();
+    let mut data = std::fs::read(&path).unwrap();
+    data[0x06..0x0a].copy_from_slice(&1u32.to_le_bytes());
+    let table = Table::from_bytes(data).unwrap();
+
+    let mut cursor = table.cursor();
+    let last = (RECORDS - 1) * 2;
+    let found = cursor.seek_key(&[Value::Long(last)]).unwrap();
+    assert_eq!(id(found), Some(last));
+    assert_eq!(id(cursor.seek_key(&[Value::Long(last + 1)]).unwrap()), None);
+}
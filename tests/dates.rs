@@ -0,0 +1,87 @@
+// The range of Paradox dates, and date, time and timestamp values written
+// to a table and read back
+
+#[path = "../src/testing.rs"]
+mod testing;
+
+use pxrs::types::*;
+use pxrs::{PxDate, PxTime, PxTimestamp};
+use testing::{field, read_rows, write_table, TempDir};
+
+#[test]
+fn dates_cover_the_paradox_range() {
+    assert_eq!(PxDate::MIN.ymd(), (100, 1, 1));
+    assert_eq!(PxDate::MAX.ymd(), (9999, 12, 31));
+    assert_eq!(PxDate::from_ymd(100, 1, 1), Some(PxDate::MIN));
+    assert_eq!(PxDate::from_ymd(99, 12, 31), None);
+    assert_eq!(PxDate::from_ymd(10000, 1, 1), None);
+    assert_eq!(PxDate::from_days(PxDate::MIN.days() - 1), None);
+    assert_eq!(PxDate::from_days(PxDate::MAX.days() + 1), None);
+    assert_eq!(
+        PxDate::from_ymd(2000, 2, 29).unwrap().to_string(),
+        "2000-02-29"
+    );
+    assert_eq!(PxDate::from_ymd(1900, 2, 29), None);
+
+    // Day 1 is 1/1/1, before the first date Paradox allows
+    assert!(PxDate::try_from(&Value::Date(1)).is_err());
+    assert!(PxDate::from_days(1).is_none());
+    assert_eq!(
+        PxDate::from_ymd(1970, 1, 1).unwrap().days(),
+        719_163,
+        "days count from 1/1/1 as day 1"
+    );
+}
+
+#[test]
+fn date_and_time_values_round_trip() {
+    let dir = TempDir::new("values-dates");
+    let fields = vec![
+        field("Date", PX_FIELD_TYPE_DATE, 4),
+        field("Time", PX_FIELD_TYPE_TIME, 4),
+        field("Stamp", PX_FIELD_TYPE_TIMESTAMP, 8),
+    ];
+    let last_ms = PxTime::from_hms_milli(23, 59, 59, 999).unwrap();
+    let samples = [
+        (PxDate::MIN, PxTime::MIDNIGHT),
+        (PxDate::from_ymd(1999, 12, 31).unwrap(), last_ms),
+        (
+            PxDate::from_ymd(2024, 2, 29).unwrap(),
+            "12:34:56.789".parse().unwrap(),
+        ),
+        (PxDate::MAX, last_ms),
+    ];
+    let rows = samples
+        .iter()
+        .map(|&(date, time)| {
+            vec![
+                date.into(),
+                time.into(),
+                PxTimestamp::new(date, time).into(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let table = write_table(
+        &dir.join("DATES.DB"),
+        &PxHeader::new_table("DATES"),
+        fields,
+        0,
+        &rows,
+    );
+    let read = read_rows(&table);
+    assert_eq!(read, rows);
+    for (row, &(date, time)) in read.iter().zip(&samples) {
+        assert_eq!(PxDate::try_from(&row[0]).unwrap(), date);
+        assert_eq!(PxTime::try_from(&row[1]).unwrap(), time);
+        assert_eq!(
+            PxTimestamp::try_from(&row[2]).unwrap(),
+            PxTimestamp::new(date, time)
+        );
+    }
+    assert_eq!(read[0][2].to_string(), "0100-01-01T00:00:00.000");
+    assert_eq!(read[3][2].to_string(), "9999-12-31T23:59:59.999");
+
+    assert!(PxTime::from_ms(86_400_000).is_none());
+    assert!(PxTimestamp::from_ms(-1.0).is_none());
+}
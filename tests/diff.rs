@@ -0,0 +1,84 @@
+// Records compared between two versions of a table
+
+#[path = "../src/testing.rs"]
+mod testing;
+
+use pxrs::diff::{diff_tables, MatchedBy, RecordChange};
+use pxrs::types::*;
+use pxrs::Table;
+use testing::{field, write_table, TempDir};
+
+fn table(dir: &TempDir, name: &str, keys: usize, rows: &[(i32, &str)]) -> Table {
+    let fields = vec![
+        field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+        field("Name", PX_FIELD_TYPE_ALPHA, 10),
+    ];
+    let rows = rows
+        .iter()
+        .map(|&(id, name)| vec![Value::Long(id), Value::Alpha(name.to_string())])
+        .collect::<Vec<_>>();
+    let path = dir.join(&format!("{}.DB", name));
+    write_table(&path, &PxHeader::new_table(name), fields, keys, &rows)
+}
+
+fn summary(changes: &[RecordChange]) -> Vec<String> {
+    changes
+        .iter()
+        .map(|change| change.to_string().lines().next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn keyed_tables_match_records_by_key() {
+    let dir = TempDir::new("diff-keyed");
+    let old = table(&dir, "OLD", 1, &[(1, "a"), (2, "b"), (3, "c")]);
+    let new = table(&dir, "NEW", 1, &[(1, "a"), (2, "B"), (4, "d")]);
+
+    let diff = diff_tables(&old, &new).unwrap();
+    assert_eq!(diff.matched_by, MatchedBy::PrimaryKey);
+    assert!(diff.warnings.is_empty());
+    assert_eq!(summary(&diff.changes), ["~ Id=2", "+ Id=4", "- Id=3"]);
+    assert_eq!(
+        serde_json::to_value(&diff.changes[0]).unwrap(),
+        serde_json::json!({
+            "change": "modified",
+            "position": 2,
+            "key": {"Id": 2},
+            "fields": [{"field": "Name", "old": "b", "new": "B"}],
+        })
+    );
+
+    assert!(diff_tables(&old, &old).unwrap().is_empty());
+}
+
+#[test]
+fn tables_keyed_on_one_side_compare_whole_records() {
+    let dir = TempDir::new("diff-one-key");
+    let keyed = table(&dir, "KEYED", 1, &[(1, "a"), (2, "b")]);
+    let heap = table(&dir, "HEAP", 0, &[(2, "b"), (1, "x")]);
+
+    let diff = diff_tables(&keyed, &heap).unwrap();
+    assert_eq!(diff.matched_by, MatchedBy::RowHash);
+    assert_eq!(
+        diff.warnings,
+        ["only the old table is keyed, comparing whole records"]
+    );
+    assert_eq!(summary(&diff.changes), ["+ record 2", "- record 1"]);
+
+    let diff = diff_tables(&heap, &keyed).unwrap();
+    assert_eq!(
+        diff.warnings,
+        ["only the new table is keyed, comparing whole records"]
+    );
+}
+
+#[test]
+fn unkeyed_tables_match_duplicates_once() {
+    let dir = TempDir::new("diff-duplicates");
+    let old = table(&dir, "OLD", 0, &[(1, "a"), (1, "a"), (2, "b")]);
+    let new = table(&dir, "NEW", 0, &[(1, "a"), (2, "b"), (2, "b")]);
+
+    let diff = diff_tables(&old, &new).unwrap();
+    assert_eq!(diff.matched_by, MatchedBy::RowHash);
+    assert_eq!(summary(&diff.changes), ["+ record 3", "- record 2"]);
+}
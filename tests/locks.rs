@@ -0,0 +1,176 @@
+// Lock files Paradox keeps next to shared tables
+
+#[path = "../src/testing.rs"]
+mod testing;
+
+use std::fs;
+
+use pxrs::lock::{set_lock_records, LockFileKind, LockType, Locks, TableLock};
+use pxrs::Error;
+use testing::TempDir;
+
+// A <TABLE>.LCK with lock records of (type code, session, record, user)
+fn table_lck(locks: &[(u16, u16, u32, &str)]) -> Vec<u8> {
+    let mut data = vec![0; 32];
+    data[..2].copy_from_slice(&(locks.len() as u16).to_le_bytes());
+    for &(code, session, record, user) in locks {
+        let mut lock = vec![0; 32];
+        lock[..2].copy_from_slice(&code.to_le_bytes());
+        lock[2..4].copy_from_slice(&session.to_le_bytes());
+        lock[4..8].copy_from_slice(&record.to_le_bytes());
+        lock[0x10..0x10 + user.len()].copy_from_slice(user.as_bytes());
+        data.extend(lock);
+    }
+    data
+}
+
+fn is_locked(result: pxrs::Result<()>) -> bool {
+    matches!(result, Err(Error::Locked { .. }))
+}
+
+#[test]
+fn finds_the_lock_files_of_a_table() {
+    let dir = TempDir::new("locks-files");
+    let table = dir.join("PARTS.DB");
+    let locks = Locks::for_table(&table).unwrap();
+    assert!(locks.is_empty());
+    assert!(locks.check_writable().is_ok());
+
+    fs::write(dir.join("PARADOX.LCK"), b"\x01\x00\x00alice\x00\x00").unwrap();
+    fs::write(
+        dir.join("PDOXUSRS.LCK"),
+        b"\x02\x00bob\x00\x00\\\\SERVER\\DATA\\PDOXUSRS.NET\x00alice\x00",
+    )
+    .unwrap();
+    fs::write(dir.join("OTHER.LCK"), table_lck(&[(1, 1, 0, "eve")])).unwrap();
+
+    let locks = Locks::for_table(&table).unwrap();
+    let kinds = locks.files.iter().map(|f| f.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [LockFileKind::DirectoryUsers, LockFileKind::Directory]
+    );
+    assert_eq!(
+        locks.files[0].net_file.as_deref(),
+        Some("\\\\SERVER\\DATA\\PDOXUSRS.NET")
+    );
+    assert_eq!(locks.users(), ["bob", "alice"]);
+    // Only the lock file of the table itself keeps it from being written
+    assert!(locks.table_lock().is_none());
+    assert!(locks.check_writable().is_ok());
+}
+
+#[test]
+fn checks_the_locks_of_other_sessions() {
+    set_lock_records(true);
+    let dir = TempDir::new("locks-records");
+    let table = dir.join("PARTS.DB");
+    fs::write(
+        dir.join("PARTS.LCK"),
+        table_lck(&[(4, 3, 0, "carol"), (5, 3, 7, "carol"), (3, 9, 0, "dave")]),
+    )
+    .unwrap();
+
+    let locks = Locks::for_table(&table).unwrap();
+    let file = locks.table_lock().unwrap();
+    assert_eq!(file.users, ["carol", "dave"]);
+    let described = file.locks.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        described,
+        [
+            "prevent full lock by carol (session 3)",
+            "record lock on record 7 by carol (session 3)",
+            "prevent write lock by dave (session 9)",
+        ]
+    );
+
+    assert!(locks.check_lock(LockType::Record).is_ok());
+    assert!(locks.check_lock(LockType::PreventFull).is_ok());
+    assert!(locks.check_lock(LockType::PreventWrite).is_ok());
+    assert!(is_locked(locks.check_lock(LockType::Write)));
+    let error = locks.check_writable().unwrap_err();
+    assert!(
+        error.to_string().contains("prevent full lock by carol"),
+        "{}",
+        error
+    );
+
+    assert!(TableLock::acquire(&table, LockType::Write).is_err());
+    let lock = TableLock::acquire(&table, LockType::PreventWrite).unwrap();
+    assert_eq!(Locks::for_table(&table).unwrap().files[0].locks.len(), 4);
+    drop(lock);
+    assert_eq!(Locks::for_table(&table).unwrap().files[0].locks.len(), 3);
+}
+
+#[test]
+fn unreadable_table_locks_lock_the_table() {
+    let dir = TempDir::new("locks-unknown");
+    let table = dir.join("PARTS.DB");
+    fs::write(dir.join("PARTS.LCK"), b"\x07\x00\x01frank\x00\x00garbage").unwrap();
+
+    let locks = Locks::for_table(&table).unwrap();
+    let file = locks.table_lock().unwrap();
+    assert_eq!(file.locks[0].lock_type, LockType::Unknown(0));
+    assert_eq!(file.users, ["frank", "garbage"]);
+    assert!(is_locked(locks.check_lock(LockType::Record)));
+    assert!(is_locked(locks.check_writable()));
+}
+
+#[test]
+fn own_locks_are_recorded_and_removed() {
+    set_lock_records(true);
+    let dir = TempDir::new("locks-own");
+    let table = dir.join("PARTS.DB");
+    let lock = TableLock::acquire(&table, LockType::Write).unwrap();
+    let locks = Locks::for_table(&table).unwrap();
+    assert_eq!(
+        locks.table_lock().unwrap().locks[0].lock_type,
+        LockType::Write
+    );
+    assert!(is_locked(locks.check_lock(LockType::PreventWrite)));
+    assert!(locks.check_lock(LockType::Record).is_ok());
+
+    drop(lock);
+    assert!(!dir.join("PARTS.LCK").exists());
+    assert!(Locks::for_table(&table).unwrap().is_empty());
+}
+
+#[test]
+fn own_locks_take_a_free_session() {
+    set_lock_records(true);
+    let dir = TempDir::new("locks-session");
+    let table = dir.join("PARTS.DB");
+    let pid = std::process::id() as u16;
+    fs::write(dir.join("PARTS.LCK"), table_lck(&[(4, pid, 0, "grace")])).unwrap();
+
+    let first = TableLock::acquire(&table, LockType::PreventWrite).unwrap();
+    let second = TableLock::acquire(&table, LockType::PreventWrite).unwrap();
+    let locks = Locks::for_table(&table).unwrap();
+    let sessions = locks.files[0]
+        .locks
+        .iter()
+        .map(|l| l.session)
+        .collect::<Vec<_>>();
+    assert_eq!(sessions, [pid, pid.wrapping_add(1), pid.wrapping_add(2)]);
+
+    // Only the own records go, and no temporary file is left behind
+    drop(first);
+    drop(second);
+    let locks = Locks::for_table(&table).unwrap();
+    assert_eq!(locks.files[0].users, ["grace"]);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn readers_only_warn_about_lock_files_they_cannot_read() {
+    let dir = TempDir::new("locks-unreadable");
+    let table = dir.join("PARTS.DB");
+    fs::create_dir(dir.join("PDOXUSRS.NET")).unwrap();
+    fs::write(dir.join("PARADOX.LCK"), b"alice").unwrap();
+
+    let (locks, errors) = Locks::for_reading(&table);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(locks.files.len(), 1);
+    assert_eq!(locks.files[0].kind, LockFileKind::Directory);
+    assert!(Locks::for_table(&table).is_err());
+}
@@ -0,0 +1,179 @@
+// Queries over small tables written for the test
+
+#[path = "../src/testing.rs"]
+mod testing;
+
+use pxrs::sql::Query;
+use pxrs::types::*;
+use pxrs::PxCurrency;
+use testing::{field, write_table, TempDir};
+
+// CUSTOMER(Id, Name, City, Balance) keyed on Id and ORDERS(OrderNo, CustId,
+// Total) keyed on OrderNo
+fn tables(name: &str) -> TempDir {
+    let dir = TempDir::new(name);
+
+    let customers = [
+        (1, "Ann", "Oslo", 1_000_000),
+        (2, "Bob", "Bergen", -250_000),
+        (3, "Cid", "Oslo", 0),
+        (4, "Dee", "", 125_000),
+    ]
+    .map(|(id, name, city, balance)| {
+        let city = match city {
+            "" => Value::Null,
+            city => Value::Alpha(city.to_string()),
+        };
+        vec![
+            Value::Long(id),
+            Value::Alpha(name.to_string()),
+            city,
+            PxCurrency::from_units(balance).into(),
+        ]
+    });
+    let fields = vec![
+        field("Id", PX_FIELD_TYPE_LONG_INT, 4),
+        field("Name", PX_FIELD_TYPE_ALPHA, 10),
+        field("City", PX_FIELD_TYPE_ALPHA, 10),
+        field("Balance", PX_FIELD_TYPE_CURRENCY, 8),
+    ];
+    let template = PxHeader::new_table("CUSTOMER");
+    write_table(&dir.join("CUSTOMER.DB"), &template, fields, 1, &customers);
+
+    let orders = [(10, 1, 5.5), (11, 1, 120.0), (12, 2, 30.25), (13, 9, 1.0)].map(
+        |(order, customer, total)| {
+            vec![
+                Value::Long(order),
+                Value::Long(customer),
+                Value::Number(total),
+            ]
+        },
+    );
+    let fields = vec![
+        field("OrderNo", PX_FIELD_TYPE_LONG_INT, 4),
+        field("CustId", PX_FIELD_TYPE_LONG_INT, 4),
+        field("Total", PX_FIELD_TYPE_NUMBER, 8),
+    ];
+    let template = PxHeader::new_table("ORDERS");
+    write_table(&dir.join("ORDERS.DB"), &template, fields, 1, &orders);
+
+    dir
+}
+
+fn run(dir: &TempDir, query: &str) -> (Vec<String>, Vec<Vec<String>>) {
+    let result = Query::parse(query).unwrap().run(dir.path()).unwrap();
+    assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+    let rows = result
+        .rows
+        .iter()
+        .map(|row| row.iter().map(Value::to_string).collect())
+        .collect();
+    (result.columns, rows)
+}
+
+fn rows(dir: &TempDir, query: &str) -> Vec<Vec<String>> {
+    run(dir, query).1
+}
+
+#[test]
+fn aggregates_group_rows() {
+    let dir = tables("sql-aggregates");
+    let (columns, grouped) = run(
+        &dir,
+        "SELECT City, COUNT(*), SUM(Balance), MIN(Name), MAX(Name), AVG(Balance) \
+         FROM customer GROUP BY City ORDER BY City",
+    );
+    assert_eq!(
+        columns,
+        [
+            "City",
+            "COUNT(*)",
+            "SUM(Balance)",
+            "MIN(Name)",
+            "MAX(Name)",
+            "AVG(Balance)"
+        ]
+    );
+    // Blank values group together and sort first
+    assert_eq!(
+        grouped,
+        [
+            ["", "1", "12.50", "Dee", "Dee", "12.5"],
+            ["Bergen", "1", "-25.00", "Bob", "Bob", "-25"],
+            ["Oslo", "2", "100.00", "Ann", "Cid", "50"],
+        ]
+    );
+
+    assert_eq!(
+        rows(
+            &dir,
+            "SELECT COUNT(*), COUNT(City), SUM(Balance), MIN(Id), MAX(Id) FROM customer"
+        ),
+        [["4", "3", "87.50", "1", "4"]]
+    );
+    assert_eq!(
+        rows(
+            &dir,
+            "SELECT COUNT(*), SUM(Total) FROM orders WHERE Total > 1000"
+        ),
+        [["0", ""]]
+    );
+}
+
+#[test]
+fn joins_match_rows_of_both_tables() {
+    let dir = tables("sql-joins");
+    assert_eq!(
+        rows(
+            &dir,
+            "SELECT c.Name, o.Total FROM orders o JOIN customer c ON o.CustId = c.Id \
+             ORDER BY o.Total"
+        ),
+        [["Ann", "5.5"], ["Bob", "30.25"], ["Ann", "120"]]
+    );
+    // Customers without orders get blank order fields
+    assert_eq!(
+        rows(
+            &dir,
+            "SELECT c.Id, o.OrderNo FROM customer c LEFT JOIN orders o \
+             ON o.CustId = c.Id AND o.Total > 10 ORDER BY c.Id DESC"
+        ),
+        [["4", ""], ["3", ""], ["2", "12"], ["1", "11"]]
+    );
+    assert_eq!(
+        rows(
+            &dir,
+            "SELECT c.Name, COUNT(*), SUM(o.Total) FROM orders o JOIN customer c \
+             ON c.Id = o.CustId GROUP BY c.Name ORDER BY c.Name"
+        ),
+        [["Ann", "2", "125.5"], ["Bob", "1", "30.25"]]
+    );
+}
+
+#[test]
+fn filters_distinct_and_limits() {
+    let dir = tables("sql-filters");
+    assert_eq!(
+        rows(
+            &dir,
+            "SELECT Id, Name FROM customer WHERE Id > 1 AND Id <= 3"
+        ),
+        [["2", "Bob"], ["3", "Cid"]]
+    );
+    assert_eq!(
+        rows(
+            &dir,
+            "SELECT DISTINCT City FROM customer WHERE City > 'A' ORDER BY City"
+        ),
+        [["Bergen"], ["Oslo"]]
+    );
+    assert_eq!(
+        rows(&dir, "SELECT Name FROM customer ORDER BY Name DESC LIMIT 2"),
+        [["Dee"], ["Cid"]]
+    );
+    assert!(Query::parse("SELECT FROM customer").is_err());
+    assert!(Query::parse("SELECT Nope FROM customer")
+        .unwrap()
+        .run(dir.path())
+        .is_err());
+}
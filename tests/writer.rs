@@ -0,0 +1,132 @@
+// Tables written with `TableWriter` read back with `Table`
+
+#[path = "../src/testing.rs"]
+mod testing;
+
+use pxrs::types::*;
+use pxrs::{PxCurrency, PxDate, PxTime, PxTimestamp, Table, TableWriter};
+use testing::{field, read_rows, TempDir};
+
+fn every_type() -> Vec<PxFieldInfo> {
+    vec![
+        field("Code", PX_FIELD_TYPE_ALPHA, 8),
+        field("Id", PX_FIELD_TYPE_INCREMENTAL, 4),
+        field("Born", PX_FIELD_TYPE_DATE, 4),
+        field("Count", PX_FIELD_TYPE_SHORT_INT, 2),
+        field("Total", PX_FIELD_TYPE_LONG_INT, 4),
+        field("Price", PX_FIELD_TYPE_CURRENCY, 8),
+        field("Ratio", PX_FIELD_TYPE_NUMBER, 8),
+        field("Active", PX_FIELD_TYPE_LOGICAL, 1),
+        field("Opens", PX_FIELD_TYPE_TIME, 4),
+        field("Changed", PX_FIELD_TYPE_TIMESTAMP, 8),
+        field("Exact", PX_FIELD_TYPE_BCD, 2),
+        field("Raw", PX_FIELD_TYPE_BYTES, 4),
+        field("Notes", PX_FIELD_TYPE_MEMO_BLOB, 30),
+    ]
+}
+
+fn row(i: i32) -> Vec<Value> {
+    let date = PxDate::from_ymd(1990 + i % 30, 1 + (i % 12) as u32, 1 + (i % 28) as u32).unwrap();
+    let time = PxTime::from_ms(i * 1000).unwrap();
+    vec![
+        Value::Alpha(format!("K{:04}", i)),
+        Value::AutoInc(i + 1),
+        date.into(),
+        Value::Short(-(i as i16)),
+        Value::Long(i * 100_000),
+        PxCurrency::from_units(i as i64 * 12_345).into(),
+        Value::Number(i as f64 / 8.0),
+        Value::Logical(i % 2 == 0),
+        time.into(),
+        PxTimestamp::new(date, time).into(),
+        Value::Bcd(format!("{}.{:02}", i, i % 100)),
+        Value::Bytes(vec![i as u8, 0, 0xff, 1]),
+        Value::Memo(format!("note {}", i)),
+    ]
+}
+
+fn nulls() -> Vec<Value> {
+    let mut values = vec![Value::Null; every_type().len()];
+    values[0] = Value::Alpha("ZNULL".to_string());
+    values
+}
+
+#[test]
+fn every_field_type_reads_back() {
+    let dir = TempDir::new("writer-types");
+    let path = dir.join("TYPES.DB");
+    let mut writer =
+        TableWriter::create_keyed(&path, &PxHeader::new_table("TYPES"), every_type(), 1).unwrap();
+    for i in 0..250 {
+        writer.write_values(&row(i)).unwrap();
+    }
+    writer.write_values(&nulls()).unwrap();
+    // Lock records for Paradox are only written when turned on
+    assert!(!dir.join("TYPES.LCK").exists());
+    let header = writer.finish().unwrap();
+    assert_eq!(header.num_records, 251);
+    assert_eq!(header.auto_inc, 251);
+
+    let table = Table::open(&path).unwrap();
+    assert!(table.warnings().is_empty(), "{:?}", table.warnings());
+    assert_eq!(table.fields(), every_type().as_slice());
+    assert_eq!(table.header().primary_key_fields, 1);
+    let rows = read_rows(&table);
+    let mut expected = (0..250).map(row).collect::<Vec<_>>();
+    let mut blank = nulls();
+    // A blank AutoInc field gets the next number
+    blank[1] = Value::AutoInc(251);
+    expected.push(blank);
+    assert_eq!(rows, expected);
+
+    // The primary index finds records by key
+    let found = table
+        .range(Value::Alpha("K0123".to_string())..)
+        .unwrap()
+        .map(|r| r.unwrap().fetch(1).unwrap())
+        .take(2)
+        .collect::<Vec<_>>();
+    assert_eq!(found, [Value::AutoInc(124), Value::AutoInc(125)]);
+}
+
+#[test]
+fn keys_must_be_unique_and_in_order() {
+    let dir = TempDir::new("writer-keys");
+    let path = dir.join("KEYS.DB");
+    let mut writer =
+        TableWriter::create_keyed(&path, &PxHeader::new_table("KEYS"), every_type(), 1).unwrap();
+    writer.write_values(&row(5)).unwrap();
+    assert!(writer.write_values(&row(5)).is_err());
+    assert!(writer.write_values(&row(4)).is_err());
+    writer.write_values(&row(6)).unwrap();
+    writer.finish().unwrap();
+
+    let table = Table::open(&path).unwrap();
+    assert_eq!(table.header().num_records, 2);
+
+    let mut wrong = row(7);
+    wrong.pop();
+    let mut writer = TableWriter::append(&path).unwrap();
+    assert!(writer.write_values(&wrong).is_err());
+    let mut too_long = row(7);
+    too_long[12] = Value::Memo("x".repeat(100));
+    assert!(writer.write_values(&too_long).is_err());
+    writer.write_values(&row(7)).unwrap();
+    writer.finish().unwrap();
+
+    let table = Table::open(dir.path().join("KEYS.DB")).unwrap();
+    let codes = table
+        .records()
+        .map(|r| r.unwrap().fetch(0).unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(codes, ["K0005", "K0006", "K0007"]);
+}
+
+#[test]
+fn blob_fields_cannot_be_keys() {
+    let dir = TempDir::new("writer-blob-key");
+    let fields = vec![field("Notes", PX_FIELD_TYPE_MEMO_BLOB, 30)];
+    let result =
+        TableWriter::create_keyed(dir.join("BLOB.DB"), &PxHeader::new_table("B"), fields, 1);
+    assert!(result.is_err());
+}